@@ -0,0 +1,272 @@
+use crate::error::Error;
+use crate::kmeans::Kmeans;
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone)]
+pub struct ClusteringFeature<T: Float + One + Zero> {
+    pub n: usize,
+    pub linear_sum: Array1<T>,
+    pub squared_sum: T,
+}
+
+impl<T: Float + One + Zero> ClusteringFeature<T> {
+    pub fn from_point(row: &ArrayView1<T>) -> ClusteringFeature<T> {
+        ClusteringFeature {
+            n: 1,
+            linear_sum: Array1::from_vec(row.to_vec()),
+            squared_sum: row.iter().fold(T::zero(), |acc, x| acc + *x * *x),
+        }
+    }
+
+    pub fn centroid(&self) -> Array1<T> {
+        let n = T::from(self.n).expect("T::from(usize)");
+        self.linear_sum.mapv(|x| x / n)
+    }
+
+    pub fn radius(&self) -> T {
+        let n = T::from(self.n).expect("T::from(usize)");
+        let centroid_sq = self.linear_sum.iter().fold(T::zero(), |acc, x| acc + (*x / n) * (*x / n));
+        (self.squared_sum / n - centroid_sq).max(T::zero()).sqrt()
+    }
+
+    pub fn absorb(&mut self, other: &ClusteringFeature<T>) {
+        self.n += other.n;
+        self.linear_sum.zip_mut_with(&other.linear_sum, |a, b| *a = *a + *b);
+        self.squared_sum = self.squared_sum + other.squared_sum;
+    }
+
+    fn merged(&self, other: &ClusteringFeature<T>) -> ClusteringFeature<T> {
+        let mut merged = self.clone();
+        merged.absorb(other);
+        merged
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CfEntry<T: Float + One + Zero> {
+    cf: ClusteringFeature<T>,
+    child: Option<Box<CfNode<T>>>,
+}
+
+#[derive(Debug, Clone)]
+struct CfNode<T: Float + One + Zero> {
+    leaf: bool,
+    entries: Vec<CfEntry<T>>,
+}
+
+impl<T: Float + One + Zero> CfNode<T> {
+    fn insert(&mut self, cf: ClusteringFeature<T>, threshold: T, branching_factor: usize) -> Option<CfNode<T>> {
+        let closest = closest_entry(&self.entries, &cf.centroid());
+        if self.leaf {
+            match closest {
+                Some(i) if self.entries[i].cf.merged(&cf).radius() <= threshold => self.entries[i].cf.absorb(&cf),
+                _ => self.entries.push(CfEntry { cf, child: None }),
+            }
+        } else {
+            let entry = &mut self.entries[closest.expect("non-empty internal node")];
+            let child = entry.child.as_mut().expect("internal entry has child");
+            let split = child.insert(cf.clone(), threshold, branching_factor);
+            match split {
+                None => entry.cf.absorb(&cf),
+                Some(_) => entry.cf = child.summary(),
+            }
+            if let Some(sibling) = split {
+                self.entries.push(CfEntry {
+                    cf: sibling.summary(),
+                    child: Some(Box::new(sibling)),
+                });
+            }
+        }
+
+        if self.entries.len() > branching_factor {
+            Some(self.split())
+        } else {
+            None
+        }
+    }
+
+    fn split(&mut self) -> CfNode<T> {
+        let centroids = self.entries.iter().map(|e| e.cf.centroid()).collect::<Vec<Array1<T>>>();
+        let mut farthest = (0, 1, T::neg_infinity());
+        for i in 0..centroids.len() {
+            for j in (i + 1)..centroids.len() {
+                let d = squared_euclidean(centroids[i].as_slice().unwrap(), centroids[j].as_slice().unwrap());
+                if d > farthest.2 {
+                    farthest = (i, j, d);
+                }
+            }
+        }
+        let (a, b) = (&centroids[farthest.0], &centroids[farthest.1]);
+        let mut sibling = CfNode { leaf: self.leaf, entries: Vec::new() };
+        let mut kept = Vec::new();
+        for (i, entry) in self.entries.drain(..).enumerate() {
+            let to_a = squared_euclidean(centroids[i].as_slice().unwrap(), a.as_slice().unwrap());
+            let to_b = squared_euclidean(centroids[i].as_slice().unwrap(), b.as_slice().unwrap());
+            if i == farthest.0 || (i != farthest.1 && to_a <= to_b) {
+                kept.push(entry);
+            } else {
+                sibling.entries.push(entry);
+            }
+        }
+        self.entries = kept;
+        sibling
+    }
+
+    fn summary(&self) -> ClusteringFeature<T> {
+        let mut entries = self.entries.iter();
+        let mut cf = entries.next().expect("non-empty node").cf.clone();
+        for entry in entries {
+            cf.absorb(&entry.cf);
+        }
+        cf
+    }
+
+    fn collect_leaves(&self, out: &mut Vec<ClusteringFeature<T>>) {
+        for entry in self.entries.iter() {
+            match entry.child {
+                Some(ref child) => child.collect_leaves(out),
+                None => out.push(entry.cf.clone()),
+            }
+        }
+    }
+}
+
+fn closest_entry<T: Float + One + Zero>(entries: &[CfEntry<T>], point: &Array1<T>) -> Option<usize> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (i, squared_euclidean(e.cf.centroid().as_slice().unwrap(), point.as_slice().unwrap())))
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from centroid"))
+        .map(|(i, _)| i)
+}
+
+#[derive(Debug)]
+pub struct Birch<T: Float + One + Zero> {
+    pub threshold: T,
+    pub branching_factor: usize,
+    root: CfNode<T>,
+}
+
+impl<T: Float + One + Zero> Birch<T> {
+    pub fn new(data: &Array2<T>, threshold: T, branching_factor: usize) -> Birch<T> {
+        assert!(branching_factor > 1, "branching_factor must be at least 2");
+        let mut model = Birch {
+            threshold,
+            branching_factor,
+            root: CfNode { leaf: true, entries: Vec::new() },
+        };
+        model.partial_fit(data);
+        model
+    }
+
+    pub fn partial_fit(&mut self, data: &Array2<T>) {
+        if !data.is_standard_layout() {
+            return self.partial_fit(&standard_layout(data.view()));
+        }
+        for row in data.outer_iter() {
+            if let Some(sibling) = self.root.insert(ClusteringFeature::from_point(&row), self.threshold, self.branching_factor) {
+                let old_root = std::mem::replace(
+                    &mut self.root,
+                    CfNode {
+                        leaf: false,
+                        entries: Vec::with_capacity(2),
+                    },
+                );
+                for node in [old_root, sibling] {
+                    self.root.entries.push(CfEntry {
+                        cf: node.summary(),
+                        child: Some(Box::new(node)),
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn subclusters(&self) -> Vec<ClusteringFeature<T>> {
+        let mut leaves = Vec::new();
+        self.root.collect_leaves(&mut leaves);
+        leaves
+    }
+
+    pub fn centroids(&self) -> Array2<T> {
+        let subclusters = self.subclusters();
+        let dims = subclusters.first().map_or(0, |cf| cf.linear_sum.len());
+        let flat = subclusters.iter().flat_map(|cf| cf.centroid().to_vec()).collect::<Vec<T>>();
+        Array2::from_shape_vec((subclusters.len(), dims), flat).expect("centroid matrix shape")
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()));
+        }
+        let centroids = self.centroids();
+        data.outer_iter()
+            .map(|row| {
+                centroids
+                    .outer_iter()
+                    .enumerate()
+                    .map(|(i, centroid)| (i, squared_euclidean(row.as_slice().unwrap(), centroid.as_slice().unwrap())))
+                    .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from centroid"))
+                    .expect("min distance from centroid")
+                    .0
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Birch<T> {
+    pub fn cluster(&self, nclust: usize, iterations: usize, nseeds: usize) -> Result<Kmeans<T>, Error> {
+        Kmeans::new(&self.centroids(), nclust, iterations, nseeds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use ndarray::s;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((10, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, 1.2, 2.0, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0, -2.0, 3.2]).unwrap()
+    }
+
+    #[test]
+    fn test_subclusters() {
+        let model = Birch::new(&data(), 0.05, 3);
+        let subclusters = model.subclusters();
+        assert!(subclusters.len() > 3);
+        assert_eq!(subclusters.iter().map(|cf| cf.n).sum::<usize>(), 10);
+        assert!(subclusters.iter().all(|cf| cf.radius() <= 0.05));
+    }
+
+    #[test]
+    fn test_global_clustering() {
+        let model = Birch::new(&data(), 0.5, 3);
+        let kmeans = model.cluster(2, 20, 5).unwrap();
+        let clustering = dbg!(kmeans.predict(&data()));
+        assert!(clustering.iter().take(5).all_equal());
+        assert!(clustering.iter().skip(5).all_equal());
+        assert!(clustering[0] != clustering[5]);
+    }
+
+    #[test]
+    fn test_partial_fit() {
+        let mut model = Birch::new(&data().slice(s![..5, ..]).to_owned(), 0.5, 3);
+        assert_eq!(model.subclusters().len(), 1);
+        model.partial_fit(&data().slice(s![5.., ..]).to_owned());
+        assert_eq!(model.subclusters().len(), 2);
+        assert_eq!(model.predict(&data()), vec![0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+
+        let fortran = Array2::from_shape_vec((10, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(model.predict(&fortran), model.predict(&data()));
+        assert_eq!(Birch::new(&fortran, 0.5, 3).centroids(), Birch::new(&data(), 0.5, 3).centroids());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::kmeans::Kmeans;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitCriterion {
+    Largest,
+    HighestWithinss,
+}
+
+#[derive(Debug)]
+pub struct BisectingKmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+    pub withinss: Vec<T>,
+    pub splits: Vec<(usize, usize)>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> BisectingKmeans<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, criterion: SplitCriterion, iterations: usize, nseeds: usize) -> BisectingKmeans<T> {
+        let all = (0..data.rows()).collect::<Vec<usize>>();
+        let center = data.mean_axis(Axis(0));
+        let mut model = BisectingKmeans {
+            withinss: vec![withinss(data, &all, &center)],
+            centers: vec![center],
+            clusters: vec![0; data.rows()],
+            splits: Vec::with_capacity(nclust.saturating_sub(1)),
+        };
+        let mut splittable = vec![data.rows() >= 2];
+
+        while model.centers.len() < nclust {
+            let sizes = model.clusters.iter().fold(vec![0; model.centers.len()], |mut sizes, c| {
+                sizes[*c] += 1;
+                sizes
+            });
+            let candidate = (0..model.centers.len()).filter(|c| splittable[*c]).max_by(|a, b| match criterion {
+                SplitCriterion::Largest => sizes[*a].cmp(&sizes[*b]),
+                SplitCriterion::HighestWithinss => model.withinss[*a].partial_cmp(&model.withinss[*b]).expect("PartialOrd withinss"),
+            });
+            let parent = match candidate {
+                Some(parent) => parent,
+                None => break,
+            };
+
+            let members = (0..data.rows()).filter(|row_idx| model.clusters[*row_idx] == parent).collect::<Vec<usize>>();
+            let split = Kmeans::new(&data.select(Axis(0), &members), 2, iterations, nseeds).expect("two-way split of a splittable cluster");
+            let mut halves = [Vec::new(), Vec::new()];
+            for (member, c) in members.iter().zip(split.clusters.iter()) {
+                halves[*c].push(*member);
+            }
+            if halves.iter().any(|half| half.is_empty()) {
+                splittable[parent] = false;
+                continue;
+            }
+
+            let child = model.centers.len();
+            for member in halves[1].iter() {
+                model.clusters[*member] = child;
+            }
+            let centers = halves.iter().map(|half| data.select(Axis(0), half).mean_axis(Axis(0))).collect::<Vec<Array1<T>>>();
+            model.withinss[parent] = withinss(data, &halves[0], &centers[0]);
+            model.withinss.push(withinss(data, &halves[1], &centers[1]));
+            model.centers[parent] = centers[0].clone();
+            model.centers.push(centers[1].clone());
+            splittable[parent] = halves[0].len() >= 2;
+            splittable.push(halves[1].len() >= 2);
+            model.splits.push((parent, child));
+        }
+        model
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        data.outer_iter().map(|row| Kmeans::predict_with_centers(&self.centers, &row)).collect::<Vec<usize>>()
+    }
+}
+
+fn withinss<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, members: &[usize], center: &Array1<T>) -> T {
+    members
+        .iter()
+        .map(|row_idx| {
+            let diff = &data.row(*row_idx) - center;
+            (&diff * &diff).sum()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_bisecting() {
+        let data = Array2::from_shape_vec((9, 1), vec![0.0, 0.1, 0.2, 5.0, 5.1, 5.2, 20.0, 20.1, 20.2]).unwrap();
+        for criterion in [SplitCriterion::Largest, SplitCriterion::HighestWithinss].iter() {
+            let model = BisectingKmeans::new(&data, 3, *criterion, 20, 5);
+            assert_eq!(model.centers.len(), 3);
+            assert_eq!(model.splits.len(), 2);
+            assert_eq!(model.splits[0], (0, 1));
+
+            let clustering = dbg!(&model.clusters);
+            assert!(clustering.chunks(3).all(|chunk| chunk.iter().all_equal()));
+            assert!(clustering.iter().unique().count() == 3);
+            assert_eq!(&model.predict(&data), clustering);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::dbscan::{core_indices, expand_clusters, kdtree_init, region_query, Dbscan, SpatialIndex};
+use crate::kmeans::Kmeans;
+use crate::metric::Euclidean;
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct Canopy<T: Float + One + Zero> {
+    pub loose: T,
+    pub tight: T,
+    pub centers: Vec<usize>,
+    pub canopies: Vec<Vec<usize>>,
+}
+
+impl<T: Float + One + Zero> Canopy<T> {
+    pub fn new(data: &Array2<T>, loose: T, tight: T) -> Canopy<T> {
+        assert!(tight <= loose, "tight threshold must not exceed loose threshold");
+        if !data.is_standard_layout() {
+            return Canopy::new(&standard_layout(data.view()), loose, tight);
+        }
+        let kdt = kdtree_init(data);
+        let mut candidate = vec![true; data.rows()];
+        let mut centers = Vec::new();
+        let mut canopies = Vec::new();
+
+        for row_idx in 0..data.rows() {
+            if !candidate[row_idx] {
+                continue;
+            }
+            let row = data.row(row_idx);
+            let mut members = Vec::new();
+            region_query(row.as_slice().unwrap(), loose, &kdt, &mut members);
+            for member in members.iter() {
+                if squared_euclidean(row.as_slice().unwrap(), data.row(*member).as_slice().unwrap()) <= tight * tight {
+                    candidate[*member] = false;
+                }
+            }
+            candidate[row_idx] = false;
+            members.sort_unstable();
+            centers.push(row_idx);
+            canopies.push(members);
+        }
+
+        Canopy { loose, tight, centers, canopies }
+    }
+
+    pub fn memberships(&self, nrows: usize) -> Vec<Vec<usize>> {
+        let mut memberships = vec![Vec::new(); nrows];
+        for (canopy, members) in self.canopies.iter().enumerate() {
+            for member in members.iter() {
+                memberships[*member].push(canopy);
+            }
+        }
+        memberships
+    }
+
+    pub fn dbscan(&self, data: &Array2<T>, eps: T, min_points: usize, borders: bool) -> Dbscan<T> {
+        if !data.is_standard_layout() {
+            return self.dbscan(&standard_layout(data.view()), eps, min_points, borders);
+        }
+        let memberships = self.memberships(data.rows());
+        let (clusters, point_types) = expand_clusters(
+            data.rows(),
+            borders,
+            |row_idx, neighbours| {
+                let row = data.row(row_idx);
+                for canopy in memberships[row_idx].iter() {
+                    for member in self.canopies[*canopy].iter() {
+                        if squared_euclidean(row.as_slice().unwrap(), data.row(*member).as_slice().unwrap()) <= eps * eps {
+                            neighbours.push(*member);
+                        }
+                    }
+                }
+                neighbours.sort_unstable();
+                neighbours.dedup();
+            },
+            |_, neighbours| neighbours.len() >= min_points,
+        );
+        Dbscan {
+            eps,
+            min_points,
+            include_self: true,
+            borders,
+            weights: None,
+            clusters,
+            core_sample_indices: core_indices(&point_types),
+            point_types,
+            metric: Euclidean,
+            data: data.to_owned(),
+            index: SpatialIndex::BruteForce,
+        }
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Canopy<T> {
+    pub fn kmeans(&self, data: &Array2<T>, iterations: usize) -> Kmeans<T> {
+        let centers = self.centers.iter().map(|row_idx| data.row(*row_idx).to_owned()).collect::<Vec<Array1<T>>>();
+        Kmeans::lloyd(data, centers, iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{adjusted_rand_index, Noise};
+    use itertools::Itertools;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -1.2, -2.1]).unwrap()
+    }
+
+    #[test]
+    fn test_canopies() {
+        let canopy = Canopy::new(&data(), 2.0, 1.0);
+        assert_eq!(canopy.centers, vec![0, 4, 6]);
+        assert_eq!(canopy.canopies[0], vec![0, 1, 2, 3]);
+        assert!(canopy.memberships(8).iter().all(|m| !m.is_empty()));
+
+        let model = canopy.kmeans(&data(), 10);
+        let clustering = dbg!(model.predict(&data()));
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).take(2).all_equal());
+        assert!(clustering.iter().skip(6).all_equal());
+        assert!(clustering.iter().unique().count() == 3);
+    }
+
+    #[test]
+    fn test_canopy_dbscan() {
+        let canopy = Canopy::new(&data(), 2.0, 1.0);
+        let restricted = canopy.dbscan(&data(), 0.5, 2, false);
+        let clustering = dbg!(restricted.clusters.clone());
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).take(2).all_equal());
+        assert!(clustering.iter().skip(6).all_equal());
+        assert!(clustering[0] != clustering[4]);
+        assert!(clustering[4] != clustering[6]);
+        assert!(clustering[6] != clustering[0]);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = Canopy::new(&fortran, 2.0, 1.0);
+        assert_eq!(transposed.canopies, canopy.canopies);
+        let labels = transposed.dbscan(&fortran, 0.5, 2, false).clusters;
+        assert_eq!(adjusted_rand_index(&labels, &restricted.clusters, Noise::OwnClass), 1.0);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::hierarchical::{Hierarchical, Linkage};
+use crate::metrics::Label;
+use ndarray::prelude::*;
+use rand::prelude::thread_rng;
+use rand::rngs::SmallRng;
+use rand::seq::index::sample;
+use rand::SeedableRng;
+
+#[derive(Debug)]
+pub struct Consensus {
+    pub coassociation: Array2<f64>,
+    pub tree: Hierarchical<f64>,
+    pub clusters: Vec<usize>,
+}
+
+impl Consensus {
+    pub fn new<T, B, F>(data: &Array2<T>, nclust: usize, runs: usize, subsample: f64, linkage: Linkage, mut cluster: F) -> Consensus
+    where
+        T: Copy,
+        B: Label,
+        F: FnMut(&Array2<T>) -> Vec<B>,
+    {
+        assert!(subsample > 0.0 && subsample <= 1.0, "subsample must be in (0, 1]");
+        let n = data.rows();
+        let size = ((n as f64 * subsample).round() as usize).clamp(1, n);
+        let mut rng = SmallRng::from_rng(thread_rng()).expect("seed SmallRng from thread_rng");
+        let mut together = Array2::<f64>::zeros((n, n));
+        let mut sampled = Array2::<f64>::zeros((n, n));
+        for _ in 0..runs {
+            let mut indices = sample(&mut rng, n, size).into_vec();
+            indices.sort_unstable();
+            let labels = cluster(&data.select(Axis(0), &indices));
+            assert_eq!(labels.len(), indices.len(), "one label per subsampled row");
+            for (a, (i, label_i)) in indices.iter().zip(labels.iter()).enumerate() {
+                for (j, label_j) in indices.iter().zip(labels.iter()).skip(a) {
+                    sampled[[*i, *j]] += 1.0;
+                    if label_i.cluster().is_some() && label_i.cluster() == label_j.cluster() {
+                        together[[*i, *j]] += 1.0;
+                    }
+                }
+            }
+        }
+
+        let mut coassociation = Array2::<f64>::zeros((n, n));
+        for i in 0..n {
+            for j in i..n {
+                let value = if sampled[[i, j]] > 0.0 { together[[i, j]] / sampled[[i, j]] } else { 0.0 };
+                coassociation[[i, j]] = value;
+                coassociation[[j, i]] = value;
+            }
+        }
+        let tree = Hierarchical::from_distances(coassociation.mapv(|x| 1.0 - x), linkage);
+        let clusters = tree.cut_clusters(nclust);
+        Consensus { coassociation, tree, clusters }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmeans::Kmeans;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_consensus() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap();
+        let model = Consensus::new(&data, 2, 30, 0.8, Linkage::Average, |subset| Kmeans::new(subset, 2, 10, 1).unwrap().clusters);
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+        assert_eq!(model.coassociation[[0, 0]], 1.0);
+        assert!(model.coassociation[[0, 1]] > model.coassociation[[0, 4]]);
+    }
+}
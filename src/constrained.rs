@@ -0,0 +1,128 @@
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::prelude::IteratorRandom;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct CopKmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+    pub withinss: Vec<T>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> CopKmeans<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, must_link: &[(usize, usize)], cannot_link: &[(usize, usize)], iterations: usize, nseeds: usize) -> Option<CopKmeans<T>> {
+        let groups = must_link_groups(data.rows(), must_link);
+        let ngroups = groups.iter().max().map_or(0, |g| g + 1);
+        let mut members = vec![Vec::new(); ngroups];
+        for (row_idx, group) in groups.iter().enumerate() {
+            members[*group].push(row_idx);
+        }
+        let mut conflicts = vec![Vec::new(); ngroups];
+        for (a, b) in cannot_link.iter() {
+            if groups[*a] == groups[*b] {
+                return None;
+            }
+            conflicts[groups[*a]].push(groups[*b]);
+            conflicts[groups[*b]].push(groups[*a]);
+        }
+
+        let mut rng = thread_rng();
+        (0..nseeds)
+            .filter_map(|_| {
+                let centers = data.outer_iter().choose_multiple(&mut rng, nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>();
+                Self::iterate(data, centers, &members, &conflicts, iterations)
+            })
+            .min_by(|a, b| a.withinss.iter().cloned().sum::<T>().partial_cmp(&b.withinss.iter().cloned().sum::<T>()).expect("withinss is not NAN"))
+    }
+
+    fn iterate(data: &Array2<T>, mut centers: Vec<Array1<T>>, members: &[Vec<usize>], conflicts: &[Vec<usize>], iterations: usize) -> Option<CopKmeans<T>> {
+        let nclust = centers.len();
+        let mut clusters = vec![0; data.rows()];
+        let mut withinss = vec![T::zero(); nclust];
+        for _ in 0..iterations {
+            let mut assigned: Vec<Option<usize>> = vec![None; members.len()];
+            withinss = vec![T::zero(); nclust];
+            for (group, rows) in members.iter().enumerate() {
+                let mut costs = centers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, center)| (i, rows.iter().map(|row_idx| (&data.row(*row_idx) - center).mapv(|x| x * x).sum()).fold(T::zero(), |acc, x| acc + x)))
+                    .collect::<Vec<(usize, T)>>();
+                costs.sort_unstable_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from center"));
+                let (cluster, cost) = costs.into_iter().find(|(cluster, _)| conflicts[group].iter().all(|other| assigned[*other] != Some(*cluster)))?;
+                assigned[group] = Some(cluster);
+                withinss[cluster] += cost;
+                for row_idx in rows.iter() {
+                    clusters[*row_idx] = cluster;
+                }
+            }
+
+            let mut sums = vec![Array1::zeros(data.cols()); nclust];
+            let mut counts = vec![0; nclust];
+            for (row, cluster) in data.outer_iter().zip(clusters.iter()) {
+                sums[*cluster] = &sums[*cluster] + &row;
+                counts[*cluster] += 1;
+            }
+            for ((center, sum), count) in centers.iter_mut().zip(sums).zip(counts) {
+                if count > 0 {
+                    *center = sum / T::from(count).expect("T::from(usize)");
+                }
+            }
+        }
+        Some(CopKmeans { centers, clusters, withinss })
+    }
+}
+
+fn must_link_groups(n: usize, must_link: &[(usize, usize)]) -> Vec<usize> {
+    let mut parent = (0..n).collect::<Vec<usize>>();
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+    for (a, b) in must_link.iter() {
+        let (ra, rb) = (find(&mut parent, *a), find(&mut parent, *b));
+        parent[ra] = rb;
+    }
+    let mut labels: Vec<Option<usize>> = vec![None; n];
+    let mut next = 0;
+    (0..n)
+        .map(|x| {
+            let root = find(&mut parent, x);
+            *labels[root].get_or_insert_with(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect::<Vec<usize>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((6, 1), vec![0.0, 0.1, 0.2, 5.0, 5.1, 5.2]).unwrap()
+    }
+
+    #[test]
+    fn test_constraints() {
+        let model = CopKmeans::new(&data(), 2, &[(0, 5)], &[(1, 2)], 10, 5).expect("feasible constraints");
+        let clustering = dbg!(model.clusters);
+        assert_eq!(clustering[0], clustering[5]);
+        assert!(clustering[1] != clustering[2]);
+    }
+
+    #[test]
+    fn test_infeasible() {
+        assert!(CopKmeans::new(&data(), 2, &[(0, 1)], &[(0, 1)], 10, 5).is_none());
+        assert!(CopKmeans::new(&data(), 2, &[], &[(0, 1), (1, 2), (0, 2)], 10, 5).is_none());
+    }
+}
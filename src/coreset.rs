@@ -0,0 +1,105 @@
+use crate::error::Error;
+use crate::kmeans::{Kmeans, KmeansBuilder};
+use crate::metric::{Metric, SquaredEuclidean};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct Coreset<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub points: Array2<T>,
+    pub weights: Vec<T>,
+    pub indices: Vec<usize>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Coreset<T> {
+    pub fn new(data: &Array2<T>, size: usize) -> Coreset<T> {
+        Self::with_rng(data, size, &mut thread_rng())
+    }
+
+    pub fn with_rng<R: Rng>(data: &Array2<T>, size: usize, rng: &mut R) -> Coreset<T> {
+        let n = data.rows();
+        assert!(n > 0 && size > 0, "need at least one row and a positive coreset size");
+        if !data.is_standard_layout() {
+            return Self::with_rng(&standard_layout(data.view()), size, rng);
+        }
+        let nt = T::from(n).expect("T::from(usize)");
+        let two = T::one() + T::one();
+        let mean = data.mean_axis(Axis(0));
+        let distances = data.outer_iter().map(|row| SquaredEuclidean.distance(row.as_slice().unwrap(), mean.as_slice().unwrap())).collect::<Vec<T>>();
+        let total = distances.iter().cloned().sum::<T>();
+        let sensitivities = distances
+            .iter()
+            .map(|d| if total > T::zero() { T::one() / (two * nt) + *d / (two * total) } else { T::one() / nt })
+            .collect::<Vec<T>>();
+        let cumulative = sensitivities
+            .iter()
+            .scan(T::zero(), |acc, q| {
+                *acc += *q;
+                Some(*acc)
+            })
+            .collect::<Vec<T>>();
+
+        let m = T::from(size).expect("T::from(usize)");
+        let mut sampled = BTreeMap::new();
+        for _ in 0..size {
+            let target = cumulative[n - 1] * T::from(rng.gen::<f64>()).expect("T::from(f64)");
+            let i = cumulative.partition_point(|c| *c <= target).min(n - 1);
+            *sampled.entry(i).or_insert_with(T::zero) += T::one() / (m * sensitivities[i]);
+        }
+
+        let indices = sampled.keys().cloned().collect::<Vec<usize>>();
+        Coreset {
+            points: data.select(Axis(0), &indices),
+            weights: sampled.values().cloned().collect::<Vec<T>>(),
+            indices,
+        }
+    }
+
+    pub fn kmeans(&self, nclust: usize, iterations: usize, nseeds: usize) -> Result<Kmeans<T>, Error> {
+        KmeansBuilder::new().k(nclust).max_iter(iterations).nseeds(nseeds).weights(self.weights.clone()).build(&self.points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_coreset() {
+        let data = Array2::from_shape_fn((400, 2), |(row, col)| ((row * 13 + col * 7) as f64 * 0.37).sin() + (row % 2) as f64 * 30.0);
+        let coreset = Coreset::with_rng(&data, 60, &mut SmallRng::seed_from_u64(5));
+        assert!(coreset.points.rows() <= 60);
+        assert_eq!(coreset.points.rows(), coreset.weights.len());
+        let total = coreset.weights.iter().sum::<f64>();
+        assert!(total > 200.0 && total < 800.0);
+
+        let approx = coreset.kmeans(2, 100, 5).unwrap();
+        let full = Kmeans::new(&data, 2, 100, 5).unwrap();
+        let cost = |model: &Kmeans<f64>| {
+            data.outer_iter()
+                .map(|row| model.centers.iter().map(|c| SquaredEuclidean.distance(row.as_slice().unwrap(), c.as_slice().unwrap())).fold(f64::INFINITY, f64::min))
+                .sum::<f64>()
+        };
+        assert!(cost(&approx) < 1.5 * cost(&full));
+        let labels = approx.predict(&data);
+        assert!(labels.iter().step_by(2).all(|l| *l == labels[0]));
+        assert!(labels.iter().skip(1).step_by(2).all(|l| *l == labels[1]));
+        assert!(labels[0] != labels[1]);
+
+        let fortran = Array2::from_shape_vec((400, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = Coreset::with_rng(&fortran, 60, &mut SmallRng::seed_from_u64(5));
+        assert_eq!(transposed.indices, coreset.indices);
+        assert_eq!(transposed.weights, coreset.weights);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::Error;
+use crate::model::{Fit, Predict};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+
+pub trait IntoDataset<T> {
+    fn into_dataset(self) -> Result<Array2<T>, Error>;
+}
+
+impl<T> IntoDataset<T> for Array2<T> {
+    fn into_dataset(self) -> Result<Array2<T>, Error> {
+        Ok(self)
+    }
+}
+
+impl<T: Copy> IntoDataset<T> for ArrayView2<'_, T> {
+    fn into_dataset(self) -> Result<Array2<T>, Error> {
+        Ok(standard_layout(self))
+    }
+}
+
+impl<T: Copy> IntoDataset<T> for &[Vec<T>] {
+    fn into_dataset(self) -> Result<Array2<T>, Error> {
+        let cols = self.first().map_or(0, |row| row.len());
+        if let Some(row) = self.iter().find(|row| row.len() != cols) {
+            return Err(Error::DimensionMismatch { expected: cols, found: row.len() });
+        }
+        Ok(Array2::from_shape_fn((self.len(), cols), |(i, j)| self[i][j]))
+    }
+}
+
+impl<T: Copy, const N: usize> IntoDataset<T> for &[[T; N]] {
+    fn into_dataset(self) -> Result<Array2<T>, Error> {
+        Ok(Array2::from_shape_fn((self.len(), N), |(i, j)| self[i][j]))
+    }
+}
+
+impl<T: Copy> IntoDataset<T> for (&[T], usize) {
+    fn into_dataset(self) -> Result<Array2<T>, Error> {
+        let (values, cols) = self;
+        if cols == 0 {
+            return Err(Error::InvalidParameter("n_cols must be positive".to_string()));
+        }
+        if values.len() % cols != 0 {
+            return Err(Error::DimensionMismatch {
+                expected: (values.len() / cols + 1) * cols,
+                found: values.len(),
+            });
+        }
+        Ok(Array2::from_shape_vec((values.len() / cols, cols), values.to_vec()).expect("length is a multiple of n_cols"))
+    }
+}
+
+pub fn fit<T, F: Fit<Array2<T>>, D: IntoDataset<T>>(estimator: &F, data: D) -> Result<F::Model, Error> {
+    estimator.fit(&data.into_dataset()?)
+}
+
+pub fn predict<T, P: Predict<Array2<T>>, D: IntoDataset<T>>(model: &P, data: D) -> Result<Vec<Option<usize>>, Error> {
+    Ok(model.predict(&data.into_dataset()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbscan::DbscanBuilder;
+    use crate::kmeans::KmeansBuilder;
+
+    #[test]
+    fn test_into_dataset() {
+        let expected = arr2(&[[0.0, 0.0], [0.1, 0.1], [5.0, 5.0], [5.1, 5.1]]);
+        let nested = [vec![0.0, 0.0], vec![0.1, 0.1], vec![5.0, 5.0], vec![5.1, 5.1]];
+        let fixed = [[0.0, 0.0], [0.1, 0.1], [5.0, 5.0], [5.1, 5.1]];
+        let flat = [0.0, 0.0, 0.1, 0.1, 5.0, 5.0, 5.1, 5.1];
+        assert_eq!(expected.clone().into_dataset(), Ok(expected.clone()));
+        assert_eq!(nested[..].into_dataset(), Ok(expected.clone()));
+        assert_eq!(fixed[..].into_dataset(), Ok(expected.clone()));
+        assert_eq!(expected.t().t().into_dataset(), Ok(expected.clone()));
+        assert_eq!(expected.t().into_dataset(), Ok(arr2(&[[0.0, 0.1, 5.0, 5.1], [0.0, 0.1, 5.0, 5.1]])));
+        assert_eq!((&flat[..], 2).into_dataset(), Ok(expected));
+
+        assert_eq!(vec![vec![1.0, 2.0], vec![3.0]][..].into_dataset(), Err(Error::DimensionMismatch { expected: 2, found: 1 }));
+        assert_eq!((&flat[..7], 2).into_dataset(), Err(Error::DimensionMismatch { expected: 8, found: 7 }));
+        assert!((&flat[..], 0).into_dataset().is_err());
+
+        let model = fit(&KmeansBuilder::new().k(2).seed(1), &fixed[..]).unwrap();
+        let labels = predict(&model, &nested[..]).unwrap();
+        assert_eq!((labels[0] == labels[1], labels[1] == labels[2], labels[2] == labels[3]), (true, false, true));
+        let columns = Array2::from_shape_vec((4, 2).f(), vec![0.0, 0.1, 5.0, 5.1, 0.0, 0.1, 5.0, 5.1]).unwrap();
+        assert_eq!(predict(&model, columns.view()).unwrap(), labels);
+        let model = fit(&DbscanBuilder::new().eps(0.5).min_points(2), (&flat[..], 2)).unwrap();
+        assert_eq!(model.n_clusters(), 2);
+    }
+}
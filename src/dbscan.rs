@@ -1,4 +1,10 @@
+use crate::error::Error;
 use crate::itertools::Itertools;
+use crate::metric::{Anisotropic, Euclidean, Hamming, Jaccard, Metric, Periodic};
+use crate::model::{Fit, Predict};
+use crate::sparse::CsrMatrix;
+use crate::validation::{check, non_finite_rows, standard_layout, NonFinite};
+use crate::vptree::VpTree;
 use kdtree::distance::squared_euclidean;
 use kdtree::KdTree;
 use ndarray::prelude::*;
@@ -6,77 +12,605 @@ use num_traits::float::Float;
 use num_traits::identities::{One, Zero};
 use rand::prelude::thread_rng;
 use rand::seq::index::sample;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighborIndex {
+    Auto,
+    KdTree,
+    Grid,
+    VpTree,
+    BruteForce,
+}
+
+#[derive(Debug)]
+pub enum SpatialIndex<T: Float + One + Zero> {
+    BruteForce,
+    KdTree(KdTree<T, usize, Vec<T>>),
+    Grid(Grid<T>),
+    VpTree(VpTree<T>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointType {
+    Core,
+    Border,
+    Noise,
+}
+
+#[derive(Debug)]
+pub struct Grid<T: Float + One + Zero> {
+    pub cell: T,
+    pub cells: HashMap<Vec<i64>, Vec<usize>>,
+}
 
 #[derive(Debug)]
-pub struct Dbscan<T: Float + One + Zero> {
+pub struct Dbscan<T: Float + One + Zero, M: Metric<T> = Euclidean> {
+    pub eps: T,
+    pub min_points: usize,
+    pub include_self: bool,
+    pub borders: bool,
+    pub weights: Option<Vec<T>>,
+    pub clusters: Vec<usize>,
+    pub core_sample_indices: Vec<usize>,
+    pub point_types: Vec<PointType>,
+    pub metric: M,
+    pub data: Array2<T>,
+    pub index: SpatialIndex<T>,
+}
+
+/// Clustering of inputs that are not kept as dense rows (packed fingerprints, sets, sparse rows), so unlike
+/// `Dbscan` it carries only the labels and cannot predict or insert new points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbscanLabels<T: Float + One + Zero> {
     pub eps: T,
     pub min_points: usize,
+    pub borders: bool,
     pub clusters: Vec<usize>,
+    pub core_sample_indices: Vec<usize>,
+    pub point_types: Vec<PointType>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DbscanBuilder<T: Float + One + Zero, M: Metric<T> = Euclidean> {
+    eps: T,
+    min_points: usize,
+    include_self: bool,
+    borders: bool,
+    weights: Option<Vec<T>>,
+    index: NeighborIndex,
+    metric: M,
+}
+
+impl<T: Float + One + Zero> DbscanBuilder<T> {
+    pub fn new() -> DbscanBuilder<T> {
+        DbscanBuilder {
+            eps: T::from(0.5).expect("T::from(f64)"),
+            min_points: 5,
+            include_self: true,
+            borders: true,
+            weights: None,
+            index: NeighborIndex::Auto,
+            metric: Euclidean,
+        }
+    }
+}
+
+impl<T: Float + One + Zero> Default for DbscanBuilder<T> {
+    fn default() -> DbscanBuilder<T> {
+        DbscanBuilder::new()
+    }
+}
+
+impl<T: Float + One + Zero, M: Metric<T> + Clone> DbscanBuilder<T, M> {
+    pub fn eps(mut self, eps: T) -> DbscanBuilder<T, M> {
+        self.eps = eps;
+        self
+    }
+
+    pub fn min_points(mut self, min_points: usize) -> DbscanBuilder<T, M> {
+        self.min_points = min_points;
+        self
+    }
+
+    pub fn include_self(mut self, include_self: bool) -> DbscanBuilder<T, M> {
+        self.include_self = include_self;
+        self
+    }
+
+    pub fn borders(mut self, borders: bool) -> DbscanBuilder<T, M> {
+        self.borders = borders;
+        self
+    }
+
+    pub fn weights(mut self, weights: Vec<T>) -> DbscanBuilder<T, M> {
+        self.weights = Some(weights);
+        self
+    }
+
+    pub fn index(mut self, index: NeighborIndex) -> DbscanBuilder<T, M> {
+        self.index = index;
+        self
+    }
+
+    pub fn metric<N: Metric<T> + Clone>(self, metric: N) -> DbscanBuilder<T, N> {
+        DbscanBuilder {
+            eps: self.eps,
+            min_points: self.min_points,
+            include_self: self.include_self,
+            borders: self.borders,
+            weights: self.weights,
+            index: self.index,
+            metric,
+        }
+    }
+}
+
+impl<T: Float + One + Zero, M: Metric<T> + Clone> Fit<Array2<T>> for DbscanBuilder<T, M> {
+    type Model = Dbscan<T, M>;
+
+    fn fit(&self, data: &Array2<T>) -> Result<Dbscan<T, M>, Error> {
+        validate(data, self.eps, self.weights.as_deref())?;
+        Dbscan::build(data, self.eps, self.min_points, self.include_self, self.borders, self.weights.as_deref(), self.metric.clone(), self.index)
+    }
+}
+
+fn validate<T: Float + One + Zero>(data: &Array2<T>, eps: T, weights: Option<&[T]>) -> Result<(), Error> {
+    if data.rows() == 0 {
+        return Err(Error::EmptyInput);
+    }
+    if eps.is_nan() || eps <= T::zero() {
+        return Err(Error::InvalidParameter("eps must be positive".to_string()));
+    }
+    if let Some(weights) = weights {
+        if weights.len() != data.rows() {
+            return Err(Error::DimensionMismatch {
+                expected: data.rows(),
+                found: weights.len(),
+            });
+        }
+    }
+    let offending = non_finite_rows(data);
+    if !offending.is_empty() {
+        return Err(Error::NonFiniteValue { rows: offending });
+    }
+    Ok(())
 }
 
 impl<T: Float + One + Zero> Dbscan<T> {
-    pub fn new(data: &Array2<T>, eps: T, min_points: usize, borders: bool) -> Dbscan<T> {
-        let mut c = 1;
+    pub fn new(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>) -> Result<Dbscan<T>, Error> {
+        Self::with_metric(data, eps, min_points, borders, weights, Euclidean)
+    }
+
+    pub fn try_new(data: &Array2<T>, eps: T, min_points: usize, borders: bool, policy: NonFinite) -> Result<(Dbscan<T>, Vec<usize>), Error> {
+        let checked = check(data, policy)?;
+        let model = Fit::fit(&DbscanBuilder::new().eps(eps).min_points(min_points).borders(borders), &checked.data)?;
+        Ok((model, checked.rows))
+    }
+
+    pub fn from_view(data: ArrayView2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>) -> Result<Dbscan<T>, Error> {
+        Self::new(&standard_layout(data), eps, min_points, borders, weights)
+    }
+
+    pub fn fit_predict(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>) -> Result<Vec<Option<usize>>, Error> {
+        Ok(Self::new(data, eps, min_points, borders, weights)?.labels())
+    }
+}
+
+impl<T: Float + One + Zero> Dbscan<T, Anisotropic<T>> {
+    pub fn anisotropic(data: &Array2<T>, eps: &[T], min_points: usize, borders: bool, weights: Option<&[T]>) -> Result<Dbscan<T, Anisotropic<T>>, Error> {
+        if eps.len() != data.cols() {
+            return Err(Error::DimensionMismatch {
+                expected: data.cols(),
+                found: eps.len(),
+            });
+        }
+        Self::with_metric(data, T::one(), min_points, borders, weights, Anisotropic::new(eps))
+    }
+}
+
+impl<T: Float + One + Zero> Dbscan<T, Periodic<T>> {
+    pub fn periodic(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>, periods: &[Option<T>]) -> Result<Dbscan<T, Periodic<T>>, Error> {
+        if periods.len() != data.cols() {
+            return Err(Error::DimensionMismatch {
+                expected: data.cols(),
+                found: periods.len(),
+            });
+        }
+        Self::with_metric(data, eps, min_points, borders, weights, Periodic::new(periods))
+    }
+}
+
+impl DbscanLabels<f64> {
+    pub fn from_fingerprints(data: &Array2<u64>, eps: u32, min_points: usize, borders: bool) -> DbscanLabels<f64> {
+        if !data.is_standard_layout() {
+            return Self::from_fingerprints(&standard_layout(data.view()), eps, min_points, borders);
+        }
+        let (clusters, point_types) = expand_clusters(
+            data.rows(),
+            borders,
+            |row_idx, neighbours| {
+                let row = data.row(row_idx);
+                for (neighbour_idx, other) in data.outer_iter().enumerate() {
+                    if Hamming::packed(row.as_slice().unwrap(), other.as_slice().unwrap()) <= eps {
+                        neighbours.push(neighbour_idx);
+                    }
+                }
+            },
+            |_, neighbours| neighbours.len() >= min_points,
+        );
+        DbscanLabels {
+            eps: f64::from(eps),
+            min_points,
+            borders,
+            clusters,
+            core_sample_indices: core_indices(&point_types),
+            point_types,
+        }
+    }
+
+    pub fn from_sets(sets: &[Vec<usize>], eps: f64, min_points: usize, borders: bool) -> DbscanLabels<f64> {
+        let (clusters, point_types) = expand_clusters(
+            sets.len(),
+            borders,
+            |row_idx, neighbours| {
+                for (neighbour_idx, other) in sets.iter().enumerate() {
+                    if Jaccard::sorted(&sets[row_idx], other) <= eps {
+                        neighbours.push(neighbour_idx);
+                    }
+                }
+            },
+            |_, neighbours| neighbours.len() >= min_points,
+        );
+        DbscanLabels {
+            eps,
+            min_points,
+            borders,
+            clusters,
+            core_sample_indices: core_indices(&point_types),
+            point_types,
+        }
+    }
+}
+
+impl<T: Float + One + Zero> DbscanLabels<T> {
+    pub fn from_csr(csr: &CsrMatrix<T>, eps: T, min_points: usize, borders: bool) -> DbscanLabels<T> {
+        let eps2 = eps * eps;
+        let (clusters, point_types) = expand_clusters(
+            csr.rows(),
+            borders,
+            |row_idx, neighbours| {
+                for neighbour_idx in 0..csr.rows() {
+                    if csr.squared_distance(row_idx, neighbour_idx) <= eps2 {
+                        neighbours.push(neighbour_idx);
+                    }
+                }
+            },
+            |_, neighbours| neighbours.len() >= min_points,
+        );
+        DbscanLabels {
+            eps,
+            min_points,
+            borders,
+            clusters,
+            core_sample_indices: core_indices(&point_types),
+            point_types,
+        }
+    }
+
+    pub fn labels(&self) -> Vec<Option<usize>> {
+        self.clusters.iter().map(|c| if *c > 0 { Some(c - 1) } else { None }).collect::<Vec<Option<usize>>>()
+    }
+
+    pub fn labels_i64(&self) -> Vec<i64> {
+        self.clusters.iter().map(|c| *c as i64 - 1).collect::<Vec<i64>>()
+    }
+
+    pub fn n_clusters(&self) -> usize {
+        self.clusters.iter().filter(|c| **c > 0).unique().count()
+    }
+
+    pub fn noise_count(&self) -> usize {
+        self.clusters.iter().filter(|c| **c == 0).count()
+    }
+}
+
+impl<T: Float + One + Zero, M: Metric<T>> Dbscan<T, M> {
+    pub fn with_metric(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>, metric: M) -> Result<Dbscan<T, M>, Error> {
+        Self::with_index(data, eps, min_points, borders, weights, metric, NeighborIndex::Auto)
+    }
+
+    pub fn with_index(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>, metric: M, index: NeighborIndex) -> Result<Dbscan<T, M>, Error> {
+        Self::with_options(data, eps, min_points, true, borders, weights, metric, index)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_options(data: &Array2<T>, eps: T, min_points: usize, include_self: bool, borders: bool, weights: Option<&[T]>, metric: M, index: NeighborIndex) -> Result<Dbscan<T, M>, Error> {
+        validate(data, eps, weights)?;
+        Self::build(data, eps, min_points, include_self, borders, weights, metric, index)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(data: &Array2<T>, eps: T, min_points: usize, include_self: bool, borders: bool, weights: Option<&[T]>, metric: M, index: NeighborIndex) -> Result<Dbscan<T, M>, Error> {
+        if !data.is_standard_layout() {
+            return Self::build(&standard_layout(data.view()), eps, min_points, include_self, borders, weights, metric, index);
+        }
+        let index = SpatialIndex::new(data, eps, &metric, index)?;
+        let (clusters, point_types) = expand_clusters(
+            data.rows(),
+            borders,
+            |row_idx, neighbours| index.within(data, data.row(row_idx).as_slice().unwrap(), eps, &metric, neighbours),
+            |row_idx, neighbours| is_core(row_idx, neighbours, min_points, include_self, weights),
+        );
+        Ok(Dbscan {
+            eps,
+            min_points,
+            include_self,
+            borders,
+            weights: weights.map(|w| w.to_vec()),
+            clusters,
+            core_sample_indices: core_indices(&point_types),
+            point_types,
+            metric,
+            data: data.to_owned(),
+            index,
+        })
+    }
+
+    pub fn predict_points(&self, new_data: &Array2<T>) -> Vec<ClusterPrediction> {
+        if !new_data.is_standard_layout() {
+            return self.predict_points(&standard_layout(new_data.view()));
+        }
+        let data = &self.data;
+        let mut neighbours = Vec::with_capacity(data.rows());
+        new_data
+            .outer_iter()
+            .map(|row| {
+                neighbours.clear();
+                self.index.within(data, row.as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
+                let count = match self.weights.as_ref() {
+                    Some(weights) => neighbours.iter().fold(T::zero(), |acc, idx| acc + weights[*idx]) + T::one(),
+                    None => T::from(neighbours.len() + 1).expect("T::from(usize)"),
+                };
+                let own = if self.include_self { T::zero() } else { T::one() };
+                let clusters = neighbours
+                    .iter()
+                    .filter(|idx| self.point_types[**idx] == PointType::Core)
+                    .map(|idx| self.clusters[*idx])
+                    .unique()
+                    .collect::<Vec<usize>>();
+                if clusters.is_empty() {
+                    ClusterPrediction::Noise
+                } else if count - own >= T::from(self.min_points).expect("T::from(usize)") {
+                    ClusterPrediction::Core(clusters)
+                } else {
+                    ClusterPrediction::Border(clusters)
+                }
+            })
+            .collect::<Vec<ClusterPrediction>>()
+    }
+
+    /// Labels each point with the cluster of its nearest core point within eps (ties broken by lowest index), or 0 if there is none.
+    /// The flag is set when core points from more than one cluster are within eps.
+    pub fn predict_labels(&self, new_data: &Array2<T>) -> Vec<(usize, bool)> {
+        if !new_data.is_standard_layout() {
+            return self.predict_labels(&standard_layout(new_data.view()));
+        }
+        let data = &self.data;
         let mut neighbours = Vec::with_capacity(data.rows());
-        let mut sub_neighbours = Vec::with_capacity(data.rows());
-        let mut visited = vec![false; data.rows()];
-        let mut clusters = vec![0; data.rows()];
-        let kdt = kdtree_init(&data);
+        new_data
+            .outer_iter()
+            .map(|row| {
+                neighbours.clear();
+                self.index.within(data, row.as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
+                neighbours.retain(|idx| self.point_types[*idx] == PointType::Core);
+                nearest_core_label(data, row.as_slice().unwrap(), &neighbours, &self.clusters, &self.metric)
+            })
+            .collect::<Vec<(usize, bool)>>()
+    }
+
+    pub fn into_compact(self) -> CompactDbscan<T, M> {
+        let cores = self.data.select(Axis(0), &self.core_sample_indices);
+        let kind = match self.index {
+            SpatialIndex::BruteForce => NeighborIndex::BruteForce,
+            SpatialIndex::KdTree(_) => NeighborIndex::KdTree,
+            SpatialIndex::Grid(_) => NeighborIndex::Grid,
+            SpatialIndex::VpTree(_) => NeighborIndex::VpTree,
+        };
+        CompactDbscan {
+            eps: self.eps,
+            clusters: self.core_sample_indices.iter().map(|idx| self.clusters[*idx]).collect::<Vec<usize>>(),
+            index: SpatialIndex::new(&cores, self.eps, &self.metric, kind).expect("index kind already built for this metric"),
+            core_sample_indices: self.core_sample_indices,
+            cores,
+            metric: self.metric,
+        }
+    }
 
-        let indices = sample(&mut thread_rng(), data.rows(), data.rows());
-        for row_idx in indices.iter() {
+    pub fn assign_borders_to_nearest_core(&mut self) {
+        let data = &self.data;
+        let mut neighbours = vec![];
+        let mut assigned = vec![];
+        for row_idx in (0..data.rows()).filter(|idx| self.point_types[*idx] == PointType::Border) {
             let row = data.row(row_idx);
-            if !visited[row_idx] {
-                visited[row_idx] = true;
+            neighbours.clear();
+            self.index.within(data, row.as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
+            let nearest = neighbours
+                .iter()
+                .filter(|idx| self.point_types[**idx] == PointType::Core)
+                .map(|idx| (*idx, self.metric.distance(row.as_slice().unwrap(), data.row(*idx).as_slice().unwrap())))
+                .min_by(|(a, da), (b, db)| da.partial_cmp(db).expect("PartialOrd distance from core").then(a.cmp(b)))
+                .expect("border point has a core neighbour")
+                .0;
+            assigned.push((row_idx, self.clusters[nearest]));
+        }
+        for (row_idx, cluster) in assigned {
+            self.clusters[row_idx] = cluster;
+        }
+    }
 
-                neighbours.clear();
-                region_query(row.as_slice().unwrap(), eps, &kdt, &mut neighbours);
-                neighbours.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-                neighbours.dedup();
-
-                if neighbours.len() >= min_points {
-                    clusters[row_idx] = c;
-                    while let Some(neighbour_idx) = neighbours.pop() {
-                        if borders {
-                            clusters[neighbour_idx] = c;
-                        }
-                        if !visited[neighbour_idx] {
-                            visited[neighbour_idx] = true;
-                            sub_neighbours.clear();
-                            region_query(data.row(neighbour_idx).as_slice().unwrap(), eps, &kdt, &mut sub_neighbours);
-
-                            if sub_neighbours.len() >= min_points {
-                                if !borders {
-                                    clusters[neighbour_idx] = c;
-                                }
-                                neighbours.extend_from_slice(&sub_neighbours);
-                                neighbours.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-                                neighbours.dedup();
-                            }
-                        }
+    pub fn insert(&mut self, new_points: &Array2<T>) {
+        if !new_points.is_standard_layout() {
+            return self.insert(&standard_layout(new_points.view()));
+        }
+        let (rows, cols) = self.data.dim();
+        assert_eq!(new_points.cols(), cols, "new points have as many columns as the training data");
+        if !self.data.is_standard_layout() {
+            self.data = standard_layout(self.data.view());
+        }
+        let mut buffer = std::mem::replace(&mut self.data, Array2::zeros((0, cols))).into_raw_vec();
+        buffer.reserve(new_points.len());
+        self.data = Array2::from_shape_vec((rows, cols), buffer).expect("training data in standard layout");
+        for (idx, row) in (rows..).zip(new_points.outer_iter()) {
+            let mut buffer = std::mem::replace(&mut self.data, Array2::zeros((0, cols))).into_raw_vec();
+            buffer.extend(row.iter().cloned());
+            self.data = Array2::from_shape_vec((idx + 1, cols), buffer).expect("training data in standard layout");
+            self.index.insert(&self.data, idx, &self.metric);
+            if let Some(weights) = self.weights.as_mut() {
+                weights.push(T::one());
+            }
+            self.clusters.push(0);
+            self.point_types.push(PointType::Noise);
+            self.insert_point(idx);
+        }
+        self.core_sample_indices = core_indices(&self.point_types);
+    }
+
+    fn insert_point(&mut self, idx: usize) {
+        let data = &self.data;
+        let query = |row_idx: usize| {
+            let mut neighbours = vec![];
+            self.index.within(data, data.row(row_idx).as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
+            neighbours
+        };
+        let neighbours = query(idx);
+        let new_cores = neighbours
+            .iter()
+            .filter(|n| self.point_types[**n] != PointType::Core)
+            .map(|n| (*n, query(*n)))
+            .filter(|(n, region)| is_core(*n, region, self.min_points, self.include_self, self.weights.as_deref()))
+            .collect::<Vec<(usize, Vec<usize>)>>();
+        if new_cores.is_empty() {
+            if let Some(core) = neighbours.iter().find(|n| self.point_types[**n] == PointType::Core) {
+                self.point_types[idx] = PointType::Border;
+                if self.borders {
+                    self.clusters[idx] = self.clusters[*core];
+                }
+            }
+            return;
+        }
+
+        // New cores join each other and existing clusters only through core neighbours within eps, so cores bridged by a
+        // border point stay apart. Nodes 0..max are the existing clusters, followed by one node per new core.
+        for (core, _) in new_cores.iter() {
+            self.point_types[*core] = PointType::Core;
+        }
+        let max = self.clusters.iter().max().map_or(0, |c| *c);
+        let mut parents = (0..max + new_cores.len()).collect::<Vec<usize>>();
+        for (i, (_, region)) in new_cores.iter().enumerate() {
+            for n in region.iter().filter(|n| self.point_types[**n] == PointType::Core) {
+                let node = match new_cores.iter().position(|(core, _)| core == n) {
+                    Some(j) => max + j,
+                    None => self.clusters[*n] - 1,
+                };
+                union_nodes(&mut parents, max + i, node);
+            }
+        }
+        let mut names = vec![0; parents.len()];
+        let mut next = max;
+        for node in 0..parents.len() {
+            let root = find_node(&mut parents, node);
+            if names[root] == 0 {
+                names[root] = if node < max {
+                    node + 1
+                } else {
+                    next += 1;
+                    next
+                };
+            }
+        }
+        for cluster in self.clusters.iter_mut().filter(|cluster| **cluster > 0) {
+            *cluster = names[find_node(&mut parents, *cluster - 1)];
+        }
+        for (i, (core, region)) in new_cores.iter().enumerate() {
+            self.clusters[*core] = names[find_node(&mut parents, max + i)];
+            for n in region.iter() {
+                if self.point_types[*n] == PointType::Noise {
+                    self.point_types[*n] = PointType::Border;
+                }
+            }
+        }
+        if self.borders {
+            for (core, region) in new_cores.iter() {
+                for n in region.iter() {
+                    if self.point_types[*n] == PointType::Border && self.clusters[*n] == 0 {
+                        self.clusters[*n] = self.clusters[*core];
                     }
-                    c += 1;
+                }
+            }
+        }
+        if self.point_types[idx] == PointType::Noise {
+            if let Some(core) = neighbours.iter().find(|n| self.point_types[**n] == PointType::Core) {
+                self.point_types[idx] = PointType::Border;
+                if self.borders {
+                    self.clusters[idx] = self.clusters[*core];
                 }
             }
         }
 
-        Dbscan {
-            eps: eps,
-            min_points: min_points,
-            clusters: clusters,
+        // Merges leave gaps, so renumber the clusters back to 1..=n_clusters.
+        let mut ids = self.clusters.iter().filter(|c| **c > 0).cloned().unique().collect::<Vec<usize>>();
+        ids.sort_unstable();
+        if ids.last().map_or(false, |last| *last > ids.len()) {
+            for cluster in self.clusters.iter_mut().filter(|c| **c > 0) {
+                *cluster = ids.binary_search(cluster).expect("cluster id") + 1;
+            }
         }
     }
 
-    pub fn predict(&self, data: &Array2<T>, new_data: &Array2<T>) -> Vec<Vec<usize>> {
+    pub fn labels(&self) -> Vec<Option<usize>> {
+        self.clusters.iter().map(|c| if *c > 0 { Some(c - 1) } else { None }).collect::<Vec<Option<usize>>>()
+    }
+
+    pub fn labels_i64(&self) -> Vec<i64> {
+        self.clusters.iter().map(|c| *c as i64 - 1).collect::<Vec<i64>>()
+    }
+
+    pub fn labels_array(&self) -> Array1<i64> {
+        Array1::from_vec(self.labels_i64())
+    }
+
+    pub fn n_clusters(&self) -> usize {
+        self.clusters.iter().filter(|c| **c > 0).unique().count()
+    }
+
+    pub fn noise_count(&self) -> usize {
+        self.clusters.iter().filter(|c| **c == 0).count()
+    }
+
+    pub fn try_predict(&self, new_data: &Array2<T>) -> Result<Vec<Vec<usize>>, Error> {
+        if self.data.cols() != new_data.cols() {
+            return Err(Error::DimensionMismatch {
+                expected: self.data.cols(),
+                found: new_data.cols(),
+            });
+        }
+        Ok(self.predict(new_data))
+    }
+
+    pub fn predict(&self, new_data: &Array2<T>) -> Vec<Vec<usize>> {
+        if !new_data.is_standard_layout() {
+            return self.predict(&standard_layout(new_data.view()));
+        }
+        let data = &self.data;
         let mut neighbours = Vec::with_capacity(data.rows());
-        let kdt = kdtree_init(&data);
         new_data
             .outer_iter()
             .map(|row| {
                 neighbours.clear();
-                region_query(row.as_slice().unwrap(), self.eps, &kdt, &mut neighbours);
+                self.index.within(data, row.as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
                 let neighbour_clusters = neighbours.iter().map(|idx| self.clusters[*idx]).unique().filter(|c| *c > 0).collect::<Vec<usize>>();
-                if neighbour_clusters.len() > 0 {
+                if !neighbour_clusters.is_empty() {
                     neighbour_clusters
                 } else {
                     vec![0]
@@ -86,7 +620,269 @@ impl<T: Float + One + Zero> Dbscan<T> {
     }
 }
 
-fn kdtree_init<'a, T: Float + One + Zero>(data: &'a Array2<T>) -> KdTree<T, usize, &'a [T]> {
+#[cfg(feature = "parallel")]
+impl<T: Float + One + Zero + Send + Sync> Dbscan<T> {
+    pub fn par_new(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>) -> Result<Dbscan<T>, Error> {
+        Self::par_with_metric(data, eps, min_points, borders, weights, Euclidean)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Float + One + Zero + Send + Sync, M: Metric<T> + Send + Sync> Dbscan<T, M> {
+    pub fn par_with_metric(data: &Array2<T>, eps: T, min_points: usize, borders: bool, weights: Option<&[T]>, metric: M) -> Result<Dbscan<T, M>, Error> {
+        validate(data, eps, weights)?;
+        if !data.is_standard_layout() {
+            return Self::par_with_metric(&standard_layout(data.view()), eps, min_points, borders, weights, metric);
+        }
+        let n = data.rows();
+        let index = SpatialIndex::new(data, eps, &metric, NeighborIndex::Auto)?;
+
+        let threads = std::thread::available_parallelism().map_or(1, |t| t.get());
+        let (index_ref, metric_ref) = (&index, &metric);
+        let indices = (0..n).collect::<Vec<usize>>();
+        let regions = std::thread::scope(|scope| {
+            let handles = indices
+                .chunks(((n + threads - 1) / threads).max(1))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|row_idx| {
+                                let mut neighbours = vec![];
+                                index_ref.within(data, data.row(*row_idx).as_slice().unwrap(), eps, metric_ref, &mut neighbours);
+                                neighbours.sort_unstable();
+                                neighbours.dedup();
+                                (is_core(*row_idx, &neighbours, min_points, true, weights), neighbours)
+                            })
+                            .collect::<Vec<(bool, Vec<usize>)>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles.into_iter().flat_map(|handle| handle.join().expect("dbscan region query thread")).collect::<Vec<(bool, Vec<usize>)>>()
+        });
+
+        let mut parents = (0..n).collect::<Vec<usize>>();
+        for (row_idx, (core, neighbours)) in regions.iter().enumerate() {
+            if *core {
+                for neighbour_idx in neighbours.iter().filter(|idx| regions[**idx].0) {
+                    union_nodes(&mut parents, row_idx, *neighbour_idx);
+                }
+            }
+        }
+
+        let mut roots = vec![0; n];
+        let mut c = 0;
+        let mut clusters = vec![0; n];
+        for row_idx in (0..n).filter(|idx| regions[*idx].0) {
+            let root = find_node(&mut parents, row_idx);
+            if roots[root] == 0 {
+                c += 1;
+                roots[root] = c;
+            }
+            clusters[row_idx] = roots[root];
+        }
+        let mut point_types = vec![PointType::Noise; n];
+        for (row_idx, (core, neighbours)) in regions.iter().enumerate() {
+            if *core {
+                point_types[row_idx] = PointType::Core;
+            } else if let Some(core_idx) = neighbours.iter().find(|idx| regions[**idx].0) {
+                point_types[row_idx] = PointType::Border;
+                if borders {
+                    clusters[row_idx] = clusters[*core_idx];
+                }
+            }
+        }
+        Ok(Dbscan {
+            eps,
+            min_points,
+            include_self: true,
+            borders,
+            weights: weights.map(|w| w.to_vec()),
+            clusters,
+            core_sample_indices: core_indices(&point_types),
+            point_types,
+            metric,
+            data: data.to_owned(),
+            index,
+        })
+    }
+}
+
+fn is_core<T: Float + One + Zero>(row_idx: usize, neighbours: &[usize], min_points: usize, include_self: bool, weights: Option<&[T]>) -> bool {
+    match weights {
+        Some(weights) => {
+            let total = neighbours.iter().fold(T::zero(), |acc, idx| acc + weights[*idx]);
+            let own = if include_self || !neighbours.contains(&row_idx) { T::zero() } else { weights[row_idx] };
+            total - own >= T::from(min_points).expect("T::from(usize)")
+        }
+        None => neighbours.iter().filter(|idx| include_self || **idx != row_idx).count() >= min_points,
+    }
+}
+
+pub(crate) fn expand_clusters<Q, C>(n: usize, borders: bool, mut region_query: Q, is_core: C) -> (Vec<usize>, Vec<PointType>)
+where
+    Q: FnMut(usize, &mut Vec<usize>),
+    C: Fn(usize, &[usize]) -> bool,
+{
+    let mut c = 1;
+    let mut neighbours = Vec::with_capacity(n);
+    let mut sub_neighbours = Vec::with_capacity(n);
+    let mut visited = vec![false; n];
+    let mut clusters = vec![0; n];
+    let mut point_types = vec![PointType::Noise; n];
+
+    let indices = sample(&mut thread_rng(), n, n);
+    for row_idx in indices.iter() {
+        if !visited[row_idx] {
+            visited[row_idx] = true;
+
+            neighbours.clear();
+            region_query(row_idx, &mut neighbours);
+            neighbours.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+            neighbours.dedup();
+
+            if is_core(row_idx, &neighbours) {
+                clusters[row_idx] = c;
+                point_types[row_idx] = PointType::Core;
+                while let Some(neighbour_idx) = neighbours.pop() {
+                    if borders {
+                        clusters[neighbour_idx] = c;
+                    }
+                    if point_types[neighbour_idx] == PointType::Noise {
+                        point_types[neighbour_idx] = PointType::Border;
+                    }
+                    if !visited[neighbour_idx] {
+                        visited[neighbour_idx] = true;
+                        sub_neighbours.clear();
+                        region_query(neighbour_idx, &mut sub_neighbours);
+
+                        if is_core(neighbour_idx, &sub_neighbours) {
+                            point_types[neighbour_idx] = PointType::Core;
+                            if !borders {
+                                clusters[neighbour_idx] = c;
+                            }
+                            neighbours.extend_from_slice(&sub_neighbours);
+                            neighbours.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+                            neighbours.dedup();
+                        }
+                    }
+                }
+                c += 1;
+            }
+        }
+    }
+    (clusters, point_types)
+}
+
+pub(crate) fn core_indices(point_types: &[PointType]) -> Vec<usize> {
+    point_types.iter().enumerate().filter(|(_, t)| **t == PointType::Core).map(|(i, _)| i).collect::<Vec<usize>>()
+}
+
+impl<T: Float + One + Zero> Grid<T> {
+    /// Buckets the rows into cells of side `cell`, or returns `None` when the data has more than three
+    /// columns (each query visits 3^dims cells) or a coordinate is too large for an integer cell key.
+    pub fn new(data: &Array2<T>, cell: T) -> Option<Grid<T>> {
+        assert!(cell > T::zero(), "grid cell size must be positive");
+        if data.cols() > 3 {
+            return None;
+        }
+        let mut grid = Grid { cell, cells: HashMap::new() };
+        for (idx, row) in data.outer_iter().enumerate() {
+            if !grid.insert(row.as_slice().unwrap(), idx) {
+                return None;
+            }
+        }
+        Some(grid)
+    }
+
+    /// Returns false, leaving the grid unchanged, when the row has no integer cell key.
+    pub fn insert(&mut self, row: &[T], idx: usize) -> bool {
+        match self.key(row) {
+            Some(key) => {
+                self.cells.entry(key).or_default().push(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn key(&self, row: &[T]) -> Option<Vec<i64>> {
+        row.iter().map(|x| (*x / self.cell).floor().to_i64()).collect::<Option<Vec<i64>>>()
+    }
+
+    pub fn within<M: Metric<T>>(&self, data: &Array2<T>, row: &[T], eps: T, metric: &M, neighbours: &mut Vec<usize>) {
+        let (key, reach) = match (self.key(row), (eps / self.cell).ceil().to_i64()) {
+            (Some(key), Some(reach)) if reach <= 1 => (key, reach),
+            _ => return metric_query::<T, M, Vec<T>>(data, row, eps, None, metric, neighbours),
+        };
+        let width = (2 * reach + 1) as usize;
+        let mut cell = key.clone();
+        'cells: for offset in 0..width.pow(key.len() as u32) {
+            let mut rest = offset;
+            for (dim, k) in key.iter().enumerate() {
+                match k.checked_add((rest % width) as i64 - reach) {
+                    Some(shifted) => cell[dim] = shifted,
+                    None => continue 'cells,
+                }
+                rest /= width;
+            }
+            if let Some(members) = self.cells.get(&cell) {
+                for neighbour_idx in members.iter() {
+                    if metric.distance(row, data.row(*neighbour_idx).as_slice().unwrap()) <= eps {
+                        neighbours.push(*neighbour_idx);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Float + One + Zero> SpatialIndex<T> {
+    pub fn new<M: Metric<T>>(data: &Array2<T>, eps: T, metric: &M, index: NeighborIndex) -> Result<SpatialIndex<T>, Error> {
+        Ok(match index {
+            NeighborIndex::Auto if metric.grid_compatible() && data.cols() <= 3 && eps > T::zero() => Grid::new(data, eps).map_or_else(|| SpatialIndex::fallback(data, metric), SpatialIndex::Grid),
+            NeighborIndex::Auto => SpatialIndex::fallback(data, metric),
+            NeighborIndex::BruteForce => SpatialIndex::BruteForce,
+            NeighborIndex::KdTree if !metric.kdtree_compatible() => return Err(Error::InvalidParameter("metric is not kd-tree compatible".to_string())),
+            NeighborIndex::KdTree => SpatialIndex::KdTree(kdtree_owned(data)),
+            NeighborIndex::Grid if !metric.grid_compatible() => return Err(Error::InvalidParameter("metric is not grid compatible".to_string())),
+            NeighborIndex::Grid => Grid::new(data, eps).map_or_else(|| SpatialIndex::fallback(data, metric), SpatialIndex::Grid),
+            NeighborIndex::VpTree => SpatialIndex::VpTree(VpTree::new(data, metric)),
+        })
+    }
+
+    fn fallback<M: Metric<T>>(data: &Array2<T>, metric: &M) -> SpatialIndex<T> {
+        if metric.kdtree_compatible() {
+            SpatialIndex::KdTree(kdtree_owned(data))
+        } else {
+            SpatialIndex::BruteForce
+        }
+    }
+
+    pub fn within<M: Metric<T>>(&self, data: &Array2<T>, row: &[T], eps: T, metric: &M, neighbours: &mut Vec<usize>) {
+        match self {
+            SpatialIndex::BruteForce => metric_query::<T, M, Vec<T>>(data, row, eps, None, metric, neighbours),
+            SpatialIndex::KdTree(kdt) => metric_query(data, row, eps, Some(kdt), metric, neighbours),
+            SpatialIndex::Grid(grid) => grid.within(data, row, eps, metric, neighbours),
+            SpatialIndex::VpTree(tree) => tree.within(data, row, eps, metric, neighbours),
+        }
+    }
+
+    pub fn insert<M: Metric<T>>(&mut self, data: &Array2<T>, idx: usize, metric: &M) {
+        match self {
+            SpatialIndex::BruteForce => {}
+            SpatialIndex::KdTree(kdt) => kdt.add(data.row(idx).to_vec(), idx).expect("KdTree error adding point"),
+            SpatialIndex::Grid(grid) => {
+                if !grid.insert(data.row(idx).as_slice().unwrap(), idx) {
+                    *self = SpatialIndex::fallback(data, metric);
+                }
+            }
+            SpatialIndex::VpTree(tree) => tree.insert(data, idx, metric),
+        }
+    }
+}
+
+pub(crate) fn kdtree_init<T: Float + One + Zero>(data: &Array2<T>) -> KdTree<T, usize, &[T]> {
     let mut kdt = KdTree::new(data.cols());
     for (idx, row) in data.outer_iter().enumerate() {
         kdt.add(row.into_slice().unwrap(), idx).unwrap();
@@ -94,12 +890,288 @@ fn kdtree_init<'a, T: Float + One + Zero>(data: &'a Array2<T>) -> KdTree<T, usiz
     kdt
 }
 
-fn region_query<'a, T: Float + One + Zero>(row: &'a [T], eps: T, kdt: &KdTree<T, usize, &'a [T]>, neighbours: &mut Vec<usize>) {
+pub(crate) fn kdtree_owned<T: Float + One + Zero>(data: &Array2<T>) -> KdTree<T, usize, Vec<T>> {
+    let mut kdt = KdTree::new(data.cols());
+    for (idx, row) in data.outer_iter().enumerate() {
+        kdt.add(row.to_vec(), idx).unwrap();
+    }
+    kdt
+}
+
+pub(crate) fn region_query<'a, T: Float + One + Zero>(row: &'a [T], eps: T, kdt: &KdTree<T, usize, &'a [T]>, neighbours: &mut Vec<usize>) {
     for (_, neighbour_idx) in kdt.within(row, eps.powi(2), &squared_euclidean).expect("KdTree error checking point") {
         neighbours.push(*neighbour_idx);
     }
 }
 
+pub(crate) fn metric_query<T: Float + One + Zero, M: Metric<T>, U: AsRef<[T]>>(data: &Array2<T>, row: &[T], eps: T, kdt: Option<&KdTree<T, usize, U>>, metric: &M, neighbours: &mut Vec<usize>) {
+    match kdt {
+        Some(kdt) => {
+            for (_, neighbour_idx) in kdt.within(row, eps, &|a: &[T], b: &[T]| metric.distance(a, b)).expect("KdTree error checking point") {
+                neighbours.push(*neighbour_idx);
+            }
+        }
+        None => {
+            for (neighbour_idx, other) in data.outer_iter().enumerate() {
+                if metric.distance(row, other.as_slice().unwrap()) <= eps {
+                    neighbours.push(neighbour_idx);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn region_query_with_distances<'a, T: Float + One + Zero>(row: &'a [T], eps: T, kdt: &KdTree<T, usize, &'a [T]>, neighbours: &mut Vec<(T, usize)>) {
+    for (distance, neighbour_idx) in kdt.within(row, eps.powi(2), &squared_euclidean).expect("KdTree error checking point") {
+        neighbours.push((distance.sqrt(), *neighbour_idx));
+    }
+}
+
+pub fn blocked<T, F>(n: usize, eps: T, min_points: usize, borders: bool, nblocks: usize, range: (T, T), mut load: F) -> Result<Vec<usize>, Error>
+where
+    T: Float + One + Zero,
+    F: FnMut(T, T) -> (Vec<usize>, Array2<T>),
+{
+    if nblocks == 0 {
+        return Err(Error::InvalidParameter("need at least one block".to_string()));
+    }
+    let width = (range.1 - range.0) / T::from(nblocks).expect("T::from(usize)");
+    let mut home = vec![None; n];
+    let mut core = vec![false; n];
+    let mut halo = vec![];
+    let mut parents = vec![];
+    for block in 0..nblocks {
+        let start = if block == 0 { T::neg_infinity() } else { range.0 + width * T::from(block).expect("T::from(usize)") };
+        let end = if block + 1 == nblocks { T::infinity() } else { range.0 + width * T::from(block + 1).expect("T::from(usize)") };
+        let (indices, data) = load(start - eps, end + eps);
+        if indices.is_empty() {
+            continue;
+        }
+        let local = Dbscan::new(&data, eps, min_points, true, None)?;
+        let offset = parents.len();
+        parents.extend(offset..offset + local.clusters.iter().max().map_or(0, |c| *c));
+        let mut neighbours = vec![];
+        for (row_idx, global) in indices.iter().enumerate() {
+            let x = data[[row_idx, 0]];
+            if x >= start && x < end {
+                home[*global] = if local.clusters[row_idx] > 0 { Some(offset + local.clusters[row_idx] - 1) } else { None };
+                core[*global] = local.point_types[row_idx] == PointType::Core;
+            } else {
+                // A halo point can look like a border here while being core in its home block, so keep every local
+                // cluster with a core point within eps rather than the single label it was given.
+                neighbours.clear();
+                local.index.within(&local.data, local.data.row(row_idx).as_slice().unwrap(), eps, &local.metric, &mut neighbours);
+                for cluster in neighbours.iter().filter(|idx| local.point_types[**idx] == PointType::Core).map(|idx| local.clusters[*idx]).unique() {
+                    halo.push((*global, offset + cluster - 1));
+                }
+            }
+        }
+    }
+
+    for (global, node) in halo.iter() {
+        if core[*global] {
+            union_nodes(&mut parents, home[*global].expect("core point has a home cluster"), *node);
+        }
+    }
+    let mut roots = vec![0; parents.len()];
+    let mut c = 0;
+    let mut label = |parents: &mut Vec<usize>, node: usize| {
+        let root = find_node(parents, node);
+        if roots[root] == 0 {
+            c += 1;
+            roots[root] = c;
+        }
+        roots[root]
+    };
+    let mut clusters = vec![0; n];
+    for global in 0..n {
+        if core[global] {
+            clusters[global] = label(&mut parents, home[global].expect("core point has a home cluster"));
+        }
+    }
+    if borders {
+        for (global, node) in home.iter().enumerate().filter_map(|(global, node)| node.map(|node| (global, node))).chain(halo) {
+            if !core[global] && clusters[global] == 0 {
+                clusters[global] = label(&mut parents, node);
+            }
+        }
+    }
+    Ok(clusters)
+}
+
+fn find_node(parents: &mut [usize], mut idx: usize) -> usize {
+    while parents[idx] != idx {
+        parents[idx] = parents[parents[idx]];
+        idx = parents[idx];
+    }
+    idx
+}
+
+fn union_nodes(parents: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find_node(parents, a), find_node(parents, b));
+    if a != b {
+        parents[a.max(b)] = a.min(b);
+    }
+}
+
+pub fn k_distances<T: Float + One + Zero>(data: &Array2<T>, k: usize) -> Vec<T> {
+    assert!(k > 0 && k < data.rows(), "k must be between 1 and the number of rows - 1");
+    if !data.is_standard_layout() {
+        return k_distances(&standard_layout(data.view()), k);
+    }
+    let kdt = kdtree_init(data);
+    let mut distances = data
+        .outer_iter()
+        .map(|row| kdt.nearest(row.as_slice().unwrap(), k + 1, &squared_euclidean).expect("KdTree error checking point")[k].0.sqrt())
+        .collect::<Vec<T>>();
+    distances.sort_by(|a, b| a.partial_cmp(b).expect("PartialOrd k-distance"));
+    distances
+}
+
+pub fn knee<T: Float + One + Zero>(sorted: &[T]) -> T {
+    assert!(!sorted.is_empty(), "need at least one distance");
+    let last = T::from(sorted.len() - 1).expect("T::from(usize)");
+    let (first, rise) = (sorted[0], sorted[sorted.len() - 1] - sorted[0]);
+    if last == T::zero() || rise <= T::zero() {
+        return sorted[0];
+    }
+    sorted
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (*d, T::from(i).expect("T::from(usize)") / last - (*d - first) / rise))
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd knee gap"))
+        .expect("max knee gap")
+        .0
+}
+
+pub fn suggest_eps<T: Float + One + Zero>(data: &Array2<T>, k: usize) -> T {
+    knee(&k_distances(data, k))
+}
+
+impl<T: Float + One + Zero, M: Metric<T>> Predict<Array2<T>> for Dbscan<T, M> {
+    fn predict(&self, data: &Array2<T>) -> Vec<Option<usize>> {
+        self.predict_labels(data).into_iter().map(|(label, _)| label.checked_sub(1)).collect::<Vec<Option<usize>>>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepResult<T: Float + One + Zero> {
+    pub eps: T,
+    pub min_points: usize,
+    pub clusters: Vec<usize>,
+    pub n_clusters: usize,
+    pub noise_count: usize,
+    pub core_count: usize,
+}
+
+pub fn sweep<T: Float + One + Zero>(data: &Array2<T>, eps_values: &[T], min_points_values: &[usize]) -> Vec<SweepResult<T>> {
+    if !data.is_standard_layout() {
+        return sweep(&standard_layout(data.view()), eps_values, min_points_values);
+    }
+    if eps_values.is_empty() {
+        return vec![];
+    }
+    let max_eps = eps_values.iter().cloned().fold(T::neg_infinity(), T::max);
+    let kdt = kdtree_init(data);
+    let candidates = data
+        .outer_iter()
+        .map(|row| {
+            let mut neighbours = vec![];
+            region_query_with_distances(row.into_slice().unwrap(), max_eps, &kdt, &mut neighbours);
+            neighbours
+        })
+        .collect::<Vec<Vec<(T, usize)>>>();
+
+    let mut results = Vec::with_capacity(eps_values.len() * min_points_values.len());
+    for eps in eps_values.iter() {
+        let lists = candidates
+            .iter()
+            .map(|neighbours| neighbours.iter().filter(|(d, _)| *d <= *eps).map(|(_, idx)| *idx).collect::<Vec<usize>>())
+            .collect::<Vec<Vec<usize>>>();
+        for min_points in min_points_values.iter() {
+            let (clusters, point_types) = expand_clusters(
+                data.rows(),
+                true,
+                |row_idx, neighbours| neighbours.extend_from_slice(&lists[row_idx]),
+                |row_idx, _| lists[row_idx].len() >= *min_points,
+            );
+            results.push(SweepResult {
+                eps: *eps,
+                min_points: *min_points,
+                n_clusters: clusters.iter().filter(|c| **c > 0).unique().count(),
+                noise_count: clusters.iter().filter(|c| **c == 0).count(),
+                core_count: point_types.iter().filter(|t| **t == PointType::Core).count(),
+                clusters,
+            });
+        }
+    }
+    results
+}
+
+#[derive(Debug)]
+pub struct CompactDbscan<T: Float + One + Zero, M: Metric<T> = Euclidean> {
+    pub eps: T,
+    pub cores: Array2<T>,
+    pub clusters: Vec<usize>,
+    pub core_sample_indices: Vec<usize>,
+    pub metric: M,
+    pub index: SpatialIndex<T>,
+}
+
+impl<T: Float + One + Zero, M: Metric<T>> CompactDbscan<T, M> {
+    pub fn predict(&self, new_data: &Array2<T>) -> Vec<Vec<usize>> {
+        if !new_data.is_standard_layout() {
+            return self.predict(&standard_layout(new_data.view()));
+        }
+        let mut neighbours = Vec::with_capacity(self.cores.rows());
+        new_data
+            .outer_iter()
+            .map(|row| {
+                neighbours.clear();
+                self.index.within(&self.cores, row.as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
+                let neighbour_clusters = neighbours.iter().map(|idx| self.clusters[*idx]).unique().collect::<Vec<usize>>();
+                if !neighbour_clusters.is_empty() {
+                    neighbour_clusters
+                } else {
+                    vec![0]
+                }
+            })
+            .collect::<Vec<Vec<usize>>>()
+    }
+
+    pub fn predict_labels(&self, new_data: &Array2<T>) -> Vec<(usize, bool)> {
+        if !new_data.is_standard_layout() {
+            return self.predict_labels(&standard_layout(new_data.view()));
+        }
+        let mut neighbours = Vec::with_capacity(self.cores.rows());
+        new_data
+            .outer_iter()
+            .map(|row| {
+                neighbours.clear();
+                self.index.within(&self.cores, row.as_slice().unwrap(), self.eps, &self.metric, &mut neighbours);
+                nearest_core_label(&self.cores, row.as_slice().unwrap(), &neighbours, &self.clusters, &self.metric)
+            })
+            .collect::<Vec<(usize, bool)>>()
+    }
+}
+
+impl<T: Float + One + Zero, M: Metric<T>> Predict<Array2<T>> for CompactDbscan<T, M> {
+    fn predict(&self, data: &Array2<T>) -> Vec<Option<usize>> {
+        self.predict_labels(data).into_iter().map(|(label, _)| label.checked_sub(1)).collect::<Vec<Option<usize>>>()
+    }
+}
+
+fn nearest_core_label<T: Float + One + Zero, M: Metric<T>>(data: &Array2<T>, row: &[T], cores: &[usize], clusters: &[usize], metric: &M) -> (usize, bool) {
+    let nearest = cores
+        .iter()
+        .map(|idx| (*idx, metric.distance(row, data.row(*idx).as_slice().unwrap())))
+        .min_by(|(a, da), (b, db)| da.partial_cmp(db).expect("PartialOrd distance from core").then(a.cmp(b)));
+    match nearest {
+        Some((idx, _)) => (clusters[idx], cores.iter().any(|core| clusters[*core] != clusters[idx])),
+        None => (0, false),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ClusterPrediction {
     Core(Vec<usize>),
@@ -110,11 +1182,12 @@ pub enum ClusterPrediction {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metric::{Cosine, Custom, Haversine, Jaccard, Manhattan, Minkowski};
 
     #[test]
     fn test_clusters() {
         let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -2.0, -1.0]).unwrap();
-        let model = Dbscan::new(&data, 0.5, 2, false);
+        let model = Dbscan::new(&data, 0.5, 2, false, None).unwrap();
         let clustering = dbg!(model.clusters);
         assert!(clustering.iter().take(4).all_equal());
         assert!(clustering.iter().skip(4).take(2).all_equal());
@@ -124,30 +1197,410 @@ mod tests {
         assert!(clustering[6] != clustering[0]);
     }
 
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel() {
+        let data = Array2::from_shape_vec((10, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -1.2, -2.1, -1.1, -1.9, 9.0, 9.0]).unwrap();
+        let serial = Dbscan::new(&data, 0.5, 2, true, None).unwrap();
+        let parallel = Dbscan::par_new(&data, 0.5, 2, true, None).unwrap();
+        for (a, b) in serial.clusters.iter().zip(parallel.clusters.iter()) {
+            assert_eq!(*a == 0, *b == 0);
+        }
+        for (i, j) in (0..10).tuple_combinations() {
+            assert_eq!(serial.clusters[i] == serial.clusters[j], parallel.clusters[i] == parallel.clusters[j]);
+        }
+        assert_eq!(parallel.n_clusters(), 3);
+        assert_eq!(parallel.noise_count(), 1);
+    }
+
     #[test]
     fn test_border_points() {
         let data = Array2::from_shape_vec((5, 1), vec![1.55, 2.0, 2.1, 2.2, 2.65]).unwrap();
 
-        let with = Dbscan::new(&data, 0.5, 3, true);
-        let without = Dbscan::new(&data, 0.5, 3, false);
-        let with_borders_clustering = dbg!(with.clusters);
-        let without_borders_clustering = dbg!(without.clusters);
+        let with = Dbscan::new(&data, 0.5, 3, true, None).unwrap();
+        let without = Dbscan::new(&data, 0.5, 3, false, None).unwrap();
+        let with_borders_clustering = dbg!(&with.clusters);
+        let without_borders_clustering = dbg!(&without.clusters);
         assert!(with_borders_clustering.iter().all(|x| *x == 1));
         assert!(without_borders_clustering.iter().take(1).all(|x| *x == 0));
         assert!(without_borders_clustering.iter().skip(1).take(3).all(|x| *x == 1));
         assert!(without_borders_clustering.iter().skip(4).all(|x| *x == 0));
+        assert_eq!(Dbscan::fit_predict(&data, 0.5, 3, false, None).unwrap(), vec![None, Some(0), Some(0), Some(0), None]);
+        assert_eq!(without.labels_i64(), vec![-1, 0, 0, 0, -1]);
+        assert_eq!(without.labels_array(), arr1(&[-1, 0, 0, 0, -1]));
+        assert_eq!((without.n_clusters(), without.noise_count()), (1, 2));
+        assert_eq!((with.n_clusters(), with.noise_count()), (1, 0));
+        assert_eq!(without.core_sample_indices, vec![1, 2, 3]);
+        assert_eq!(without.point_types, vec![PointType::Border, PointType::Core, PointType::Core, PointType::Core, PointType::Border]);
+        assert_eq!(with.point_types, without.point_types);
+    }
+
+    #[test]
+    fn test_nearest_core_borders() {
+        let data = Array2::from_shape_vec((9, 1), vec![0.0, 0.1, 0.2, 0.3, 0.62, 1.0, 1.1, 1.2, 1.3]).unwrap();
+        for _ in 0..10 {
+            let mut model = Dbscan::new(&data, 0.4, 4, true, None).unwrap();
+            assert_eq!(model.point_types[4], PointType::Border);
+            model.assign_borders_to_nearest_core();
+            assert_eq!(model.clusters[4], model.clusters[0]);
+            assert!(model.clusters[0] != model.clusters[5]);
+        }
+        let mut without = Dbscan::new(&data, 0.4, 4, false, None).unwrap();
+        without.assign_borders_to_nearest_core();
+        assert_eq!(without.clusters[4], without.clusters[3]);
+    }
+
+    #[test]
+    fn test_insert() {
+        let data = Array2::from_shape_vec((7, 1), vec![0.0, 0.1, 0.2, 1.0, 1.1, 1.2, 5.0]).unwrap();
+        let mut model = Dbscan::new(&data, 0.3, 3, true, None).unwrap();
+        assert_eq!((model.n_clusters(), model.noise_count()), (2, 1));
+
+        model.insert(&arr2(&[[5.1], [5.2]]));
+        assert_eq!((model.n_clusters(), model.noise_count()), (3, 0));
+        assert_eq!(model.clusters[6], model.clusters[8]);
+
+        model.insert(&arr2(&[[0.4], [0.6], [0.8]]));
+        assert_eq!(model.n_clusters(), 2);
+        assert!(Predict::predict(&model, &model.data).iter().flatten().all(|c| *c < model.n_clusters()));
+        assert!(model.clusters[..6].iter().all(|c| *c == model.clusters[0]));
+
+        let refit = Dbscan::new(&model.data, 0.3, 3, true, None).unwrap();
+        for (i, j) in (0..model.clusters.len()).tuple_combinations() {
+            assert_eq!(model.clusters[i] == model.clusters[j], refit.clusters[i] == refit.clusters[j]);
+        }
+        assert_eq!(model.core_sample_indices, refit.core_sample_indices);
+
+        // 0 and 1.8 both become core, but only through the new border point 0.9 between them.
+        let data = Array2::from_shape_vec((6, 1), vec![0.0, -0.5, -0.6, 1.8, 2.3, 2.4]).unwrap();
+        let mut model = Dbscan::new(&data, 1.0, 4, true, None).unwrap();
+        model.insert(&arr2(&[[0.9]]));
+        let refit = Dbscan::new(&model.data, 1.0, 4, true, None).unwrap();
+        assert_eq!((model.n_clusters(), refit.n_clusters()), (2, 2));
+        assert_eq!(model.core_sample_indices, refit.core_sample_indices);
+        for (i, j) in model.core_sample_indices.iter().tuple_combinations() {
+            assert_eq!(model.clusters[*i] == model.clusters[*j], refit.clusters[*i] == refit.clusters[*j]);
+        }
+        assert!(model.clusters[6] == model.clusters[0] || model.clusters[6] == model.clusters[3]);
+    }
+
+    #[test]
+    fn test_blocked() {
+        let data = Array2::from_shape_fn((60, 2), |(row, col)| if col == 0 { (row % 30) as f64 * 0.3 + (row / 30) as f64 * 20.0 } else { (row as f64 * 0.7).sin() * 0.1 });
+        let load = |start: f64, end: f64| {
+            let indices = (0..data.rows()).filter(|i| data[[*i, 0]] >= start && data[[*i, 0]] < end).collect::<Vec<usize>>();
+            let block = data.select(Axis(0), &indices);
+            (indices, block)
+        };
+        let full = Dbscan::new(&data, 0.5, 3, false, None).unwrap();
+        let clusters = blocked(data.rows(), 0.5, 3, false, 5, (0.0, 30.0), load).unwrap();
+        assert_eq!(full.n_clusters(), 2);
+        for (i, j) in (0..data.rows()).tuple_combinations() {
+            assert_eq!(full.clusters[i] == full.clusters[j], clusters[i] == clusters[j]);
+        }
+        assert_eq!(blocked(data.rows(), 0.5, 3, true, 5, (0.0, 30.0), load).unwrap().iter().filter(|c| **c == 0).count(), 0);
+
+        // p and q are core points either side of the boundary at 10, but each sees only three neighbours in the other's
+        // block, where it also touches a second local cluster (around d and c) and may take that cluster's label.
+        let bridge = arr2(&[
+            [9.9, 0.0],
+            [10.1, 0.0],
+            [10.5, 0.9],
+            [10.6, 1.5],
+            [10.3, 1.6],
+            [9.5, -0.9],
+            [9.4, -1.5],
+            [9.7, -1.6],
+            [8.95, 0.1],
+            [11.05, -0.1],
+        ]);
+        let load = |start: f64, end: f64| {
+            let indices = (0..bridge.rows()).filter(|i| bridge[[*i, 0]] >= start && bridge[[*i, 0]] < end).collect::<Vec<usize>>();
+            let block = bridge.select(Axis(0), &indices);
+            (indices, block)
+        };
+        for _ in 0..40 {
+            let clusters = blocked(bridge.rows(), 1.0, 4, false, 2, (0.0, 20.0), load).unwrap();
+            assert!([1, 2, 5].iter().all(|i| clusters[*i] == clusters[0]));
+        }
     }
 
     #[test]
     fn test_prediction() {
         let data = Array2::from_shape_vec((6, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1]).unwrap();
-        let model = Dbscan::new(&data, 0.5, 2, false);
+        let model = Dbscan::new(&data, 0.5, 2, false, None).unwrap();
 
         let new_data = Array2::from_shape_vec((2, 2), vec![1.0, 2.0, 4.0, 4.0]).unwrap();
-        let classes = dbg!(model.predict(&data, &new_data));
+        let classes = dbg!(model.predict(&new_data));
 
-        let c0 = classes.get(0).unwrap();
+        let c0 = classes.first().unwrap();
         assert!(c0.iter().any(|c| *c == model.clusters[0]));
         assert!(classes[1] == vec![0]);
     }
+
+    #[test]
+    fn test_neighbor_index() {
+        let data = Array2::from_shape_fn((60, 2), |(row, col)| ((row * 7 + col * 3) as f64 * 0.61).sin() * 0.8 + (row % 3) as f64 * 5.0);
+        let grid = Dbscan::with_index(&data, 0.4, 3, false, None, Euclidean, NeighborIndex::Grid).unwrap();
+        if let SpatialIndex::Grid(_) = grid.index {
+        } else {
+            panic!("expected a grid index");
+        }
+        for index in [NeighborIndex::KdTree, NeighborIndex::VpTree, NeighborIndex::BruteForce].iter() {
+            let other = Dbscan::with_index(&data, 0.4, 3, false, None, Euclidean, *index).unwrap();
+            assert_eq!(grid.clusters.iter().map(|c| *c == 0).collect::<Vec<bool>>(), other.clusters.iter().map(|c| *c == 0).collect::<Vec<bool>>());
+            assert_eq!(grid.n_clusters(), other.n_clusters());
+        }
+        if let SpatialIndex::Grid(_) = Dbscan::new(&data, 0.4, 3, false, None).unwrap().index {
+        } else {
+            panic!("expected a grid index by default in two dimensions");
+        }
+        assert_eq!(grid.predict(&arr2(&[[0.0, 0.0], [20.0, 20.0]]))[1], vec![0]);
+
+        let wide = Array2::from_shape_fn((20, 5), |(row, col)| (row + col) as f64 * 0.1);
+        if let SpatialIndex::KdTree(_) = Dbscan::with_index(&wide, 0.4, 3, false, None, Euclidean, NeighborIndex::Grid).unwrap().index {
+        } else {
+            panic!("expected a kd-tree fallback above three dimensions");
+        }
+        let mut huge = Dbscan::with_index(&arr2(&[[0.0, 0.0], [0.1, 0.0], [1e300, 0.0]]), 0.5, 2, false, None, Euclidean, NeighborIndex::Grid).unwrap();
+        if let SpatialIndex::KdTree(_) = huge.index {
+        } else {
+            panic!("expected a kd-tree fallback for coordinates without a cell key");
+        }
+        let mut grid = Dbscan::with_index(&data, 0.4, 3, false, None, Euclidean, NeighborIndex::Grid).unwrap();
+        grid.insert(&arr2(&[[f64::MAX, 0.0]]));
+        if let SpatialIndex::KdTree(_) = grid.index {
+        } else {
+            panic!("expected a kd-tree after inserting a point without a cell key");
+        }
+        huge.insert(&arr2(&[[0.05, 0.0]]));
+        assert_eq!(huge.predict(&arr2(&[[1e300, 0.0]]))[0], vec![0]);
+    }
+
+    #[test]
+    fn test_k_distances() {
+        let data = Array2::from_shape_vec((8, 1), vec![0.0, 0.1, 0.2, 0.3, 5.0, 5.1, 5.2, 20.0]).unwrap();
+        let distances = k_distances(&data, 2);
+        assert_eq!(distances.len(), 8);
+        assert!(distances.windows(2).all(|w| w[0] <= w[1]));
+        assert!((distances[7] - 14.9).abs() < 1e-9);
+        let eps = suggest_eps(&data, 2);
+        assert!(eps > 0.1 && eps < 1.0);
+        assert_eq!(Dbscan::new(&data, eps, 3, false, None).unwrap().n_clusters(), 2);
+
+        let fortran = Array2::from_shape_vec((4, 2).f(), vec![0.0, 0.1, 5.0, 5.2, 0.0, 0.0, 1.0, 1.0]).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(k_distances(&fortran, 1), k_distances(&standard_layout(fortran.view()), 1));
+    }
+
+    #[test]
+    fn test_anisotropic() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.1, 200.0, 0.2, 400.0, 5.0, 0.0, 5.1, 150.0, 5.2, 300.0]).unwrap();
+        let model = Dbscan::anisotropic(&data, &[0.5, 250.0], 2, false, None).unwrap();
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering.iter().skip(3).all(|x| *x == clustering[3] && *x > 0));
+        assert!(clustering[0] != clustering[3]);
+        assert_eq!(model.predict(&arr2(&[[0.3, 300.0], [2.5, 0.0]])), vec![vec![clustering[0]], vec![0]]);
+        assert!(Dbscan::new(&data, 0.5, 2, false, None).unwrap().clusters.iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn test_periodic() {
+        let data = Array2::from_shape_vec((6, 2), vec![359.0, 1.0, 0.5, 1.2, 1.5, 0.9, 180.0, 1.0, 181.0, 1.1, 10.0, 50.0]).unwrap();
+        let model = Dbscan::periodic(&data, 2.0, 2, false, None, &[Some(360.0), None]).unwrap();
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering[3] > 0 && clustering[3] == clustering[4] && clustering[3] != clustering[0]);
+        assert_eq!(clustering[5], 0);
+        let flat = Dbscan::new(&data, 2.0, 2, false, None).unwrap();
+        assert!(flat.clusters[0] != flat.clusters[1]);
+    }
+
+    #[test]
+    fn test_views() {
+        let data = Array2::from_shape_vec((2, 8), vec![1.0, 1.1, 0.9, 1.0, -2.0, -2.2, -1.0, -2.0, 2.0, 2.2, 1.9, 2.1, 3.0, 3.1, -2.0, -1.0]).unwrap();
+        let transposed = data.t();
+        let standard = standard_layout(transposed);
+        assert!(!transposed.is_standard_layout() && standard.is_standard_layout());
+        let viewed = Dbscan::from_view(transposed, 0.5, 2, false, None).unwrap();
+        let owned = Dbscan::new(&transposed.to_owned(), 0.5, 2, false, None).unwrap();
+        for (i, j) in (0..8).tuple_combinations() {
+            assert_eq!(viewed.clusters[i] == viewed.clusters[j], owned.clusters[i] == owned.clusters[j]);
+        }
+        let new_data = arr2(&[[1.0, 4.0], [2.0, 4.0]]);
+        assert_eq!(viewed.predict(&new_data.reversed_axes()), viewed.predict(&arr2(&[[1.0, 2.0], [4.0, 4.0]])));
+    }
+
+    #[test]
+    fn test_include_self() {
+        let data = Array2::from_shape_vec((6, 1), vec![0.0, 0.1, 0.2, 5.0, 5.1, 9.0]).unwrap();
+        let including = Dbscan::new(&data, 0.15, 2, false, None).unwrap();
+        let excluding = Dbscan::with_options(&data, 0.15, 2, false, false, None, Euclidean, NeighborIndex::Auto).unwrap();
+        assert_eq!(including.core_sample_indices, vec![0, 1, 2, 3, 4]);
+        assert_eq!(excluding.core_sample_indices, vec![1]);
+        assert_eq!(Dbscan::with_options(&data, 0.15, 1, false, false, None, Euclidean, NeighborIndex::Auto).unwrap().core_sample_indices, including.core_sample_indices);
+
+        let new_data = arr2(&[[0.05], [5.2], [7.0]]);
+        let c = including.clusters[0];
+        assert_eq!(including.predict_points(&new_data), vec![ClusterPrediction::Core(vec![c]), ClusterPrediction::Core(vec![including.clusters[3]]), ClusterPrediction::Noise]);
+        assert_eq!(excluding.predict_points(&new_data)[0], ClusterPrediction::Core(vec![excluding.clusters[1]]));
+        assert_eq!(excluding.predict_points(&new_data)[1], ClusterPrediction::Noise);
+    }
+
+    #[test]
+    fn test_predict_labels() {
+        let data = arr2(&[[0.0], [0.4], [0.8], [1.7], [2.1], [2.5]]);
+        let model = Dbscan::new(&data, 0.5, 2, true, None).unwrap();
+        let (left, right) = (model.clusters[0], model.clusters[5]);
+        assert!(left != right);
+        let new_data = arr2(&[[0.1], [1.15], [1.22], [1.28], [1.6], [5.0]]);
+        assert_eq!(model.predict_labels(&new_data), vec![(left, false), (left, false), (left, true), (right, true), (right, false), (0, false)]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let data = arr2(&[[0.0], [0.4], [0.8], [1.2], [1.7], [2.1], [2.5], [9.0]]);
+        let model = Dbscan::new(&data, 0.5, 3, true, None).unwrap();
+        let new_data = arr2(&[[0.1], [1.15], [1.22], [1.6], [5.0]]);
+        let labels = model.predict_labels(&new_data);
+        let core_clusters = model.core_sample_indices.iter().map(|idx| model.clusters[*idx]).collect::<Vec<usize>>();
+        let compact = model.into_compact();
+        assert_eq!(compact.cores.rows(), 5);
+        assert_eq!(compact.clusters, core_clusters);
+        assert_eq!(compact.predict_labels(&new_data), labels);
+        assert_eq!(compact.predict(&new_data)[4], vec![0]);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -2.0, -1.0]).unwrap();
+        let results = sweep(&data, &[0.5, 2.0], &[2, 3]);
+        assert_eq!(results.iter().map(|r| (r.eps, r.min_points)).collect::<Vec<(f64, usize)>>(), vec![(0.5, 2), (0.5, 3), (2.0, 2), (2.0, 3)]);
+        for result in results.iter() {
+            let model = Dbscan::new(&data, result.eps, result.min_points, true, None).unwrap();
+            assert_eq!((result.n_clusters, result.noise_count, result.core_count), (model.n_clusters(), model.noise_count(), model.core_sample_indices.len()));
+        }
+        assert_eq!((results[0].n_clusters, results[0].noise_count), (2, 2));
+        assert!(sweep(&data, &[], &[2]).is_empty());
+    }
+
+    #[test]
+    fn test_builder() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -2.0, -1.0]).unwrap();
+        let model = Fit::fit(&DbscanBuilder::new().eps(0.5).min_points(2).borders(false), &data).unwrap();
+        assert_eq!(model.point_types, Dbscan::new(&data, 0.5, 2, false, None).unwrap().point_types);
+        assert_eq!(model.clusters[..4], [model.clusters[0]; 4]);
+        assert_eq!((model.n_clusters(), model.noise_count()), (2, 2));
+
+        let manhattan = Fit::fit(&DbscanBuilder::new().eps(0.6).min_points(2).metric(Manhattan).index(NeighborIndex::VpTree), &arr2(&[[0.0, 0.0], [0.4, 0.4], [5.0, 5.0]])).unwrap();
+        assert_eq!(manhattan.noise_count(), 3);
+        let weighted = Fit::fit(&DbscanBuilder::new().eps(0.5).min_points(3).weights(vec![3.0, 1.0, 1.0]), &arr2(&[[0.0], [0.1], [5.0]])).unwrap();
+        assert_eq!(weighted.core_sample_indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_csr() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -2.0, -1.0]).unwrap();
+        let dense = Dbscan::new(&data, 0.5, 2, false, None).unwrap();
+        let sparse = DbscanLabels::from_csr(&CsrMatrix::from_dense(&data), 0.5, 2, false);
+        assert_eq!(sparse.point_types, dense.point_types);
+        assert_eq!(sparse.n_clusters(), dense.n_clusters());
+    }
+
+    #[test]
+    fn test_manhattan() {
+        let data = Array2::from_shape_vec((4, 2), vec![0.0, 0.0, 0.4, 0.4, 5.0, 5.0, 5.4, 5.4]).unwrap();
+        let euclidean = Dbscan::new(&data, 0.6, 2, false, None).unwrap();
+        let manhattan = Dbscan::with_metric(&data, 0.6, 2, false, None, Manhattan).unwrap();
+        assert!(euclidean.clusters.iter().all(|x| *x > 0));
+        assert!(manhattan.clusters.iter().all(|x| *x == 0));
+        let chebyshev = Dbscan::with_metric(&data, 0.5, 2, false, None, Minkowski::new(f64::INFINITY)).unwrap();
+        assert!(chebyshev.clusters.iter().all(|x| *x > 0));
+        let l1 = Dbscan::with_metric(&data, 0.6, 2, false, None, Minkowski::new(1.0)).unwrap();
+        assert_eq!(l1.clusters, manhattan.clusters);
+    }
+
+    #[test]
+    fn test_cosine() {
+        let data = Array2::from_shape_vec((6, 2), vec![1.0, 0.1, 10.0, 0.9, 0.2, 0.03, 0.1, 1.0, 1.1, 10.0, 0.02, 0.2]).unwrap();
+        let model = Dbscan::with_metric(&data, 0.05, 2, false, None, Cosine).unwrap();
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering.iter().skip(3).all(|x| *x == clustering[3] && *x > 0));
+        assert!(clustering[0] != clustering[3]);
+
+        let new_data = Array2::from_shape_vec((2, 2), vec![100.0, 9.0, -1.0, 0.0]).unwrap();
+        assert_eq!(model.predict(&new_data), vec![vec![clustering[0]], vec![0]]);
+    }
+
+    #[test]
+    fn test_haversine() {
+        let data = Array2::from_shape_vec((5, 2), vec![48.8566, 2.3522, 48.8606, 2.3376, 48.8530, 2.3499, 51.5074, -0.1278, 51.5007, -0.1246]).unwrap();
+        let model = Dbscan::with_metric(&data, 2_000.0, 2, false, None, Haversine::meters()).unwrap();
+        let clustering = dbg!(model.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering.iter().skip(3).all(|x| *x == clustering[3] && *x > 0));
+        assert!(clustering[0] != clustering[3]);
+    }
+
+    #[test]
+    fn test_closure_metric() {
+        let data = Array2::from_shape_vec((6, 1), vec![1.0, 2.0, 3.0, 10.0, 20.0, 30.0]).unwrap();
+        let log_distance = |a: &[f64], b: &[f64]| (a[0].ln() - b[0].ln()).abs();
+        let brute = Dbscan::with_metric(&data, 0.75, 2, false, None, log_distance).unwrap();
+        let clustering = dbg!(brute.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering.iter().skip(3).all(|x| *x == clustering[3] && *x > 0));
+        assert!(clustering[0] != clustering[3]);
+
+        let tree = Dbscan::with_metric(&data, 1.5, 2, false, None, Custom::new(|a: &[f64], b: &[f64]| (a[0] - b[0]).abs(), true)).unwrap();
+        assert!(tree.clusters.iter().take(3).all(|x| *x == tree.clusters[0] && *x > 0));
+        assert!(tree.clusters.iter().skip(3).all(|x| *x == 0));
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let data = Array2::from_shape_vec((5, 2), vec![0b1111, 0, 0b1110, 0, 0b0111, 1, u64::MAX, u64::MAX, u64::MAX - 1, u64::MAX]).unwrap();
+        let model = DbscanLabels::from_fingerprints(&data, 2, 2, false);
+        let clustering = dbg!(model.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering.iter().skip(3).all(|x| *x == clustering[3] && *x > 0));
+        assert!(clustering[0] != clustering[3]);
+
+        let fortran = Array2::from_shape_vec((5, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = DbscanLabels::from_fingerprints(&fortran, 2, 2, false).clusters;
+        assert!(transposed.iter().take(3).all(|x| *x == transposed[0] && *x > 0));
+        assert!(transposed.iter().skip(3).all(|x| *x == transposed[3] && *x > 0));
+        assert!(transposed[0] != transposed[3]);
+    }
+
+    #[test]
+    fn test_sets() {
+        let sets = vec![vec![0, 1, 2], vec![0, 1, 3], vec![0, 1, 2, 3], vec![7, 8], vec![7, 8, 9], vec![4]];
+        let model = DbscanLabels::from_sets(&sets, 0.5, 2, false);
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(3).all(|x| *x == clustering[0] && *x > 0));
+        assert!(clustering.iter().skip(3).take(2).all(|x| *x == clustering[3] && *x > 0));
+        assert!(clustering[0] != clustering[3] && clustering[5] == 0);
+        assert_eq!((model.n_clusters(), model.noise_count(), model.labels()[5]), (2, 1, None));
+
+        let indicators = Array2::from_shape_fn((6, 10), |(i, j)| if sets[i].contains(&j) { 1.0 } else { 0.0 });
+        assert_eq!(Dbscan::with_metric(&indicators, 0.5, 2, false, None, Jaccard).unwrap().clusters.iter().filter(|x| **x == 0).count(), 1);
+        assert_eq!(Dbscan::with_index(&indicators, 0.5, 2, false, None, Jaccard, NeighborIndex::VpTree).unwrap().noise_count(), 1);
+    }
+
+    #[test]
+    fn test_sample_weights() {
+        let data = Array2::from_shape_vec((5, 1), vec![0.0, 0.1, 5.0, 10.0, 10.1]).unwrap();
+        let unweighted = Dbscan::new(&data, 0.5, 3, false, None).unwrap();
+        assert!(unweighted.clusters.iter().all(|x| *x == 0));
+
+        let weighted = Dbscan::new(&data, 0.5, 3, false, Some(&[2.0, 1.0, 3.0, 1.0, 1.0])).unwrap();
+        let clustering = dbg!(weighted.clusters);
+        assert!(clustering[0] > 0 && clustering[0] == clustering[1]);
+        assert!(clustering[2] > 0 && clustering[2] != clustering[0]);
+        assert!(clustering[3] == 0 && clustering[4] == 0);
+    }
 }
@@ -0,0 +1,106 @@
+use crate::kmeans::Kmeans;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone)]
+pub struct DivisiveNode {
+    pub depth: usize,
+    pub members: Vec<usize>,
+    pub children: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct Divisive {
+    pub nodes: Vec<DivisiveNode>,
+    pub levels: Vec<Vec<usize>>,
+}
+
+impl Divisive {
+    pub fn new<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, max_depth: usize, min_size: usize, iterations: usize, nseeds: usize) -> Divisive {
+        let mut nodes = vec![DivisiveNode {
+            depth: 0,
+            members: (0..data.rows()).collect(),
+            children: Vec::new(),
+        }];
+        let mut levels = vec![vec![0; data.rows()]];
+        let mut frontier = vec![0];
+
+        for depth in 1..=max_depth {
+            let mut next = Vec::with_capacity(2 * frontier.len());
+            let mut split = false;
+            for node_idx in frontier {
+                let members = &nodes[node_idx].members;
+                if members.len() < min_size.max(2) {
+                    next.push(node_idx);
+                    continue;
+                }
+                let model = Kmeans::new(&data.select(Axis(0), members), 2, iterations, nseeds).expect("two-way split of at least two rows");
+                let mut halves = vec![Vec::new(), Vec::new()];
+                for (member, c) in members.iter().zip(model.clusters.iter()) {
+                    halves[*c].push(*member);
+                }
+                if halves.iter().any(|half| half.is_empty()) {
+                    next.push(node_idx);
+                    continue;
+                }
+                for half in halves {
+                    let child_idx = nodes.len();
+                    nodes.push(DivisiveNode {
+                        depth,
+                        members: half,
+                        children: Vec::new(),
+                    });
+                    nodes[node_idx].children.push(child_idx);
+                    next.push(child_idx);
+                }
+                split = true;
+            }
+            frontier = next;
+            if !split {
+                break;
+            }
+
+            let mut labels = vec![0; data.rows()];
+            for (label, node_idx) in frontier.iter().enumerate() {
+                for member in nodes[*node_idx].members.iter() {
+                    labels[*member] = label;
+                }
+            }
+            levels.push(labels);
+        }
+
+        Divisive { nodes, levels }
+    }
+
+    pub fn leaves(&self) -> Vec<usize> {
+        (0..self.nodes.len()).filter(|idx| self.nodes[*idx].children.is_empty()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_levels() {
+        let data = Array2::from_shape_vec((8, 1), vec![0.0, 0.1, 1.0, 1.1, 10.0, 10.1, 11.0, 11.1]).unwrap();
+        let model = Divisive::new(&data, 2, 2, 20, 5);
+        assert_eq!(model.levels.len(), 3);
+        assert!(model.levels[0].iter().all(|c| *c == 0));
+
+        let level1 = dbg!(&model.levels[1]);
+        assert!(level1.iter().take(4).all_equal());
+        assert!(level1.iter().skip(4).all_equal());
+        assert!(level1[0] != level1[4]);
+
+        let level2 = dbg!(&model.levels[2]);
+        assert!(level2.chunks(2).all(|pair| pair[0] == pair[1]));
+        assert!(level2.iter().unique().count() == 4);
+        assert_eq!(model.leaves().len(), 4);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::kmeans::Kmeans;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct DpMeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub lambda: T,
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> DpMeans<T> {
+    pub fn new(data: &Array2<T>, lambda: T, iterations: usize) -> DpMeans<T> {
+        let mut centers = vec![data.mean_axis(Axis(0))];
+        let mut clusters = vec![0; data.rows()];
+        for _ in 0..iterations {
+            let mut changed = false;
+            for (row_idx, row) in data.outer_iter().enumerate() {
+                let cluster = Kmeans::predict_with_centers(&centers, &row);
+                let distance = ((&row - &centers[cluster]) * (&row - &centers[cluster])).sum().sqrt();
+                let cluster = if distance > lambda {
+                    centers.push(row.to_owned());
+                    centers.len() - 1
+                } else {
+                    cluster
+                };
+                if clusters[row_idx] != cluster {
+                    clusters[row_idx] = cluster;
+                    changed = true;
+                }
+            }
+
+            let mut sums = vec![Array1::zeros(data.cols()); centers.len()];
+            let mut counts = vec![0; centers.len()];
+            for (row, cluster) in data.outer_iter().zip(clusters.iter()) {
+                sums[*cluster] = &sums[*cluster] + &row;
+                counts[*cluster] += 1;
+            }
+            let mut relabel = vec![0; centers.len()];
+            centers = sums
+                .into_iter()
+                .zip(counts)
+                .enumerate()
+                .filter(|(_, (_, count))| *count > 0)
+                .enumerate()
+                .map(|(new, (old, (sum, count)))| {
+                    relabel[old] = new;
+                    sum / T::from(count).expect("T::from(usize)")
+                })
+                .collect::<Vec<Array1<T>>>();
+            for cluster in clusters.iter_mut() {
+                *cluster = relabel[*cluster];
+            }
+            if !changed {
+                break;
+            }
+        }
+        DpMeans { lambda, centers, clusters }
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        data.outer_iter().map(|row| Kmeans::predict_with_centers(&self.centers, &row)).collect::<Vec<usize>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_dpmeans() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -1.2, -2.1]).unwrap();
+        let model = DpMeans::new(&data, 2.0, 20);
+        assert_eq!(model.centers.len(), 3);
+        let clustering = dbg!(model.predict(&data));
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).take(2).all_equal());
+        assert!(clustering.iter().skip(6).all_equal());
+        assert!(clustering.iter().unique().count() == 3);
+    }
+}
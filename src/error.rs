@@ -0,0 +1,82 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    EmptyInput,
+    DimensionMismatch { expected: usize, found: usize },
+    InvalidParameter(String),
+    NonFiniteValue { rows: Vec<usize> },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::EmptyInput => write!(f, "input has no rows"),
+            Error::DimensionMismatch { expected, found } => write!(f, "dimension mismatch: expected {}, found {}", expected, found),
+            Error::InvalidParameter(message) => write!(f, "invalid parameter: {}", message),
+            Error::NonFiniteValue { rows } => write!(f, "non-finite values in rows {:?}", rows),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbscan::{blocked, Dbscan, DbscanBuilder, NeighborIndex};
+    use crate::kmeans::{InitMethod, Kmeans, KmeansBuilder};
+    use crate::metric::Cosine;
+    use crate::model::Fit;
+    use ndarray::prelude::*;
+    use std::f64;
+
+    #[test]
+    fn test_errors() {
+        let data = arr2(&[[0.0, 0.0], [0.1, 0.1], [5.0, 5.0], [5.1, 5.1]]);
+        let wide = Array2::zeros((1, 3));
+        assert_eq!(Kmeans::new(&data, 2, 10, 2).unwrap().try_predict(&wide), Err(Error::DimensionMismatch { expected: 2, found: 3 }));
+        assert_eq!(
+            Dbscan::new(&data, 0.5, 2, true, None).unwrap().try_predict(&wide),
+            Err(Error::DimensionMismatch { expected: 2, found: 3 })
+        );
+        assert_eq!(Dbscan::new(&data, 0.5, 2, true, None).unwrap().try_predict(&data).map(|p| p.len()), Ok(4));
+
+        let weighted = KmeansBuilder::new().k(2).weights(vec![1.0; 3]);
+        assert_eq!(Fit::fit(&weighted, &data).unwrap_err(), Error::DimensionMismatch { expected: 4, found: 3 });
+        let weighted = KmeansBuilder::new().k(2).weights(vec![1.0, f64::NAN, 1.0, f64::INFINITY]);
+        assert_eq!(Fit::fit(&weighted, &data).unwrap_err(), Error::NonFiniteValue { rows: vec![1, 3] });
+        let weighted = KmeansBuilder::new().k(2).weights(vec![1.0, -1.0, 1.0, 1.0]);
+        assert_eq!(Fit::fit(&weighted, &data).unwrap_err(), Error::InvalidParameter("weights must be non-negative".to_string()));
+        assert_eq!(
+            Fit::fit(&KmeansBuilder::new().k(2).nseeds(0), &data).unwrap_err(),
+            Error::InvalidParameter("nseeds must be at least 1".to_string())
+        );
+        let explicit = KmeansBuilder::new().k(2).init(InitMethod::Explicit(vec![arr1(&[0.0, 0.0])]));
+        assert_eq!(Fit::fit(&explicit, &data).unwrap_err(), Error::DimensionMismatch { expected: 2, found: 1 });
+        assert_eq!(explicit.build(&data).unwrap_err(), Error::DimensionMismatch { expected: 2, found: 1 });
+        assert!(KmeansBuilder::new().k(5).build_all(&data).is_err());
+        assert!(KmeansBuilder::new().k(2).weights(vec![1.0; 3]).restart(&data, 7).is_err());
+        assert_eq!(
+            Kmeans::new(&data, 5, 10, 2).unwrap_err(),
+            Error::InvalidParameter("k = 5 must be between 1 and the number of rows (4)".to_string())
+        );
+        assert_eq!(Dbscan::new(&data, 0.0, 2, true, None).unwrap_err(), Error::InvalidParameter("eps must be positive".to_string()));
+        assert_eq!(Dbscan::anisotropic(&data, &[0.5], 2, true, None).unwrap_err(), Error::DimensionMismatch { expected: 2, found: 1 });
+        assert_eq!(Dbscan::periodic(&data, 0.5, 2, true, None, &[None]).unwrap_err(), Error::DimensionMismatch { expected: 2, found: 1 });
+        assert_eq!(
+            Dbscan::with_index(&data, 0.5, 2, true, None, Cosine, NeighborIndex::KdTree).unwrap_err(),
+            Error::InvalidParameter("metric is not kd-tree compatible".to_string())
+        );
+        assert_eq!(
+            blocked(4, 0.5, 2, true, 0, (0.0, 1.0), |_, _| (vec![], data.clone())).unwrap_err(),
+            Error::InvalidParameter("need at least one block".to_string())
+        );
+        let mut nan = data.clone();
+        nan[[2, 1]] = f64::NAN;
+        let err = Fit::fit(&DbscanBuilder::new(), &nan).unwrap_err();
+        assert_eq!(err, Error::NonFiniteValue { rows: vec![2] });
+        assert_eq!(err.to_string(), "non-finite values in rows [2]");
+    }
+}
@@ -0,0 +1,157 @@
+use crate::kmeans::Kmeans;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::f64::consts::PI;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct Gmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub k: usize,
+    pub model: Kmeans<T>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Gmeans<T> {
+    pub fn new(data: &Array2<T>, kmin: usize, kmax: usize, critical_value: T, iterations: usize, nseeds: usize) -> Gmeans<T> {
+        assert!(kmin > 0 && kmin <= kmax, "kmin must be between 1 and kmax");
+        let mut model = Kmeans::new(data, kmin, iterations, nseeds).expect("k-means on finite data with kmin at most the number of rows");
+        loop {
+            let k = model.centers.len();
+            let mut budget = kmax - k;
+            let mut centers = Vec::with_capacity(kmax);
+            for (cluster, center) in model.centers.iter().enumerate() {
+                let members = (0..data.rows()).filter(|row_idx| model.clusters[*row_idx] == cluster).collect::<Vec<usize>>();
+                if budget > 0 && members.len() > 7 {
+                    let subset = data.select(Axis(0), &members);
+                    let children = split(&subset, center, iterations);
+                    let direction = &children.centers[0] - &children.centers[1];
+                    let norm = direction.dot(&direction);
+                    if norm > T::zero() {
+                        let projected = subset.outer_iter().map(|row| row.dot(&direction) / norm).collect::<Vec<T>>();
+                        if anderson_darling(&projected) > critical_value {
+                            centers.extend(children.centers);
+                            budget -= 1;
+                            continue;
+                        }
+                    }
+                }
+                centers.push(center.clone());
+            }
+            if centers.len() == k {
+                break;
+            }
+            model = Kmeans::lloyd(data, centers, iterations);
+        }
+
+        Gmeans { k: model.centers.len(), model }
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        self.model.predict(data)
+    }
+}
+
+fn split<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(subset: &Array2<T>, center: &Array1<T>, iterations: usize) -> Kmeans<T> {
+    let centered = subset - &center.view().insert_axis(Axis(0));
+    let n = T::from(subset.rows().max(2) - 1).expect("T::from(usize)");
+    let covariance = centered.t().dot(&centered) / n;
+
+    let widest = (0..subset.cols())
+        .max_by(|a, b| covariance[[*a, *a]].partial_cmp(&covariance[[*b, *b]]).expect("PartialOrd variance"))
+        .expect("non-empty dimensions");
+    let mut component = covariance.column(widest).to_owned();
+    let mut eigenvalue = T::zero();
+    for _ in 0..50 {
+        let next = covariance.dot(&component);
+        eigenvalue = next.dot(&next).sqrt();
+        if eigenvalue <= T::zero() {
+            break;
+        }
+        component = next / eigenvalue;
+    }
+
+    let offset = component * (T::from(2.0 / PI).expect("T::from(f64)") * eigenvalue).sqrt();
+    Kmeans::lloyd(subset, vec![center + &offset, center - &offset], iterations.max(1))
+}
+
+pub fn anderson_darling<T: Float + One + Zero>(values: &[T]) -> T {
+    let n = T::from(values.len()).expect("T::from(usize)");
+    let mean = values.iter().fold(T::zero(), |acc, x| acc + *x) / n;
+    let variance = values.iter().fold(T::zero(), |acc, x| acc + (*x - mean).powi(2)) / (n - T::one());
+    let std = variance.sqrt();
+    if std.is_nan() || std <= T::zero() {
+        return T::zero();
+    }
+
+    let mut z = values.iter().map(|x| (*x - mean) / std).collect::<Vec<T>>();
+    z.sort_unstable_by(|a, b| a.partial_cmp(b).expect("PartialOrd standardized value"));
+    let eps = T::from(1e-12).expect("T::from(f64)");
+    let cdf = z.iter().map(|x| normal_cdf(*x).max(eps).min(T::one() - eps)).collect::<Vec<T>>();
+    let s = (0..z.len()).fold(T::zero(), |acc, i| {
+        let weight = T::from(2 * i + 1).expect("T::from(usize)");
+        acc + weight * (cdf[i].ln() + (T::one() - cdf[z.len() - 1 - i]).ln())
+    });
+    let a2 = -n - s / n;
+    a2 * (T::one() + T::from(4.0).expect("T::from(f64)") / n - T::from(25.0).expect("T::from(f64)") / (n * n))
+}
+
+fn normal_cdf<T: Float + One + Zero>(x: T) -> T {
+    let half = T::from(0.5).expect("T::from(f64)");
+    half * (T::one() + erf(x / T::from(2.0).expect("T::from(f64)").sqrt()))
+}
+
+fn erf<T: Float + One + Zero>(x: T) -> T {
+    let coefficients = [0.254_829_592, -0.284_496_736, 1.421_413_741, -1.453_152_027, 1.061_405_429];
+    let sign = x.signum();
+    let x = x.abs();
+    let t = T::one() / (T::one() + T::from(0.327_591_1).expect("T::from(f64)") * x);
+    let poly = coefficients.iter().rev().fold(T::zero(), |acc, c| (acc + T::from(*c).expect("T::from(f64)")) * t);
+    sign * (T::one() - poly * (-x * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn blobs() -> Array2<f64> {
+        let offsets = [
+            (-0.3, 0.1),
+            (0.2, -0.4),
+            (0.0, 0.0),
+            (0.4, 0.3),
+            (-0.1, -0.2),
+            (0.1, 0.5),
+            (-0.5, -0.1),
+            (0.3, -0.2),
+            (-0.2, 0.4),
+            (0.05, -0.5),
+        ];
+        let centers = [(0.0, 0.0), (10.0, 10.0), (20.0, 0.0)];
+        let flat = centers
+            .iter()
+            .flat_map(|(cx, cy)| offsets.iter().flat_map(move |(dx, dy)| vec![cx + dx, cy + dy]))
+            .collect::<Vec<f64>>();
+        Array2::from_shape_vec((30, 2), flat).unwrap()
+    }
+
+    #[test]
+    fn test_anderson_darling() {
+        let normalish = [-1.5, -1.0, -0.7, -0.4, -0.2, 0.0, 0.2, 0.4, 0.7, 1.0, 1.5];
+        let bimodal = (0..20).map(|i| if i % 2 == 0 { -5.0 } else { 5.0 } + f64::from(i) * 0.01).collect::<Vec<f64>>();
+        assert!(anderson_darling(&normalish) < 0.5);
+        assert!(anderson_darling(&bimodal) > 1.8692);
+    }
+
+    #[test]
+    fn test_gmeans() {
+        let data = blobs();
+        let model = Gmeans::new(&data, 1, 10, 1.8692, 50, 10);
+        assert_eq!(model.k, 3);
+        let clustering = dbg!(model.predict(&data));
+        assert!(clustering.chunks(10).all(|chunk| chunk.iter().all_equal()));
+        assert!(clustering.iter().unique().count() == 3);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::dbscan::kdtree_init;
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct Graph<T: Float + One + Zero> {
+    pub adjacency: Vec<Vec<(usize, T)>>,
+}
+
+impl<T: Float + One + Zero> Graph<T> {
+    pub fn from_edges(nodes: usize, edges: &[(usize, usize, T)]) -> Graph<T> {
+        let mut adjacency = vec![Vec::new(); nodes];
+        for (a, b, weight) in edges.iter() {
+            adjacency[*a].push((*b, *weight));
+            if a != b {
+                adjacency[*b].push((*a, *weight));
+            }
+        }
+        for neighbours in adjacency.iter_mut() {
+            neighbours.sort_unstable_by_key(|(node, _)| *node);
+        }
+        Graph { adjacency }
+    }
+
+    pub fn from_dense(affinity: &Array2<T>) -> Graph<T> {
+        let adjacency = affinity
+            .outer_iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, weight)| **weight != T::zero())
+                    .map(|(node, weight)| (node, *weight))
+                    .collect::<Vec<(usize, T)>>()
+            })
+            .collect::<Vec<Vec<(usize, T)>>>();
+        Graph { adjacency }
+    }
+
+    pub fn knn(data: &Array2<T>, k: usize) -> Graph<T> {
+        if !data.is_standard_layout() {
+            return Graph::knn(&standard_layout(data.view()), k);
+        }
+        let kdt = kdtree_init(data);
+        let mut edges = Vec::with_capacity(data.rows() * k);
+        for (row_idx, row) in data.outer_iter().enumerate() {
+            let neighbours = kdt.nearest(row.as_slice().unwrap(), k + 1, &squared_euclidean).expect("KdTree error checking point");
+            for (distance, neighbour) in neighbours.into_iter().filter(|(_, neighbour)| **neighbour != row_idx).take(k) {
+                edges.push((row_idx, *neighbour, T::one() / (T::one() + distance.sqrt())));
+            }
+        }
+        let mut graph = Graph::from_edges(data.rows(), &edges);
+        for neighbours in graph.adjacency.iter_mut() {
+            neighbours.dedup_by_key(|(node, _)| *node);
+        }
+        graph
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn components(&self) -> Vec<usize> {
+        let mut labels: Vec<Option<usize>> = vec![None; self.nodes()];
+        let mut undirected = vec![Vec::new(); self.nodes()];
+        for (a, neighbours) in self.adjacency.iter().enumerate() {
+            for (b, _) in neighbours.iter() {
+                undirected[a].push(*b);
+                undirected[*b].push(a);
+            }
+        }
+        let mut next = 0;
+        for start in 0..self.nodes() {
+            if labels[start].is_some() {
+                continue;
+            }
+            let mut stack = vec![start];
+            labels[start] = Some(next);
+            while let Some(node) = stack.pop() {
+                for neighbour in undirected[node].iter() {
+                    if labels[*neighbour].is_none() {
+                        labels[*neighbour] = Some(next);
+                        stack.push(*neighbour);
+                    }
+                }
+            }
+            next += 1;
+        }
+        labels.into_iter().map(|label| label.expect("every node labelled")).collect::<Vec<usize>>()
+    }
+}
+
+#[derive(Debug)]
+pub struct Mcl<T: Float + One + Zero> {
+    pub expansion: usize,
+    pub inflation: T,
+    pub matrix: Graph<T>,
+    pub clusters: Vec<usize>,
+}
+
+impl<T: Float + One + Zero> Mcl<T> {
+    pub fn new(graph: &Graph<T>, expansion: usize, inflation: T, iterations: usize) -> Mcl<T> {
+        assert!(expansion > 1, "expansion must be at least 2");
+        let n = graph.nodes();
+        let prune = T::from(1e-5).expect("T::from(f64)");
+        let tolerance = T::from(1e-8).expect("T::from(f64)");
+
+        let mut columns = graph.adjacency.clone();
+        for (node, column) in columns.iter_mut().enumerate() {
+            if !column.iter().any(|(row, _)| *row == node) {
+                column.push((node, T::one()));
+                column.sort_unstable_by_key(|(row, _)| *row);
+            }
+        }
+        normalize(&mut columns);
+
+        let mut scratch = vec![T::zero(); n];
+        for _ in 0..iterations {
+            let mut expanded = columns.clone();
+            for _ in 1..expansion {
+                expanded = multiply(&columns, &expanded, &mut scratch);
+            }
+            for column in expanded.iter_mut() {
+                for (_, value) in column.iter_mut() {
+                    *value = value.powf(inflation);
+                }
+            }
+            normalize(&mut expanded);
+            for column in expanded.iter_mut() {
+                column.retain(|(_, value)| *value > prune);
+            }
+            normalize(&mut expanded);
+
+            let change = difference(&columns, &expanded, &mut scratch);
+            columns = expanded;
+            if change < tolerance {
+                break;
+            }
+        }
+
+        let matrix = Graph { adjacency: columns };
+        Mcl {
+            expansion,
+            inflation,
+            clusters: matrix.components(),
+            matrix,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ChineseWhispers {
+    pub iterations: usize,
+    pub clusters: Vec<usize>,
+}
+
+impl ChineseWhispers {
+    pub fn new<T: Float + One + Zero>(graph: &Graph<T>, iterations: usize) -> ChineseWhispers {
+        let mut rng = SmallRng::from_rng(thread_rng()).expect("seed SmallRng from thread_rng");
+        let mut labels = (0..graph.nodes()).collect::<Vec<usize>>();
+        let mut order = (0..graph.nodes()).collect::<Vec<usize>>();
+        let mut votes: HashMap<usize, T> = HashMap::new();
+        for _ in 0..iterations {
+            order.shuffle(&mut rng);
+            let mut changed = false;
+            for node in order.iter() {
+                votes.clear();
+                for (neighbour, weight) in graph.adjacency[*node].iter().filter(|(neighbour, _)| neighbour != node) {
+                    let vote = votes.entry(labels[*neighbour]).or_insert_with(T::zero);
+                    *vote = *vote + *weight;
+                }
+                let best = votes
+                    .iter()
+                    .max_by(|(la, a), (lb, b)| a.partial_cmp(b).expect("PartialOrd label weight").then(lb.cmp(la)))
+                    .map(|(label, _)| *label);
+                if let Some(label) = best {
+                    if label != labels[*node] {
+                        labels[*node] = label;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut relabel = HashMap::new();
+        let clusters = labels
+            .iter()
+            .map(|label| {
+                let next = relabel.len();
+                *relabel.entry(*label).or_insert(next)
+            })
+            .collect::<Vec<usize>>();
+        ChineseWhispers { iterations, clusters }
+    }
+}
+
+fn normalize<T: Float + One + Zero>(columns: &mut [Vec<(usize, T)>]) {
+    for column in columns.iter_mut() {
+        let total = column.iter().fold(T::zero(), |acc, (_, value)| acc + *value);
+        if total > T::zero() {
+            for (_, value) in column.iter_mut() {
+                *value = *value / total;
+            }
+        }
+    }
+}
+
+fn multiply<T: Float + One + Zero>(left: &[Vec<(usize, T)>], right: &[Vec<(usize, T)>], scratch: &mut [T]) -> Vec<Vec<(usize, T)>> {
+    right
+        .iter()
+        .map(|column| {
+            let mut touched = Vec::new();
+            for (k, weight) in column.iter() {
+                for (row, value) in left[*k].iter() {
+                    if scratch[*row] == T::zero() {
+                        touched.push(*row);
+                    }
+                    scratch[*row] = scratch[*row] + *weight * *value;
+                }
+            }
+            touched.sort_unstable();
+            touched
+                .into_iter()
+                .map(|row| {
+                    let value = scratch[row];
+                    scratch[row] = T::zero();
+                    (row, value)
+                })
+                .filter(|(_, value)| *value > T::zero())
+                .collect::<Vec<(usize, T)>>()
+        })
+        .collect::<Vec<Vec<(usize, T)>>>()
+}
+
+fn difference<T: Float + One + Zero>(left: &[Vec<(usize, T)>], right: &[Vec<(usize, T)>], scratch: &mut [T]) -> T {
+    let mut change = T::zero();
+    for (a, b) in left.iter().zip(right.iter()) {
+        for (row, value) in a.iter() {
+            scratch[*row] = *value;
+        }
+        for (row, value) in b.iter() {
+            change = change.max((scratch[*row] - *value).abs());
+            scratch[*row] = T::zero();
+        }
+        for (row, _) in a.iter() {
+            change = change.max(scratch[*row].abs());
+            scratch[*row] = T::zero();
+        }
+    }
+    change
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn graph() -> Graph<f64> {
+        let edges = [(0, 1, 1.0), (0, 2, 1.0), (1, 2, 1.0), (2, 3, 0.1), (3, 4, 1.0), (3, 5, 1.0), (4, 5, 1.0)];
+        Graph::from_edges(6, &edges)
+    }
+
+    #[test]
+    fn test_components() {
+        let graph = Graph::from_edges(5, &[(0, 1, 1.0), (3, 4, 1.0)]);
+        assert_eq!(graph.components(), vec![0, 0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_chinese_whispers() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap();
+        let model = ChineseWhispers::new(&Graph::knn(&data, 3), 20);
+        let clustering = dbg!(model.clusters);
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(Graph::knn(&data, 3).adjacency, Graph::knn(&fortran, 3).adjacency);
+    }
+
+    #[test]
+    fn test_mcl() {
+        let model = Mcl::new(&graph(), 2, 2.0, 100);
+        let clustering = dbg!(model.clusters);
+        assert!(clustering.iter().take(3).all_equal());
+        assert!(clustering.iter().skip(3).all_equal());
+        assert!(clustering[0] != clustering[3]);
+    }
+}
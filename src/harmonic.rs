@@ -0,0 +1,114 @@
+use crate::metric::{Euclidean, Metric};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::prelude::IteratorRandom;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct KHarmonicMeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub p: T,
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+    pub performance: T,
+    pub n_iter: usize,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> KHarmonicMeans<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, p: T, iterations: usize) -> KHarmonicMeans<T> {
+        assert!(nclust > 0 && nclust <= data.rows(), "nclust must be between 1 and the number of rows");
+        let centers = data.outer_iter().choose_multiple(&mut thread_rng(), nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>();
+        Self::with_centers(data, centers, p, iterations)
+    }
+
+    pub fn with_centers(data: &Array2<T>, mut centers: Vec<Array1<T>>, p: T, iterations: usize) -> KHarmonicMeans<T> {
+        assert!(p >= T::one(), "p must be at least 1");
+        if !data.is_standard_layout() {
+            return Self::with_centers(&standard_layout(data.view()), centers, p, iterations);
+        }
+        let mut n_iter = 0;
+        for _ in 0..iterations {
+            n_iter += 1;
+            let mut sums = vec![Array1::zeros(data.cols()); centers.len()];
+            let mut totals = vec![T::zero(); centers.len()];
+            for row in data.outer_iter() {
+                let distances = Self::distances(&centers, &row);
+                let inverse = distances.iter().map(|d| d.powf(-p - T::one() - T::one())).collect::<Vec<T>>();
+                let harmonic = distances.iter().map(|d| d.powf(-p)).sum::<T>();
+                let norm = inverse.iter().cloned().sum::<T>();
+                let weight = norm / (harmonic * harmonic);
+                for (j, q) in inverse.iter().enumerate() {
+                    let factor = *q / norm * weight;
+                    sums[j] = &sums[j] + &(&row * factor);
+                    totals[j] += factor;
+                }
+            }
+            let updated = sums.into_iter().zip(totals).map(|(sum, total)| sum / total).collect::<Vec<Array1<T>>>();
+            if updated == centers {
+                break;
+            }
+            centers = updated;
+        }
+
+        let clusters = data.outer_iter().map(|row| Self::nearest(&centers, &row)).collect::<Vec<usize>>();
+        let k = T::from(centers.len()).expect("T::from(usize)");
+        let performance = data.outer_iter().map(|row| k / Self::distances(&centers, &row).iter().map(|d| d.powf(-p)).sum::<T>()).sum::<T>();
+        KHarmonicMeans {
+            p,
+            centers,
+            clusters,
+            performance,
+            n_iter,
+        }
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()));
+        }
+        data.outer_iter().map(|row| Self::nearest(&self.centers, &row)).collect::<Vec<usize>>()
+    }
+
+    fn distances(centers: &[Array1<T>], row: &ArrayView1<T>) -> Vec<T> {
+        centers
+            .iter()
+            .map(|center| Euclidean.distance(row.as_slice().unwrap(), center.as_slice().unwrap()).max(T::epsilon()))
+            .collect::<Vec<T>>()
+    }
+
+    fn nearest(centers: &[Array1<T>], row: &ArrayView1<T>) -> usize {
+        Self::distances(centers, row)
+            .into_iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from center"))
+            .expect("min distance from center")
+            .0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harmonic() {
+        let data = Array2::from_shape_vec((8, 2), vec![0.0, 0.0, 1.0, 0.5, 0.5, 1.5, 1.5, 1.0, 8.0, 8.0, 9.5, 7.5, 7.0, 8.5, 8.5, 9.0]).unwrap();
+        let model = KHarmonicMeans::with_centers(&data, vec![arr1(&[0.0, 0.0]), arr1(&[4.0, 4.0])], 3.5, 100);
+        let clusters = &model.clusters;
+        assert!(clusters[..4].iter().all(|c| *c == clusters[0]));
+        assert!(clusters[4..].iter().all(|c| *c == clusters[4]));
+        assert!(clusters[0] != clusters[4]);
+        assert_eq!(model.predict(&arr2(&[[0.1, 0.1], [8.0, 8.1]])), vec![clusters[0], clusters[4]]);
+        assert!(model.performance < KHarmonicMeans::with_centers(&data, vec![arr1(&[0.0, 0.0]), arr1(&[4.0, 4.0])], 3.5, 1).performance);
+        assert_eq!(KHarmonicMeans::new(&data, 2, 3.5, 100).centers.len(), 2);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(KHarmonicMeans::with_centers(&fortran, vec![arr1(&[0.0, 0.0]), arr1(&[4.0, 4.0])], 3.5, 100).clusters, model.clusters);
+        assert_eq!(model.predict(&fortran), model.clusters);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::dbscan::kdtree_init;
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug)]
+pub struct Hdbscan<T: Float + One + Zero> {
+    pub min_cluster_size: usize,
+    pub min_samples: usize,
+    pub clusters: Vec<usize>,
+    pub strengths: Vec<T>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Child {
+    Point(usize),
+    Cluster(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CondensedEdge<T> {
+    parent: usize,
+    child: Child,
+    lambda: T,
+    size: usize,
+}
+
+impl<T: Float + One + Zero> Hdbscan<T> {
+    pub fn new(data: &Array2<T>, min_cluster_size: usize, min_samples: usize) -> Hdbscan<T> {
+        assert!(min_cluster_size > 1, "min_cluster_size must be at least 2");
+        assert!(min_samples > 0, "min_samples must be at least 1");
+        if !data.is_standard_layout() {
+            return Hdbscan::new(&standard_layout(data.view()), min_cluster_size, min_samples);
+        }
+
+        let core_distances = core_distances(data, min_samples);
+        let mst = mutual_reachability_mst(data, &core_distances);
+        let (children, distances, sizes) = single_linkage_tree(data.rows(), mst);
+        let (condensed, births) = condense_tree(data.rows(), &children, &distances, &sizes, min_cluster_size);
+        let selected = select_clusters(&condensed, &births);
+        let (clusters, strengths) = label_points(data.rows(), &condensed, &selected);
+
+        Hdbscan {
+            min_cluster_size,
+            min_samples,
+            clusters,
+            strengths,
+        }
+    }
+}
+
+fn core_distances<T: Float + One + Zero>(data: &Array2<T>, min_samples: usize) -> Vec<T> {
+    let kdt = kdtree_init(data);
+    data.outer_iter()
+        .map(|row| {
+            kdt.nearest(row.as_slice().unwrap(), min_samples, &squared_euclidean)
+                .expect("KdTree error checking point")
+                .last()
+                .map_or(T::zero(), |(distance, _)| distance.sqrt())
+        })
+        .collect::<Vec<T>>()
+}
+
+fn mutual_reachability_mst<T: Float + One + Zero>(data: &Array2<T>, core_distances: &[T]) -> Vec<(usize, usize, T)> {
+    let n = data.rows();
+    let mut in_tree = vec![false; n];
+    let mut best = vec![(T::infinity(), 0); n];
+    let mut edges = Vec::with_capacity(n.saturating_sub(1));
+    let mut current = 0;
+    for _ in 1..n {
+        in_tree[current] = true;
+        let row = data.row(current);
+        let row = row.as_slice().unwrap();
+        let mut next: Option<usize> = None;
+        for other in 0..n {
+            if in_tree[other] {
+                continue;
+            }
+            let distance = squared_euclidean(row, data.row(other).as_slice().unwrap()).sqrt();
+            let reachability = distance.max(core_distances[current]).max(core_distances[other]);
+            if reachability < best[other].0 {
+                best[other] = (reachability, current);
+            }
+            next = match next {
+                Some(candidate) if best[candidate].0 <= best[other].0 => Some(candidate),
+                _ => Some(other),
+            };
+        }
+        let next = next.expect("unvisited point");
+        edges.push((best[next].1, next, best[next].0));
+        current = next;
+    }
+    edges
+}
+
+fn single_linkage_tree<T: Float + One + Zero>(n: usize, mut mst: Vec<(usize, usize, T)>) -> (Vec<(usize, usize)>, Vec<T>, Vec<usize>) {
+    mst.sort_unstable_by(|a, b| a.2.partial_cmp(&b.2).expect("PartialOrd mutual reachability"));
+    let mut parent = (0..2 * n).collect::<Vec<usize>>();
+    let mut sizes = vec![1; n];
+    let mut children = Vec::with_capacity(n);
+    let mut distances = Vec::with_capacity(n);
+    for (a, b, distance) in mst {
+        let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+        let node = n + children.len();
+        parent[a] = node;
+        parent[b] = node;
+        sizes.push(sizes[a] + sizes[b]);
+        children.push((a, b));
+        distances.push(distance);
+    }
+    (children, distances, sizes)
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+fn leaves(n: usize, children: &[(usize, usize)], node: usize, out: &mut Vec<usize>) {
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if node < n {
+            out.push(node);
+        } else {
+            let (left, right) = children[node - n];
+            stack.push(left);
+            stack.push(right);
+        }
+    }
+}
+
+fn condense_tree<T: Float + One + Zero>(n: usize, children: &[(usize, usize)], distances: &[T], sizes: &[usize], min_cluster_size: usize) -> (Vec<CondensedEdge<T>>, Vec<T>) {
+    let mut condensed = Vec::with_capacity(n);
+    let mut births = vec![T::zero()];
+    if n < 2 {
+        for point in 0..n {
+            condensed.push(CondensedEdge {
+                parent: 0,
+                child: Child::Point(point),
+                lambda: T::zero(),
+                size: 1,
+            });
+        }
+        return (condensed, births);
+    }
+
+    let mut fallen = Vec::new();
+    let mut stack = vec![(sizes.len() - 1, 0)];
+    while let Some((node, cluster)) = stack.pop() {
+        let (left, right) = children[node - n];
+        let distance = distances[node - n];
+        let lambda = if distance > T::zero() { T::one() / distance } else { T::infinity() };
+        let big_left = sizes[left] >= min_cluster_size;
+        let big_right = sizes[right] >= min_cluster_size;
+
+        if big_left && big_right {
+            for &child in [left, right].iter() {
+                let child_cluster = births.len();
+                births.push(lambda);
+                condensed.push(CondensedEdge {
+                    parent: cluster,
+                    child: Child::Cluster(child_cluster),
+                    lambda,
+                    size: sizes[child],
+                });
+                stack.push((child, child_cluster));
+            }
+        } else {
+            for &(child, big) in [(left, big_left), (right, big_right)].iter() {
+                if big {
+                    stack.push((child, cluster));
+                } else {
+                    fallen.clear();
+                    leaves(n, children, child, &mut fallen);
+                    for &point in fallen.iter() {
+                        condensed.push(CondensedEdge {
+                            parent: cluster,
+                            child: Child::Point(point),
+                            lambda,
+                            size: 1,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    (condensed, births)
+}
+
+fn select_clusters<T: Float + One + Zero>(condensed: &[CondensedEdge<T>], births: &[T]) -> Vec<bool> {
+    let nclusters = births.len();
+    let mut stability = vec![T::zero(); nclusters];
+    let mut cluster_children = vec![Vec::new(); nclusters];
+    for edge in condensed.iter() {
+        let lambda = if edge.lambda.is_finite() { edge.lambda } else { T::max_value() };
+        stability[edge.parent] = stability[edge.parent] + (lambda - births[edge.parent]) * T::from(edge.size).expect("T::from(usize)");
+        if let Child::Cluster(child) = edge.child {
+            cluster_children[edge.parent].push(child);
+        }
+    }
+
+    let mut selected = vec![false; nclusters];
+    for cluster in (1..nclusters).rev() {
+        let subtree_stability = cluster_children[cluster].iter().fold(T::zero(), |acc, child| acc + stability[*child]);
+        if cluster_children[cluster].is_empty() || stability[cluster] >= subtree_stability {
+            selected[cluster] = true;
+            let mut stack = cluster_children[cluster].clone();
+            while let Some(descendant) = stack.pop() {
+                selected[descendant] = false;
+                stack.extend_from_slice(&cluster_children[descendant]);
+            }
+        } else {
+            stability[cluster] = subtree_stability;
+        }
+    }
+    selected
+}
+
+fn label_points<T: Float + One + Zero>(n: usize, condensed: &[CondensedEdge<T>], selected: &[bool]) -> (Vec<usize>, Vec<T>) {
+    let mut cluster_parent = vec![None; selected.len()];
+    for edge in condensed.iter() {
+        if let Child::Cluster(child) = edge.child {
+            cluster_parent[child] = Some(edge.parent);
+        }
+    }
+
+    let mut labels = vec![0; selected.len()];
+    let mut c = 1;
+    for (cluster, is_selected) in selected.iter().enumerate() {
+        if *is_selected {
+            labels[cluster] = c;
+            c += 1;
+        }
+    }
+
+    let selected_ancestor = |mut cluster: usize| loop {
+        if selected[cluster] {
+            return Some(cluster);
+        }
+        cluster = cluster_parent[cluster]?;
+    };
+
+    let mut clusters = vec![0; n];
+    let mut lambdas = vec![T::zero(); n];
+    let mut max_lambdas = vec![T::zero(); selected.len()];
+    for edge in condensed.iter() {
+        if let Child::Point(point) = edge.child {
+            if let Some(cluster) = selected_ancestor(edge.parent) {
+                clusters[point] = labels[cluster];
+                lambdas[point] = edge.lambda;
+                max_lambdas[cluster] = max_lambdas[cluster].max(edge.lambda);
+            }
+        }
+    }
+
+    let mut max_lambda_by_label = vec![T::zero(); c];
+    for (cluster, label) in labels.iter().enumerate() {
+        max_lambda_by_label[*label] = max_lambda_by_label[*label].max(max_lambdas[cluster]);
+    }
+    let strengths = clusters
+        .iter()
+        .zip(lambdas.iter())
+        .map(|(label, lambda)| {
+            let max_lambda = max_lambda_by_label[*label];
+            if *label == 0 {
+                T::zero()
+            } else if *lambda >= max_lambda {
+                T::one()
+            } else {
+                *lambda / max_lambda
+            }
+        })
+        .collect::<Vec<T>>();
+    (clusters, strengths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_clusters() {
+        let data = Array2::from_shape_vec(
+            (11, 2),
+            vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, 1.2, 2.0, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.2, -2.0, 2.8, 10.0, -10.0],
+        )
+        .unwrap();
+        let model = Hdbscan::new(&data, 3, 3);
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(5).all_equal());
+        assert!(clustering.iter().skip(5).take(5).all_equal());
+        assert!(clustering[0] > 0 && clustering[5] > 0);
+        assert!(clustering[0] != clustering[5]);
+        assert_eq!(clustering[10], 0);
+        assert_eq!(model.strengths[10], 0.0);
+        assert!(model.strengths.iter().take(10).all(|s| *s > 0.0 && *s <= 1.0));
+
+        let fortran = Array2::from_shape_vec((11, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = Hdbscan::new(&fortran, 3, 3);
+        assert_eq!(model.clusters, transposed.clusters);
+        assert_eq!(model.strengths, transposed.strengths);
+    }
+}
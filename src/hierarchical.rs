@@ -0,0 +1,211 @@
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Linkage {
+    Single,
+    Complete,
+    Average,
+    Ward,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge<T: Float + One + Zero> {
+    pub left: usize,
+    pub right: usize,
+    pub distance: T,
+    pub size: usize,
+}
+
+#[derive(Debug)]
+pub struct Hierarchical<T: Float + One + Zero> {
+    pub linkage: Linkage,
+    pub merges: Vec<Merge<T>>,
+    pub npoints: usize,
+}
+
+impl<T: Float + One + Zero> Hierarchical<T> {
+    pub fn new(data: &Array2<T>, linkage: Linkage) -> Hierarchical<T> {
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), linkage);
+        }
+        let n = data.rows();
+        let mut distances = Array2::zeros((n, n));
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let d = squared_euclidean(data.row(i).as_slice().unwrap(), data.row(j).as_slice().unwrap()).sqrt();
+                distances[[i, j]] = d;
+                distances[[j, i]] = d;
+            }
+        }
+        Self::from_distances(distances, linkage)
+    }
+
+    pub fn from_distances(mut distances: Array2<T>, linkage: Linkage) -> Hierarchical<T> {
+        let n = distances.rows();
+        let mut active = vec![true; n];
+        let mut sizes = vec![1; n];
+        let mut chain: Vec<usize> = Vec::with_capacity(n);
+        let mut raw_merges = Vec::with_capacity(n.saturating_sub(1));
+
+        while raw_merges.len() + 1 < n {
+            if chain.is_empty() {
+                chain.push(active.iter().position(|a| *a).expect("active cluster"));
+            }
+            let (a, b) = loop {
+                let a = *chain.last().unwrap();
+                let previous = if chain.len() >= 2 { Some(chain[chain.len() - 2]) } else { None };
+                let mut nearest = previous;
+                for other in 0..n {
+                    if !active[other] || other == a {
+                        continue;
+                    }
+                    nearest = match nearest {
+                        Some(current) if distances[[a, current]] <= distances[[a, other]] => Some(current),
+                        _ => Some(other),
+                    };
+                }
+                let b = nearest.expect("nearest active cluster");
+                if Some(b) == previous {
+                    chain.pop();
+                    chain.pop();
+                    break (a, b);
+                }
+                chain.push(b);
+            };
+
+            let d_ab = distances[[a, b]];
+            let (size_a, size_b) = (sizes[a], sizes[b]);
+            for k in 0..n {
+                if !active[k] || k == a || k == b {
+                    continue;
+                }
+                let d = lance_williams(linkage, distances[[a, k]], distances[[b, k]], d_ab, size_a, size_b, sizes[k]);
+                distances[[b, k]] = d;
+                distances[[k, b]] = d;
+            }
+            active[a] = false;
+            sizes[b] = size_a + size_b;
+            raw_merges.push((a, b, d_ab));
+        }
+
+        raw_merges.sort_by(|x, y| x.2.partial_cmp(&y.2).expect("PartialOrd merge distance"));
+        let mut parent = (0..n).collect::<Vec<usize>>();
+        let mut node = (0..n).collect::<Vec<usize>>();
+        let mut node_sizes = vec![1; n];
+        let merges = raw_merges
+            .into_iter()
+            .enumerate()
+            .map(|(i, (a, b, distance))| {
+                let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+                let (left, right) = (node[ra].min(node[rb]), node[ra].max(node[rb]));
+                let size = node_sizes[ra] + node_sizes[rb];
+                parent[ra] = rb;
+                node[rb] = n + i;
+                node_sizes[rb] = size;
+                Merge { left, right, distance, size }
+            })
+            .collect::<Vec<Merge<T>>>();
+
+        Hierarchical { linkage, merges, npoints: n }
+    }
+
+    pub fn cut_clusters(&self, nclust: usize) -> Vec<usize> {
+        self.cut(self.npoints.saturating_sub(nclust.max(1)))
+    }
+
+    pub fn cut_distance(&self, threshold: T) -> Vec<usize> {
+        self.cut(self.merges.iter().take_while(|m| m.distance <= threshold).count())
+    }
+
+    fn cut(&self, nmerges: usize) -> Vec<usize> {
+        let n = self.npoints;
+        if n == 0 {
+            return vec![];
+        }
+        let mut parent = (0..2 * n - 1).collect::<Vec<usize>>();
+        for (i, merge) in self.merges.iter().take(nmerges).enumerate() {
+            parent[merge.left] = n + i;
+            parent[merge.right] = n + i;
+        }
+        let mut labels = vec![None; 2 * n - 1];
+        let mut c = 0;
+        (0..n)
+            .map(|point| {
+                let root = find(&mut parent, point);
+                *labels[root].get_or_insert_with(|| {
+                    c += 1;
+                    c - 1
+                })
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+fn lance_williams<T: Float + One + Zero>(linkage: Linkage, d_ak: T, d_bk: T, d_ab: T, size_a: usize, size_b: usize, size_k: usize) -> T {
+    let (na, nb, nk) = (
+        T::from(size_a).expect("T::from(usize)"),
+        T::from(size_b).expect("T::from(usize)"),
+        T::from(size_k).expect("T::from(usize)"),
+    );
+    match linkage {
+        Linkage::Single => d_ak.min(d_bk),
+        Linkage::Complete => d_ak.max(d_bk),
+        Linkage::Average => (na * d_ak + nb * d_bk) / (na + nb),
+        Linkage::Ward => (((na + nk) * d_ak.powi(2) + (nb + nk) * d_bk.powi(2) - nk * d_ab.powi(2)) / (na + nb + nk)).max(T::zero()).sqrt(),
+    }
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -1.2, -2.1]).unwrap()
+    }
+
+    #[test]
+    fn test_linkages() {
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average, Linkage::Ward].iter() {
+            let model = Hierarchical::new(&data(), *linkage);
+            assert_eq!(model.merges.len(), 7);
+            assert_eq!(model.merges.last().unwrap().size, 8);
+            assert!(model.merges.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+            let clustering = dbg!(model.cut_clusters(3));
+            assert!(clustering.iter().take(4).all_equal());
+            assert!(clustering.iter().skip(4).take(2).all_equal());
+            assert!(clustering.iter().skip(6).all_equal());
+            assert!(clustering.iter().unique().count() == 3);
+        }
+    }
+
+    #[test]
+    fn test_cut_distance() {
+        let model = Hierarchical::new(&data(), Linkage::Single);
+        assert!(model.cut_distance(0.0).iter().unique().count() == 8);
+        assert!(model.cut_distance(1.0).iter().unique().count() == 3);
+        assert!(model.cut_distance(100.0).iter().all(|c| *c == 0));
+
+        let empty = Hierarchical::new(&Array2::<f64>::zeros((0, 2)), Linkage::Single);
+        assert!(empty.cut_clusters(3).is_empty());
+        assert!(empty.cut_distance(1.0).is_empty());
+        assert_eq!(Hierarchical::new(&arr2(&[[1.0, 2.0]]), Linkage::Single).cut_clusters(3), vec![0]);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(Hierarchical::new(&fortran, Linkage::Single).merges, model.merges);
+    }
+}
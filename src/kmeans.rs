@@ -1,82 +1,1113 @@
-use itertools::Itertools;
+use crate::error::Error;
+use crate::metric::{Centroid, Euclidean, Manhattan, Metric, SquaredEuclidean};
+use crate::model::{Fit, Predict};
+use crate::validation::{check, non_finite_rows, standard_layout, NonFinite};
 use ndarray::prelude::*;
 use ndarray::ScalarOperand;
 use num_traits::float::Float;
 use num_traits::identities::{One, Zero};
 use rand::prelude::thread_rng;
 use rand::prelude::IteratorRandom;
-use rand::seq::index::sample;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 use std::f32;
 use std::iter::Sum;
 use std::ops::AddAssign;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Lloyd,
+    Elkan,
+    Hamerly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InitMethod<T> {
+    Random,
+    KmeansPlusPlus,
+    Explicit(Vec<Array1<T>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyCluster {
+    Reseed,
+    Drop,
+}
+
+type Run<T, M> = fn(&Array2<T>, Vec<Array1<T>>, usize, T, Option<&[T]>, EmptyCluster, M) -> Kmeans<T, M>;
+
+#[derive(Debug, Clone)]
+pub struct KmeansBuilder<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> = SquaredEuclidean> {
+    k: usize,
+    max_iter: usize,
+    tol: T,
+    nseeds: usize,
+    init: InitMethod<T>,
+    seed: Option<u64>,
+    weights: Option<Vec<T>>,
+    policy: EmptyCluster,
+    algorithm: Algorithm,
+    swaps: usize,
+    run: Run<T, M>,
+    metric: M,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> KmeansBuilder<T> {
+    pub fn new() -> KmeansBuilder<T> {
+        KmeansBuilder {
+            k: 8,
+            max_iter: 300,
+            tol: T::from(1e-4).expect("T::from(f64)"),
+            nseeds: 10,
+            init: InitMethod::KmeansPlusPlus,
+            seed: None,
+            weights: None,
+            policy: EmptyCluster::Reseed,
+            algorithm: Algorithm::Lloyd,
+            swaps: 0,
+            run: runner(Algorithm::Lloyd),
+            metric: SquaredEuclidean,
+        }
+    }
+
+    pub fn metric<N: Metric<T> + Clone>(self, metric: N) -> KmeansBuilder<T, N> {
+        KmeansBuilder {
+            k: self.k,
+            max_iter: self.max_iter,
+            tol: self.tol,
+            nseeds: self.nseeds,
+            init: self.init,
+            seed: self.seed,
+            weights: self.weights,
+            policy: self.policy,
+            algorithm: self.algorithm,
+            swaps: self.swaps,
+            run: runner(self.algorithm),
+            metric,
+        }
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Default for KmeansBuilder<T> {
+    fn default() -> KmeansBuilder<T> {
+        KmeansBuilder::new()
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> + Clone> KmeansBuilder<T, M> {
+    pub fn k(mut self, k: usize) -> KmeansBuilder<T, M> {
+        self.k = k;
+        self
+    }
+
+    pub fn max_iter(mut self, max_iter: usize) -> KmeansBuilder<T, M> {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn tol(mut self, tol: T) -> KmeansBuilder<T, M> {
+        self.tol = tol;
+        self
+    }
+
+    pub fn nseeds(mut self, nseeds: usize) -> KmeansBuilder<T, M> {
+        self.nseeds = nseeds;
+        self
+    }
+
+    pub fn init(mut self, init: InitMethod<T>) -> KmeansBuilder<T, M> {
+        self.init = init;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> KmeansBuilder<T, M> {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn weights(mut self, weights: Vec<T>) -> KmeansBuilder<T, M> {
+        self.weights = Some(weights);
+        self
+    }
+
+    pub fn empty_clusters(mut self, policy: EmptyCluster) -> KmeansBuilder<T, M> {
+        self.policy = policy;
+        self
+    }
+
+    pub fn random_swaps(mut self, swaps: usize) -> KmeansBuilder<T, M> {
+        self.swaps = swaps;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> KmeansBuilder<T, M> {
+        self.algorithm = algorithm;
+        self.run = runner(algorithm);
+        self
+    }
+
+    pub fn build(&self, data: &Array2<T>) -> Result<Kmeans<T, M>, Error> {
+        match self.seed {
+            Some(seed) => self.build_with_rng(data, &mut SmallRng::seed_from_u64(seed)),
+            None => self.build_with_rng(data, &mut thread_rng()),
+        }
+    }
+
+    pub fn build_with_rng<R: Rng>(&self, data: &Array2<T>, rng: &mut R) -> Result<Kmeans<T, M>, Error> {
+        if !data.is_standard_layout() {
+            return self.build_with_rng(&standard_layout(data.view()), rng);
+        }
+        self.validate(data)?;
+        Ok(best(self.subseeds(rng).into_iter().map(|subseed| self.run(data, subseed))))
+    }
+
+    pub fn build_all(&self, data: &Array2<T>) -> Result<Vec<Kmeans<T, M>>, Error> {
+        match self.seed {
+            Some(seed) => self.build_all_with_rng(data, &mut SmallRng::seed_from_u64(seed)),
+            None => self.build_all_with_rng(data, &mut thread_rng()),
+        }
+    }
+
+    pub fn build_all_with_rng<R: Rng>(&self, data: &Array2<T>, rng: &mut R) -> Result<Vec<Kmeans<T, M>>, Error> {
+        if !data.is_standard_layout() {
+            return self.build_all_with_rng(&standard_layout(data.view()), rng);
+        }
+        self.validate(data)?;
+        Ok(self.subseeds(rng).into_iter().map(|subseed| self.run(data, subseed)).collect::<Vec<Kmeans<T, M>>>())
+    }
+
+    fn subseeds<R: Rng>(&self, rng: &mut R) -> Vec<u64> {
+        let nseeds = if let InitMethod::Explicit(_) = self.init { 1 } else { self.nseeds };
+        (0..nseeds).map(|_| rng.gen::<u64>()).collect::<Vec<u64>>()
+    }
+
+    pub fn restart(&self, data: &Array2<T>, seed: u64) -> Result<Kmeans<T, M>, Error> {
+        if !data.is_standard_layout() {
+            return self.restart(&standard_layout(data.view()), seed);
+        }
+        self.validate(data)?;
+        Ok(self.run(data, seed))
+    }
+
+    fn run(&self, data: &Array2<T>, seed: u64) -> Kmeans<T, M> {
+        let weights = self.weights.as_deref();
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let centers = initialize(data, self.k, &self.init, weights, &self.metric, &mut rng);
+        let model = (self.run)(data, centers, self.max_iter, self.tol, weights, self.policy, self.metric.clone());
+        let mut model = self.random_swap(data, model, &mut rng);
+        model.seed = Some(seed);
+        model
+    }
+
+    fn random_swap(&self, data: &Array2<T>, mut model: Kmeans<T, M>, rng: &mut SmallRng) -> Kmeans<T, M> {
+        for _ in 0..self.swaps {
+            let mut centers = model.centers.clone();
+            let replaced = rng.gen_range(0, centers.len());
+            centers[replaced] = data.row(rng.gen_range(0, data.rows())).to_owned();
+            let candidate = (self.run)(data, centers, self.max_iter, self.tol, self.weights.as_deref(), self.policy, self.metric.clone());
+            if candidate.tot_withinss() < model.tot_withinss() {
+                model = candidate;
+            }
+        }
+        model
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum + Send + Sync, M: Metric<T> + Clone + Send + Sync> KmeansBuilder<T, M> {
+    pub fn par_build(&self, data: &Array2<T>) -> Result<Kmeans<T, M>, Error> {
+        match self.seed {
+            Some(seed) => self.par_build_with_rng(data, &mut SmallRng::seed_from_u64(seed)),
+            None => self.par_build_with_rng(data, &mut thread_rng()),
+        }
+    }
+
+    pub fn par_build_with_rng<R: Rng>(&self, data: &Array2<T>, rng: &mut R) -> Result<Kmeans<T, M>, Error> {
+        if !data.is_standard_layout() {
+            return self.par_build_with_rng(&standard_layout(data.view()), rng);
+        }
+        self.validate(data)?;
+        let subseeds = self.subseeds(rng);
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if subseeds.len() < threads && self.algorithm == Algorithm::Lloyd {
+            return Ok(best(subseeds.into_iter().map(|subseed| self.par_restart(data, subseed, threads))));
+        }
+        Ok(std::thread::scope(|scope| {
+            let handles = subseeds
+                .chunks((subseeds.len() + threads - 1) / threads)
+                .map(|chunk| scope.spawn(move || chunk.iter().map(|subseed| self.run(data, *subseed)).collect::<Vec<Kmeans<T, M>>>()))
+                .collect::<Vec<_>>();
+            best(handles.into_iter().flat_map(|handle| handle.join().expect("kmeans restart thread")))
+        }))
+    }
+
+    fn par_restart(&self, data: &Array2<T>, subseed: u64, threads: usize) -> Kmeans<T, M> {
+        let weights = self.weights.as_deref();
+        let mut rng = SmallRng::seed_from_u64(subseed);
+        let centers = initialize(data, self.k, &self.init, weights, &self.metric, &mut rng);
+        let model = Kmeans::lloyd_by(data, centers, self.max_iter, self.tol, weights, self.policy, self.metric.clone(), |data, centers, metric| {
+            let rows = ((data.rows() + threads.max(1) - 1) / threads.max(1)).max(1);
+            std::thread::scope(|scope| {
+                let handles = data
+                    .axis_chunks_iter(Axis(0), rows)
+                    .map(|block| scope.spawn(move || block.outer_iter().map(|row| nearest_center(centers, &row, metric)).collect::<Vec<(usize, T)>>()))
+                    .collect::<Vec<_>>();
+                handles.into_iter().flat_map(|handle| handle.join().expect("kmeans assignment thread")).collect::<Vec<(usize, T)>>()
+            })
+        });
+        let mut model = self.random_swap(data, model, &mut rng);
+        model.seed = Some(subseed);
+        model
+    }
+}
+
 #[derive(Debug)]
-pub struct Kmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+pub struct Kmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> = SquaredEuclidean> {
     pub centers: Vec<Array1<T>>,
     pub clusters: Vec<usize>,
     pub withinss: Vec<T>,
+    pub counts: Vec<usize>,
+    pub cluster_weights: Vec<T>,
+    pub n_iter: usize,
+    pub converged: bool,
+    pub inertia_history: Vec<T>,
+    pub empty_clusters: usize,
+    pub seed: Option<u64>,
+    pub metric: M,
 }
 
 impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Kmeans<T> {
-    pub fn new(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize) -> Kmeans<T> {
-        let mut rng = thread_rng();
-        (0..nseeds)
-            .map(|_| {
-                let mut centers = data.outer_iter().choose_multiple(&mut rng, nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>();
-                let mut clusters = vec![0; data.rows()];
-                let mut withinss = vec![T::zero(); nclust];
-                for _ in 0..iterations {
-                    let mut sums = vec![Array1::zeros((data.cols())); nclust];
-                    let mut counts = vec![0; nclust];
-                    withinss = vec![T::zero(); nclust];
-                    for (row_idx, row) in data.outer_iter().enumerate() {
-                        let (cluster, distance) = centers
-                            .iter()
-                            .enumerate()
-                            .map(|(i, center)| (i, ((&row - center) * (&row - center)).sum()))
-                            .map(|(i, x)| if x.is_nan() { (i, T::from(f32::MAX).expect("T::from(f32::MAX)")) } else { (i, x) })
-                            .min_by(|(_, a), (_, b)| a.partial_cmp(&b).expect("PartialOrd distance from center"))
-                            .expect("min distance from center");
-                        clusters[row_idx] = cluster;
-                        sums[cluster] = &sums[cluster] + &row;
-                        counts[cluster] += 1;
-                        withinss[cluster] += distance;
+    pub fn new(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize) -> Result<Kmeans<T>, Error> {
+        Self::with_metric(data, nclust, iterations, nseeds, SquaredEuclidean)
+    }
+
+    pub fn fit_predict(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize) -> Result<Vec<usize>, Error> {
+        Ok(Self::new(data, nclust, iterations, nseeds)?.clusters)
+    }
+
+    pub fn try_new(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, policy: NonFinite) -> Result<(Kmeans<T>, Vec<usize>), Error> {
+        let checked = check(data, policy)?;
+        let model = Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds), &checked.data)?;
+        Ok((model, checked.rows))
+    }
+
+    pub fn with_tol(data: &Array2<T>, nclust: usize, iterations: usize, tol: T, nseeds: usize) -> Result<Kmeans<T>, Error> {
+        Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(tol).nseeds(nseeds), data)
+    }
+
+    pub fn with_empty_policy(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, policy: EmptyCluster) -> Result<Kmeans<T>, Error> {
+        Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).empty_clusters(policy), data)
+    }
+
+    pub fn with_weights(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, weights: &[T]) -> Result<Kmeans<T>, Error> {
+        Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).weights(weights.to_vec()), data)
+    }
+
+    pub fn with_init(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, init: InitMethod<T>) -> Result<Kmeans<T>, Error> {
+        Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).init(init), data)
+    }
+
+    pub fn with_seed(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, seed: u64) -> Result<Kmeans<T>, Error> {
+        Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).seed(seed), data)
+    }
+
+    pub fn with_algorithm(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, algorithm: Algorithm) -> Result<Kmeans<T>, Error> {
+        Fit::fit(&KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).algorithm(algorithm), data)
+    }
+
+    pub fn medians(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize) -> Result<Kmeans<T, Manhattan>, Error> {
+        Fit::fit(&KmeansBuilder::new().metric(Manhattan).k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds), data)
+    }
+
+    pub(crate) fn lloyd(data: &Array2<T>, centers: Vec<Array1<T>>, iterations: usize) -> Kmeans<T> {
+        Self::lloyd_with_metric(data, centers, iterations, T::zero(), EmptyCluster::Reseed, SquaredEuclidean)
+    }
+
+    pub fn predict_with_centers(centers: &[Array1<T>], row: &ArrayView1<T>) -> usize {
+        nearest_center(centers, row, &SquaredEuclidean).0
+    }
+
+    pub fn totss(&self, data: &Array2<T>, weights: Option<&[T]>) -> T {
+        if let Some(weights) = weights {
+            assert_eq!(weights.len(), data.rows(), "one weight per row");
+        }
+        let weight = |i: usize| weights.map_or(T::one(), |w| w[i]);
+        let total = (0..data.rows()).fold(T::zero(), |acc, i| acc + weight(i));
+        if total <= T::zero() {
+            return T::zero();
+        }
+        let mean = data.outer_iter().enumerate().fold(Array1::zeros(data.cols()), |acc, (i, row)| acc + &(&row * weight(i))) / total;
+        data.outer_iter().enumerate().fold(T::zero(), |acc, (i, row)| acc + weight(i) * (&row - &mean).mapv(|x| x * x).sum())
+    }
+
+    pub fn betweenss(&self, data: &Array2<T>, weights: Option<&[T]>) -> T {
+        self.totss(data, weights) - self.tot_withinss()
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> + Clone> Kmeans<T, M> {
+    pub fn with_metric(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, metric: M) -> Result<Kmeans<T, M>, Error> {
+        Self::with_rng(data, nclust, iterations, nseeds, metric, &mut thread_rng())
+    }
+
+    pub fn with_rng<R: Rng>(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, metric: M, rng: &mut R) -> Result<Kmeans<T, M>, Error> {
+        KmeansBuilder::new().metric(metric).k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).build_with_rng(data, rng)
+    }
+
+    pub(crate) fn lloyd_with_metric(data: &Array2<T>, centers: Vec<Array1<T>>, iterations: usize, tol: T, policy: EmptyCluster, metric: M) -> Kmeans<T, M> {
+        if !data.is_standard_layout() {
+            return Self::lloyd_with_metric(&standard_layout(data.view()), centers, iterations, tol, policy, metric);
+        }
+        Self::lloyd_by(data, centers, iterations, tol, None, policy, metric, assign)
+    }
+
+    pub(crate) fn elkan(data: &Array2<T>, mut centers: Vec<Array1<T>>, iterations: usize, tol: T, weights: Option<&[T]>, policy: EmptyCluster, metric: M) -> Kmeans<T, M> {
+        let (n, nclust) = (data.rows(), centers.len());
+        let half = T::from(0.5).expect("T::from(f64)");
+        let distance = |a: &ArrayView1<T>, b: &Array1<T>| Euclidean.distance(a.as_slice().unwrap(), b.as_slice().unwrap());
+        let mut lower = Array2::from_shape_fn((n, nclust), |(i, j)| distance(&data.row(i), &centers[j]));
+        let mut clusters = lower
+            .outer_iter()
+            .map(|row| {
+                row.indexed_iter()
+                    .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from center"))
+                    .expect("min distance from center")
+                    .0
+            })
+            .collect::<Vec<usize>>();
+        let mut upper = clusters.iter().enumerate().map(|(i, c)| lower[[i, *c]]).collect::<Vec<T>>();
+        let mut tight = vec![true; n];
+        let (mean, norms) = centered_norms(data);
+        let mut withinss = vec![T::zero(); nclust];
+        let mut cluster_weights = vec![T::zero(); nclust];
+        let mut n_iter = 0;
+        let mut converged = false;
+        let mut inertia_history = Vec::with_capacity(iterations);
+        let mut empty_clusters = 0;
+        while n_iter < iterations {
+            n_iter += 1;
+            let nclust = centers.len();
+            let between = Array2::from_shape_fn((nclust, nclust), |(a, b)| distance(&centers[a].view(), &centers[b]));
+            let separation = (0..nclust)
+                .map(|a| (0..nclust).filter(|b| *b != a).map(|b| between[[a, b]]).fold(T::infinity(), |acc, d| acc.min(d)) * half)
+                .collect::<Vec<T>>();
+            for (i, row) in data.outer_iter().enumerate() {
+                let mut assigned = clusters[i];
+                if upper[i] <= separation[assigned] {
+                    continue;
+                }
+                for j in 0..nclust {
+                    if j == assigned || upper[i] <= lower[[i, j]] || upper[i] <= between[[assigned, j]] * half {
+                        continue;
+                    }
+                    if !tight[i] {
+                        upper[i] = distance(&row, &centers[assigned]);
+                        lower[[i, assigned]] = upper[i];
+                        tight[i] = true;
+                        if upper[i] <= lower[[i, j]] || upper[i] <= between[[assigned, j]] * half {
+                            continue;
+                        }
+                    }
+                    let d = distance(&row, &centers[j]);
+                    lower[[i, j]] = d;
+                    if d < upper[i] {
+                        assigned = j;
+                        upper[i] = d;
                     }
-                    centers = sums
-                        .into_iter()
-                        .zip(counts.into_iter())
-                        .map(|(sum, count)| sum / T::from(count).expect("T::from(usize)"))
-                        .collect::<Vec<Array1<T>>>();
                 }
-                Kmeans {
-                    centers: centers,
-                    clusters: clusters,
-                    withinss: withinss,
+                clusters[i] = assigned;
+            }
+
+            let (squares, totals) = cluster_sums(&clusters, &norms, weights, nclust);
+            if policy == EmptyCluster::Reseed && totals.iter().any(|total| *total <= T::zero()) {
+                tighten(data, &centers, &clusters, &mut upper, &mut tight, distance);
+            }
+            let costs = upper.iter().map(|d| *d * *d).collect::<Vec<T>>();
+            let (updated, kept, _, totals, empty) = update_centers(data, &mut clusters, &costs, weights, nclust, policy, Centroid::Mean);
+            let shifts = kept.iter().zip(updated.iter()).map(|(old, center)| distance(&centers[*old].view(), center)).collect::<Vec<T>>();
+            withinss = scatter(&kept, &squares, &totals, &updated, &shifts, &mean);
+            cluster_weights = totals;
+            inertia_history.push(withinss.iter().fold(T::zero(), |acc, x| acc + *x));
+            empty_clusters += empty;
+            centers = updated;
+            lower = lower.select(Axis(1), &kept);
+            for mut bounds in lower.outer_iter_mut() {
+                for (b, shift) in bounds.iter_mut().zip(shifts.iter()) {
+                    *b = (*b - *shift).max(T::zero());
+                }
+            }
+            for (i, cluster) in clusters.iter().enumerate() {
+                upper[i] += shifts[*cluster];
+                tight[i] = tight[i] && shifts[*cluster] <= T::zero();
+            }
+            if empty == 0 && shifts.iter().fold(T::zero(), |acc, x| acc.max(*x * *x)) <= tol {
+                converged = true;
+                break;
+            }
+        }
+        Kmeans {
+            counts: sizes(&clusters, centers.len()),
+            cluster_weights,
+            centers,
+            clusters,
+            withinss,
+            n_iter,
+            converged,
+            inertia_history,
+            empty_clusters,
+            seed: None,
+            metric,
+        }
+    }
+
+    pub(crate) fn hamerly(data: &Array2<T>, mut centers: Vec<Array1<T>>, iterations: usize, tol: T, weights: Option<&[T]>, policy: EmptyCluster, metric: M) -> Kmeans<T, M> {
+        let (n, nclust) = (data.rows(), centers.len());
+        let half = T::from(0.5).expect("T::from(f64)");
+        let distance = |a: &ArrayView1<T>, b: &Array1<T>| Euclidean.distance(a.as_slice().unwrap(), b.as_slice().unwrap());
+        let two_nearest = |row: &ArrayView1<T>, centers: &[Array1<T>]| {
+            centers.iter().enumerate().fold((0, T::infinity(), T::infinity()), |(best, first, second), (j, center)| {
+                let d = distance(row, center);
+                if d < first {
+                    (j, d, first)
+                } else {
+                    (best, first, second.min(d))
                 }
             })
-            .min_by(|a, b| a.withinss.iter().cloned().sum::<T>().partial_cmp(&b.withinss.iter().cloned().sum::<T>()).expect("withinss is not NAN"))
-            .expect("min withinss")
+        };
+        let mut clusters = vec![0; n];
+        let mut upper = vec![T::zero(); n];
+        let mut lower = vec![T::zero(); n];
+        for (i, row) in data.outer_iter().enumerate() {
+            let (best, first, second) = two_nearest(&row, &centers);
+            clusters[i] = best;
+            upper[i] = first;
+            lower[i] = second;
+        }
+        let mut tight = vec![true; n];
+        let (mean, norms) = centered_norms(data);
+        let mut withinss = vec![T::zero(); nclust];
+        let mut cluster_weights = vec![T::zero(); nclust];
+        let mut n_iter = 0;
+        let mut converged = false;
+        let mut inertia_history = Vec::with_capacity(iterations);
+        let mut empty_clusters = 0;
+        while n_iter < iterations {
+            n_iter += 1;
+            let nclust = centers.len();
+            let separation = (0..nclust)
+                .map(|a| {
+                    (0..nclust)
+                        .filter(|b| *b != a)
+                        .map(|b| distance(&centers[a].view(), &centers[b]))
+                        .fold(T::infinity(), |acc, d| acc.min(d))
+                        * half
+                })
+                .collect::<Vec<T>>();
+            for (i, row) in data.outer_iter().enumerate() {
+                let bound = separation[clusters[i]].max(lower[i]);
+                if upper[i] <= bound {
+                    continue;
+                }
+                if !tight[i] {
+                    upper[i] = distance(&row, &centers[clusters[i]]);
+                    tight[i] = true;
+                    if upper[i] <= bound {
+                        continue;
+                    }
+                }
+                let (best, first, second) = two_nearest(&row, &centers);
+                clusters[i] = best;
+                upper[i] = first;
+                lower[i] = second;
+            }
+
+            let (squares, totals) = cluster_sums(&clusters, &norms, weights, nclust);
+            if policy == EmptyCluster::Reseed && totals.iter().any(|total| *total <= T::zero()) {
+                tighten(data, &centers, &clusters, &mut upper, &mut tight, distance);
+            }
+            let costs = upper.iter().map(|d| *d * *d).collect::<Vec<T>>();
+            let (updated, kept, _, totals, empty) = update_centers(data, &mut clusters, &costs, weights, nclust, policy, Centroid::Mean);
+            let shifts = kept.iter().zip(updated.iter()).map(|(old, center)| distance(&centers[*old].view(), center)).collect::<Vec<T>>();
+            withinss = scatter(&kept, &squares, &totals, &updated, &shifts, &mean);
+            cluster_weights = totals;
+            inertia_history.push(withinss.iter().fold(T::zero(), |acc, x| acc + *x));
+            empty_clusters += empty;
+            let largest = shifts.iter().fold(T::zero(), |acc, x| acc.max(*x));
+            centers = updated;
+            for bound in lower.iter_mut() {
+                *bound = (*bound - largest).max(T::zero());
+            }
+            for (i, cluster) in clusters.iter().enumerate() {
+                upper[i] += shifts[*cluster];
+                tight[i] = tight[i] && shifts[*cluster] <= T::zero();
+            }
+            if empty == 0 && largest * largest <= tol {
+                converged = true;
+                break;
+            }
+        }
+        Kmeans {
+            counts: sizes(&clusters, centers.len()),
+            cluster_weights,
+            centers,
+            clusters,
+            withinss,
+            n_iter,
+            converged,
+            inertia_history,
+            empty_clusters,
+            seed: None,
+            metric,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn lloyd_by<A>(data: &Array2<T>, mut centers: Vec<Array1<T>>, iterations: usize, tol: T, weights: Option<&[T]>, policy: EmptyCluster, metric: M, assign: A) -> Kmeans<T, M>
+    where
+        A: Fn(&Array2<T>, &[Array1<T>], &M) -> Vec<(usize, T)>,
+    {
+        let mut clusters = vec![0; data.rows()];
+        let mut withinss = vec![T::zero(); centers.len()];
+        let mut cluster_weights = vec![T::zero(); centers.len()];
+        let mut n_iter = 0;
+        let mut converged = false;
+        let mut inertia_history = Vec::with_capacity(iterations);
+        let mut empty_clusters = 0;
+        while n_iter < iterations {
+            n_iter += 1;
+            let mut costs = Vec::with_capacity(data.rows());
+            for (row_idx, (cluster, distance)) in assign(data, &centers, &metric).into_iter().enumerate() {
+                clusters[row_idx] = cluster;
+                costs.push(distance);
+            }
+            let (updated, kept, costs, totals, empty) = update_centers(data, &mut clusters, &costs, weights, centers.len(), policy, metric.centroid());
+            withinss = costs;
+            cluster_weights = totals;
+            inertia_history.push(withinss.iter().fold(T::zero(), |acc, x| acc + *x));
+            empty_clusters += empty;
+            let movement = kept
+                .iter()
+                .zip(updated.iter())
+                .map(|(old, new)| metric.distance(centers[*old].as_slice().unwrap(), new.as_slice().unwrap()))
+                .fold(T::zero(), |acc, x| acc.max(x));
+            centers = updated;
+            if empty == 0 && movement <= tol {
+                converged = true;
+                break;
+            }
+        }
+        Kmeans {
+            counts: sizes(&clusters, centers.len()),
+            cluster_weights,
+            centers,
+            clusters,
+            withinss,
+            n_iter,
+            converged,
+            inertia_history,
+            empty_clusters,
+            seed: None,
+            metric,
+        }
     }
 
     pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
-        data.outer_iter().map(|row| Self::predict_with_centers(&self.centers, &row)).collect::<Vec<usize>>()
+        if !data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()));
+        }
+        data.outer_iter().map(|row| nearest_center(&self.centers, &row, &self.metric).0).collect::<Vec<usize>>()
     }
 
-    pub fn predict_with_centers(centers: &[Array1<T>], row: &ArrayView1<T>) -> usize {
-        centers
-            .iter()
-            .enumerate()
-            .map(|(i, center)| (i, ((row - center) * (row - center)).sum()))
-            .map(|(i, x)| if x.is_nan() { (i, T::from(f32::MAX).expect("T::from(f32::MAX)")) } else { (i, x) })
-            .min_by(|(_, a), (_, b)| a.partial_cmp(&b).expect("PartialOrd distance from center"))
-            .expect("min distance from center")
-            .0
+    pub fn try_predict(&self, data: &Array2<T>) -> Result<Vec<usize>, Error> {
+        match self.centers.first() {
+            Some(center) if center.len() != data.cols() => Err(Error::DimensionMismatch {
+                expected: center.len(),
+                found: data.cols(),
+            }),
+            _ => Ok(self.predict(data)),
+        }
+    }
+
+    pub fn refit(&self, data: &Array2<T>, iterations: usize) -> Kmeans<T, M> {
+        Self::lloyd_with_metric(data, self.centers.clone(), iterations, T::zero(), EmptyCluster::Reseed, self.metric.clone())
+    }
+
+    pub fn transform(&self, data: &Array2<T>) -> Array2<T> {
+        if !data.is_standard_layout() {
+            return self.transform(&standard_layout(data.view()));
+        }
+        Array2::from_shape_fn((data.rows(), self.centers.len()), |(i, j)| {
+            self.metric.distance(data.row(i).as_slice().unwrap(), self.centers[j].as_slice().unwrap())
+        })
+    }
+
+    pub fn predict_proba(&self, data: &Array2<T>, temperature: T) -> Array2<T> {
+        assert!(temperature > T::zero(), "temperature must be positive");
+        let mut weights = self.transform(data);
+        for mut row in weights.outer_iter_mut() {
+            let nearest = row.fold(T::infinity(), |acc, d| acc.min(*d));
+            row.mapv_inplace(|d| (-(d - nearest) / temperature).exp());
+            let total = row.sum();
+            row.mapv_inplace(|w| w / total);
+        }
+        weights
+    }
+
+    pub fn tot_withinss(&self) -> T {
+        self.withinss.iter().fold(T::zero(), |acc, x| acc + *x)
+    }
+
+    pub fn partial_fit(&mut self, batch: &Array2<T>) -> Vec<usize> {
+        self.partial_fit_weighted(batch, &vec![T::one(); batch.rows()])
+    }
+
+    pub fn partial_fit_weighted(&mut self, batch: &Array2<T>, weights: &[T]) -> Vec<usize> {
+        assert_eq!(weights.len(), batch.rows(), "one weight per row");
+        let dims = T::from(batch.cols().max(1)).expect("T::from(usize)");
+        batch
+            .outer_iter()
+            .zip(weights.iter())
+            .map(|(row, w)| {
+                let (cluster, _) = nearest_center(&self.centers, &row, &self.metric);
+                self.counts[cluster] += 1;
+                let total = self.cluster_weights[cluster] + *w;
+                if total <= T::zero() {
+                    return cluster;
+                }
+                let rate = *w / total;
+                let center = &self.centers[cluster];
+                let updated = match self.metric.centroid() {
+                    Centroid::Median if self.withinss[cluster] > T::zero() && self.cluster_weights[cluster] > T::zero() => {
+                        let step = rate * self.withinss[cluster] / (self.cluster_weights[cluster] * dims);
+                        Array1::from_shape_fn(center.len(), |j| {
+                            let offset = row[j] - center[j];
+                            center[j] + offset.signum() * offset.abs().min(step)
+                        })
+                    }
+                    Centroid::NormalizedMean => {
+                        let mean = center + &((&row - center) * rate);
+                        let norm = mean.fold(T::zero(), |acc, x| acc + *x * *x).sqrt();
+                        if norm > T::zero() {
+                            mean / norm
+                        } else {
+                            mean
+                        }
+                    }
+                    _ => center + &((&row - center) * rate),
+                };
+                self.withinss[cluster] += *w * self.metric.distance(&row.to_vec(), updated.as_slice().unwrap());
+                self.cluster_weights[cluster] = total;
+                self.centers[cluster] = updated;
+                cluster
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum + Send + Sync> Kmeans<T> {
+    pub fn par_with_seed(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, seed: u64) -> Result<Kmeans<T>, Error> {
+        KmeansBuilder::new().k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).seed(seed).par_build(data)
     }
 }
 
+#[cfg(feature = "parallel")]
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum + Send + Sync, M: Metric<T> + Clone + Send + Sync> Kmeans<T, M> {
+    pub fn par_new(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, metric: M) -> Result<Kmeans<T, M>, Error> {
+        Self::par_with_rng(data, nclust, iterations, nseeds, metric, &mut thread_rng())
+    }
+
+    pub fn par_with_rng<R: Rng>(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, metric: M, rng: &mut R) -> Result<Kmeans<T, M>, Error> {
+        KmeansBuilder::new().metric(metric).k(nclust).max_iter(iterations).tol(T::zero()).nseeds(nseeds).par_build_with_rng(data, rng)
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> + Clone> Fit<Array2<T>> for KmeansBuilder<T, M> {
+    type Model = Kmeans<T, M>;
+
+    fn fit(&self, data: &Array2<T>) -> Result<Kmeans<T, M>, Error> {
+        self.build(data)
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> + Clone> KmeansBuilder<T, M> {
+    fn validate(&self, data: &Array2<T>) -> Result<(), Error> {
+        if data.rows() == 0 {
+            return Err(Error::EmptyInput);
+        }
+        if self.k == 0 || self.k > data.rows() {
+            return Err(Error::InvalidParameter(format!("k = {} must be between 1 and the number of rows ({})", self.k, data.rows())));
+        }
+        if let Some(weights) = self.weights.as_ref() {
+            if weights.len() != data.rows() {
+                return Err(Error::DimensionMismatch {
+                    expected: data.rows(),
+                    found: weights.len(),
+                });
+            }
+            let offending = (0..weights.len()).filter(|row_idx| !weights[*row_idx].is_finite()).collect::<Vec<usize>>();
+            if !offending.is_empty() {
+                return Err(Error::NonFiniteValue { rows: offending });
+            }
+            if weights.iter().any(|w| *w < T::zero()) {
+                return Err(Error::InvalidParameter("weights must be non-negative".to_string()));
+            }
+        }
+        if self.nseeds == 0 {
+            return Err(Error::InvalidParameter("nseeds must be at least 1".to_string()));
+        }
+        if let InitMethod::Explicit(centers) = &self.init {
+            if centers.len() != self.k {
+                return Err(Error::DimensionMismatch {
+                    expected: self.k,
+                    found: centers.len(),
+                });
+            }
+            if let Some(center) = centers.iter().find(|center| center.len() != data.cols()) {
+                return Err(Error::DimensionMismatch {
+                    expected: data.cols(),
+                    found: center.len(),
+                });
+            }
+        }
+        if self.algorithm != Algorithm::Lloyd && !self.metric.elkan_compatible() {
+            return Err(Error::InvalidParameter(format!("{:?} requires the squared Euclidean metric", self.algorithm)));
+        }
+        let offending = non_finite_rows(data);
+        if !offending.is_empty() {
+            return Err(Error::NonFiniteValue { rows: offending });
+        }
+        Ok(())
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> + Clone> Predict<Array2<T>> for Kmeans<T, M> {
+    fn predict(&self, data: &Array2<T>) -> Vec<Option<usize>> {
+        Kmeans::predict(self, data).into_iter().map(Some).collect::<Vec<Option<usize>>>()
+    }
+}
+
+fn runner<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T> + Clone>(algorithm: Algorithm) -> Run<T, M> {
+    match algorithm {
+        Algorithm::Lloyd => |data, centers, iterations, tol, weights, policy, metric| Kmeans::lloyd_by(data, centers, iterations, tol, weights, policy, metric, assign),
+        Algorithm::Elkan => Kmeans::elkan,
+        Algorithm::Hamerly => Kmeans::hamerly,
+    }
+}
+
+fn best<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, M: Metric<T>, I: Iterator<Item = Kmeans<T, M>>>(models: I) -> Kmeans<T, M> {
+    models
+        .min_by(|a, b| a.withinss.iter().cloned().sum::<T>().partial_cmp(&b.withinss.iter().cloned().sum::<T>()).expect("withinss is not NAN"))
+        .expect("min withinss")
+}
+
+#[derive(Debug)]
+pub struct InertiaCurve<T: Float + One + Zero> {
+    pub ks: Vec<usize>,
+    pub inertias: Vec<T>,
+    pub knee: Option<usize>,
+}
+
+pub fn inertia_curve<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, kmin: usize, kmax: usize, iterations: usize, nseeds: usize) -> InertiaCurve<T> {
+    assert!(kmin > 0 && kmin <= kmax && kmax <= data.rows(), "kmin must be between 1 and kmax, kmax at most the number of rows");
+    if !data.is_standard_layout() {
+        return inertia_curve(&standard_layout(data.view()), kmin, kmax, iterations, nseeds);
+    }
+    let inertia = |model: &Kmeans<T>| {
+        data.outer_iter()
+            .map(|row| {
+                let center = &model.centers[Kmeans::predict_with_centers(&model.centers, &row)];
+                SquaredEuclidean.distance(row.as_slice().unwrap(), center.as_slice().unwrap())
+            })
+            .fold(T::zero(), |acc, x| acc + x)
+    };
+
+    let ks = (kmin..=kmax).collect::<Vec<usize>>();
+    let mut model = Kmeans::new(data, kmin, iterations, nseeds).expect("k-means on finite data with k at most the number of rows");
+    let mut inertias = vec![inertia(&model)];
+    for _ in ks.iter().skip(1) {
+        let farthest = data
+            .outer_iter()
+            .max_by(|a, b| {
+                let da = SquaredEuclidean.distance(a.as_slice().unwrap(), model.centers[Kmeans::predict_with_centers(&model.centers, a)].as_slice().unwrap());
+                let db = SquaredEuclidean.distance(b.as_slice().unwrap(), model.centers[Kmeans::predict_with_centers(&model.centers, b)].as_slice().unwrap());
+                da.partial_cmp(&db).expect("PartialOrd distance from center")
+            })
+            .expect("non-empty data")
+            .to_owned();
+        let mut centers = model.centers;
+        centers.push(farthest);
+        model = Kmeans::lloyd(data, centers, iterations);
+        inertias.push(inertia(&model));
+    }
+
+    let knee = kneedle(&ks, &inertias);
+    InertiaCurve { ks, inertias, knee }
+}
+
+fn kneedle<T: Float + One + Zero>(ks: &[usize], values: &[T]) -> Option<usize> {
+    if ks.len() < 3 {
+        return None;
+    }
+    let (first, last) = (T::from(ks[0]).expect("T::from(usize)"), T::from(ks[ks.len() - 1]).expect("T::from(usize)"));
+    let high = values.iter().fold(T::neg_infinity(), |acc, v| acc.max(*v));
+    let low = values.iter().fold(T::infinity(), |acc, v| acc.min(*v));
+    if high <= low {
+        return None;
+    }
+    ks.iter()
+        .zip(values.iter())
+        .map(|(k, v)| (*k, (high - *v) / (high - low) - (T::from(*k).expect("T::from(usize)") - first) / (last - first)))
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd knee difference"))
+        .map(|(k, _)| k)
+}
+
+#[derive(Debug)]
+pub struct Gap<T: Float + One + Zero> {
+    pub ks: Vec<usize>,
+    pub gaps: Vec<T>,
+    pub errors: Vec<T>,
+    pub best: usize,
+}
+
+pub fn gap_statistic<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, kmin: usize, kmax: usize, references: usize, iterations: usize, nseeds: usize) -> Gap<T> {
+    gap_statistic_with_rng(data, kmin, kmax, references, iterations, nseeds, &mut thread_rng())
+}
+
+pub fn gap_statistic_with_rng<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum, R: Rng>(
+    data: &Array2<T>,
+    kmin: usize,
+    kmax: usize,
+    references: usize,
+    iterations: usize,
+    nseeds: usize,
+    rng: &mut R,
+) -> Gap<T> {
+    assert!(kmin > 0 && kmin <= kmax && kmax <= data.rows(), "kmin must be between 1 and kmax, kmax at most the number of rows");
+    let lows = data.gencolumns().into_iter().map(|col| col.fold(T::infinity(), |acc, x| acc.min(*x))).collect::<Vec<T>>();
+    let highs = data.gencolumns().into_iter().map(|col| col.fold(T::neg_infinity(), |acc, x| acc.max(*x))).collect::<Vec<T>>();
+    let log_dispersion = |data: &Array2<T>, k: usize, rng: &mut R| {
+        Kmeans::with_rng(data, k, iterations, nseeds, SquaredEuclidean, rng).expect("k-means on finite data with k at most the number of rows").tot_withinss().max(T::epsilon()).ln()
+    };
+    let b = T::from(references.max(1)).expect("T::from(usize)");
+
+    let ks = (kmin..=kmax).collect::<Vec<usize>>();
+    let mut gaps = Vec::with_capacity(ks.len());
+    let mut errors = Vec::with_capacity(ks.len());
+    for k in ks.iter() {
+        let mut reference_logs = Vec::with_capacity(references.max(1));
+        for _ in 0..references.max(1) {
+            let uniform = Array2::from_shape_fn(data.dim(), |(_, col)| lows[col] + (highs[col] - lows[col]) * T::from(rng.gen::<f64>()).expect("T::from(f64)"));
+            reference_logs.push(log_dispersion(&uniform, *k, rng));
+        }
+        let mean = reference_logs.iter().fold(T::zero(), |acc, x| acc + *x) / b;
+        let sd = (reference_logs.iter().fold(T::zero(), |acc, x| acc + (*x - mean).powi(2)) / b).sqrt();
+        gaps.push(mean - log_dispersion(data, *k, rng));
+        errors.push(sd * (T::one() + T::one() / b).sqrt());
+    }
+
+    let best = (0..ks.len() - 1).find(|i| gaps[*i] >= gaps[i + 1] - errors[i + 1]).map_or(kmax, |i| ks[i]);
+    Gap { ks, gaps, errors, best }
+}
+
+fn sizes(clusters: &[usize], nclust: usize) -> Vec<usize> {
+    let mut counts = vec![0; nclust];
+    for cluster in clusters.iter() {
+        counts[*cluster] += 1;
+    }
+    counts
+}
+
+// Elkan and Hamerly only keep upper bounds on each row's distance to its center, so withinss comes from per-cluster
+// sums of squared norms around the data mean: the scatter around the new mean, plus the shift from the old center.
+fn centered_norms<T: Float + One + Zero + ScalarOperand>(data: &Array2<T>) -> (Array1<T>, Vec<T>) {
+    let mean = data.mean_axis(Axis(0));
+    let norms = data.outer_iter().map(|row| SquaredEuclidean.distance(row.as_slice().unwrap(), mean.as_slice().unwrap())).collect::<Vec<T>>();
+    (mean, norms)
+}
+
+fn cluster_sums<T: Float + One + Zero>(clusters: &[usize], norms: &[T], weights: Option<&[T]>, nclust: usize) -> (Vec<T>, Vec<T>) {
+    let mut squares = vec![T::zero(); nclust];
+    let mut totals = vec![T::zero(); nclust];
+    for (i, cluster) in clusters.iter().enumerate() {
+        let w = weights.map_or(T::one(), |w| w[i]);
+        squares[*cluster] = squares[*cluster] + norms[i] * w;
+        totals[*cluster] = totals[*cluster] + w;
+    }
+    (squares, totals)
+}
+
+fn scatter<T: Float + One + Zero>(kept: &[usize], squares: &[T], totals: &[T], centers: &[Array1<T>], shifts: &[T], mean: &Array1<T>) -> Vec<T> {
+    kept.iter()
+        .zip(totals.iter())
+        .zip(centers.iter().zip(shifts.iter()))
+        .map(|((old, total), (center, shift))| {
+            if *total > T::zero() {
+                let spread = SquaredEuclidean.distance(center.as_slice().unwrap(), mean.as_slice().unwrap());
+                (squares[*old] - *total * spread).max(T::zero()) + *total * *shift * *shift
+            } else {
+                T::zero()
+            }
+        })
+        .collect::<Vec<T>>()
+}
+
+fn tighten<T: Float + One + Zero, D: Fn(&ArrayView1<T>, &Array1<T>) -> T>(data: &Array2<T>, centers: &[Array1<T>], clusters: &[usize], upper: &mut [T], tight: &mut [bool], distance: D) {
+    for (i, row) in data.outer_iter().enumerate() {
+        if !tight[i] {
+            upper[i] = distance(&row, &centers[clusters[i]]);
+            tight[i] = true;
+        }
+    }
+}
+
+type CenterUpdate<T> = (Vec<Array1<T>>, Vec<usize>, Vec<T>, Vec<T>, usize);
+
+#[allow(clippy::too_many_arguments)]
+fn update_centers<T: Float + One + Zero + ScalarOperand>(
+    data: &Array2<T>,
+    clusters: &mut [usize],
+    costs: &[T],
+    weights: Option<&[T]>,
+    nclust: usize,
+    policy: EmptyCluster,
+    centroid: Centroid,
+) -> CenterUpdate<T> {
+    let weight = |i: usize| weights.map_or(T::one(), |w| w[i]);
+    let costs = costs.iter().enumerate().map(|(i, cost)| *cost * weight(i)).collect::<Vec<T>>();
+    let mut sums = vec![Array1::zeros(data.cols()); nclust];
+    let mut totals = vec![T::zero(); nclust];
+    let mut withinss = vec![T::zero(); nclust];
+    for (i, (row, cluster)) in data.outer_iter().zip(clusters.iter()).enumerate() {
+        sums[*cluster] = &sums[*cluster] + &(&row * weight(i));
+        totals[*cluster] = totals[*cluster] + weight(i);
+        withinss[*cluster] = withinss[*cluster] + costs[i];
+    }
+    let empty = totals.iter().filter(|total| **total <= T::zero()).count();
+    let mut farthest = (0..data.rows()).collect::<Vec<usize>>();
+    farthest.sort_by(|a, b| costs[*b].partial_cmp(&costs[*a]).expect("PartialOrd distance from center"));
+    let mut farthest = farthest.into_iter();
+    let mut centers = Vec::with_capacity(nclust);
+    let mut kept = Vec::with_capacity(nclust);
+    for (cluster, (sum, total)) in sums.into_iter().zip(totals.iter()).enumerate() {
+        if *total > T::zero() {
+            let mean = sum / *total;
+            let norm = mean.fold(T::zero(), |acc, x| acc + *x * *x).sqrt();
+            centers.push(match centroid {
+                Centroid::NormalizedMean if norm > T::zero() => mean / norm,
+                Centroid::Median => median(
+                    data,
+                    &clusters.iter().enumerate().filter(|(_, c)| **c == cluster).map(|(i, _)| i).collect::<Vec<usize>>(),
+                    &weight,
+                    *total,
+                ),
+                _ => mean,
+            });
+        } else if policy == EmptyCluster::Reseed {
+            centers.push(data.row(farthest.next().expect("point to reseed from")).to_owned());
+        } else {
+            continue;
+        }
+        kept.push(cluster);
+    }
+    for cluster in clusters.iter_mut() {
+        *cluster = kept.binary_search(cluster).expect("assigned cluster is kept");
+    }
+    let withinss = kept.iter().map(|cluster| withinss[*cluster]).collect::<Vec<T>>();
+    let totals = kept.iter().map(|cluster| totals[*cluster]).collect::<Vec<T>>();
+    (centers, kept, withinss, totals, empty)
+}
+
+fn median<T: Float + One + Zero, W: Fn(usize) -> T>(data: &Array2<T>, members: &[usize], weight: &W, total: T) -> Array1<T> {
+    let half = total / (T::one() + T::one());
+    Array1::from_shape_fn(data.cols(), |col| {
+        let mut values = members.iter().map(|i| (data[[*i, col]], weight(*i))).filter(|(_, w)| *w > T::zero()).collect::<Vec<(T, T)>>();
+        values.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("PartialOrd coordinate"));
+        let mut cumulative = T::zero();
+        for (i, (value, w)) in values.iter().enumerate() {
+            cumulative = cumulative + *w;
+            if cumulative > half {
+                return *value;
+            }
+            if cumulative == half {
+                return (*value + values[i + 1].0) / (T::one() + T::one());
+            }
+        }
+        values[values.len() - 1].0
+    })
+}
+
+fn initialize<T: Float + One + Zero, M: Metric<T>, R: Rng>(data: &Array2<T>, nclust: usize, init: &InitMethod<T>, weights: Option<&[T]>, metric: &M, rng: &mut R) -> Vec<Array1<T>> {
+    match init {
+        InitMethod::Random => data.outer_iter().choose_multiple(rng, nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>(),
+        InitMethod::KmeansPlusPlus => plusplus(data, nclust, weights, metric, rng),
+        InitMethod::Explicit(centers) => {
+            assert_eq!(centers.len(), nclust, "one initial center per cluster");
+            assert!(centers.iter().all(|center| center.len() == data.cols()), "initial centers must match the data dimension");
+            centers.clone()
+        }
+    }
+}
+
+pub(crate) fn plusplus<T: Float + One + Zero, M: Metric<T>, R: Rng>(data: &Array2<T>, nclust: usize, sample_weights: Option<&[T]>, metric: &M, rng: &mut R) -> Vec<Array1<T>> {
+    let n = data.rows();
+    assert!(nclust > 0 && nclust <= n, "nclust must be between 1 and the number of rows");
+    let weight = |i: usize| sample_weights.map_or(T::one(), |w| w[i]);
+    let mut centers = vec![data.row(rng.gen_range(0, n)).to_owned()];
+    let mut weights = data
+        .outer_iter()
+        .enumerate()
+        .map(|(i, row)| metric.distance(row.as_slice().unwrap(), centers[0].as_slice().unwrap()) * weight(i))
+        .collect::<Vec<T>>();
+    while centers.len() < nclust {
+        let total = weights.iter().fold(T::zero(), |acc, w| acc + *w);
+        let chosen = if total > T::zero() && total.is_finite() {
+            let mut target = total * T::from(rng.gen::<f64>()).expect("T::from(f64)");
+            weights
+                .iter()
+                .position(|w| {
+                    target = target - *w;
+                    target < T::zero()
+                })
+                .unwrap_or_else(|| weights.iter().rposition(|w| *w > T::zero()).expect("positive weight"))
+        } else {
+            rng.gen_range(0, n)
+        };
+        let center = data.row(chosen).to_owned();
+        for (i, (w, row)) in weights.iter_mut().zip(data.outer_iter()).enumerate() {
+            *w = w.min(metric.distance(row.as_slice().unwrap(), center.as_slice().unwrap()) * weight(i));
+        }
+        centers.push(center);
+    }
+    centers
+}
+
+fn assign<T: Float + One + Zero, M: Metric<T>>(data: &Array2<T>, centers: &[Array1<T>], metric: &M) -> Vec<(usize, T)> {
+    data.outer_iter().map(|row| nearest_center(centers, &row, metric)).collect::<Vec<(usize, T)>>()
+}
+
+fn nearest_center<T: Float + One + Zero, M: Metric<T>>(centers: &[Array1<T>], row: &ArrayView1<T>, metric: &M) -> (usize, T) {
+    let scratch;
+    let row = match row.as_slice() {
+        Some(row) => row,
+        None => {
+            scratch = row.to_vec();
+            &scratch[..]
+        }
+    };
+    centers
+        .iter()
+        .enumerate()
+        .map(|(i, center)| (i, metric.distance(row, center.as_slice().unwrap())))
+        .map(|(i, x)| if x.is_nan() { (i, T::from(f32::MAX).expect("T::from(f32::MAX)")) } else { (i, x) })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from center"))
+        .expect("min distance from center")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metric::Cosine;
 
     #[test]
     fn test_kmeans() {
@@ -88,7 +1119,7 @@ mod tests {
         )
         .unwrap();
 
-        let model = Kmeans::new(&data, 2, 100, 10);
+        let model = Kmeans::new(&data, 2, 100, 10).unwrap();
         assert!(model.centers.len() == 2);
 
         let classes = model.predict(&data);
@@ -96,5 +1127,288 @@ mod tests {
 
         assert!(classes.iter().take(3).all(|x| *x == class_a));
         assert!(classes.iter().skip(3).all(|x| *x != class_a));
+
+        let labels = Kmeans::fit_predict(&data, 2, 100, 10).unwrap();
+        assert!(labels.iter().take(3).all(|x| *x == labels[0]));
+        assert!(labels.iter().skip(3).all(|x| *x != labels[0]));
+    }
+
+    #[test]
+    fn test_plusplus() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 100.0, 100.0, 100.0, 100.0, -100.0, 100.0]).unwrap();
+        let mut rng = SmallRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let centers = plusplus(&data, 3, None, &SquaredEuclidean, &mut rng);
+            let mut distinct = centers.iter().map(|c| (c[0] as i64, c[1] as i64)).collect::<Vec<(i64, i64)>>();
+            distinct.sort();
+            distinct.dedup();
+            assert_eq!(distinct.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        let data = Array2::from_shape_fn((40, 2), |(row, col)| ((row * 7 + col * 3) as f64 * 0.61).sin() * 10.0);
+        let a = Kmeans::with_seed(&data, 4, 20, 3, 42).unwrap();
+        let b = Kmeans::with_seed(&data, 4, 20, 3, 42).unwrap();
+        assert_eq!(a.clusters, b.clusters);
+        assert_eq!(a.centers, b.centers);
+    }
+
+    #[test]
+    fn test_tol() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1]).unwrap();
+        let model = Kmeans::with_tol(&data, 2, 100, 1e-9, 3).unwrap();
+        assert!(model.n_iter < 100);
+        assert!(model.converged);
+        assert_eq!(model.inertia_history.len(), model.n_iter);
+        assert!(model.inertia_history.windows(2).all(|w| w[1] <= w[0] + 1e-9));
+        assert!(!Kmeans::new(&data, 2, 0, 1).unwrap().converged);
+    }
+
+    #[test]
+    fn test_accelerated() {
+        let data = Array2::from_shape_fn((60, 2), |(row, col)| ((row * 7 + col * 3) as f64 * 0.61).sin() * 10.0 + (row % 4) as f64 * 20.0);
+        let centers = plusplus(&data, 5, None, &SquaredEuclidean, &mut SmallRng::seed_from_u64(3));
+        let lloyd = Kmeans::lloyd(&data, centers.clone(), 50);
+        for model in [
+            Kmeans::elkan(&data, centers.clone(), 50, 0.0, None, EmptyCluster::Reseed, SquaredEuclidean),
+            Kmeans::hamerly(&data, centers, 50, 0.0, None, EmptyCluster::Reseed, SquaredEuclidean),
+        ] {
+            assert_eq!(lloyd.clusters, model.clusters);
+            assert_eq!(lloyd.n_iter, model.n_iter);
+            assert!(lloyd.withinss.iter().zip(model.withinss.iter()).all(|(a, b)| (a - b).abs() < 1e-9));
+        }
+
+        assert!(Kmeans::with_algorithm(&data, 4, 50, 3, Algorithm::Elkan).unwrap().converged);
+        assert!(Kmeans::with_algorithm(&data, 4, 50, 3, Algorithm::Hamerly).unwrap().converged);
+    }
+
+    #[test]
+    fn test_empty_clusters() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1]).unwrap();
+        let centers = vec![arr1(&[0.1, 0.1]), arr1(&[8.0, 8.0]), arr1(&[100.0, 100.0])];
+        let reseeded = Kmeans::lloyd_with_metric(&data, centers.clone(), 20, 0.0, EmptyCluster::Reseed, SquaredEuclidean);
+        assert_eq!(reseeded.centers.len(), 3);
+        assert!(reseeded.empty_clusters > 0);
+        assert!(reseeded.centers.iter().all(|c| c.iter().all(|x| x.is_finite())));
+
+        for model in [
+            Kmeans::lloyd_with_metric(&data, centers.clone(), 20, 0.0, EmptyCluster::Drop, SquaredEuclidean),
+            Kmeans::elkan(&data, centers.clone(), 20, 0.0, None, EmptyCluster::Drop, SquaredEuclidean),
+            Kmeans::hamerly(&data, centers.clone(), 20, 0.0, None, EmptyCluster::Drop, SquaredEuclidean),
+        ] {
+            assert_eq!(model.centers.len(), 2);
+            assert_eq!(model.withinss.len(), 2);
+            assert_eq!(model.empty_clusters, 1);
+            assert!(model.converged);
+            assert!(model.clusters.iter().all(|c| *c < 2));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel() {
+        let data = Array2::from_shape_fn((200, 3), |(row, col)| ((row * 7 + col * 3) as f64 * 0.61).sin() * 10.0 + (row % 5) as f64 * 20.0);
+        for nseeds in [1, 16] {
+            let serial = Kmeans::with_seed(&data, 5, 50, nseeds, 11).unwrap();
+            let parallel = Kmeans::par_with_seed(&data, 5, 50, nseeds, 11).unwrap();
+            assert_eq!(serial.clusters, parallel.clusters);
+            assert_eq!(serial.centers, parallel.centers);
+            assert_eq!(serial.withinss, parallel.withinss);
+        }
+
+        let builder = KmeansBuilder::new().k(5).max_iter(50).tol(0.0).nseeds(3).seed(5);
+        let serial = builder.build(&data).unwrap();
+        assert_eq!(builder.restart(&data, 7).unwrap().inertia_history, builder.par_restart(&data, 7, 4).inertia_history);
+        assert_eq!(serial.clusters, builder.par_build(&data).unwrap().clusters);
+        let fortran = Array2::from_shape_vec((200, 3).f(), data.t().iter().cloned().collect()).unwrap();
+        assert_eq!(serial.clusters, builder.par_build(&fortran).unwrap().clusters);
+    }
+
+    #[test]
+    fn test_refit() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1]).unwrap();
+        let model = Kmeans::new(&data, 2, 20, 3).unwrap();
+        let drifted = data.mapv(|x| x + 1.0);
+        let refreshed = model.refit(&drifted, 20);
+        assert_eq!(refreshed.clusters, model.clusters);
+        assert!(refreshed.centers.iter().zip(model.centers.iter()).all(|(a, b)| (a - b).iter().all(|x| (x - 1.0).abs() < 1e-12)));
+    }
+
+    #[test]
+    fn test_init() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1]).unwrap();
+        let model = Kmeans::with_init(&data, 2, 20, 5, InitMethod::Explicit(vec![arr1(&[9.0, 9.0]), arr1(&[-1.0, -1.0])])).unwrap();
+        assert_eq!(model.clusters, vec![1, 1, 1, 0, 0, 0]);
+        let random = Kmeans::with_init(&data, 2, 20, 5, InitMethod::Random).unwrap();
+        assert_eq!(random.centers.len(), 2);
+    }
+
+    #[test]
+    fn test_builder() {
+        let data = Array2::from_shape_fn((60, 2), |(row, col)| ((row * 7 + col * 3) as f64 * 0.61).sin() + (row % 3) as f64 * 20.0);
+        let builder = KmeansBuilder::new().k(3).max_iter(300).tol(1e-4).init(InitMethod::KmeansPlusPlus).seed(42);
+        let model = builder.build(&data).unwrap();
+        assert_eq!(model.centers.len(), 3);
+        assert!(model.converged);
+        assert_eq!(model.clusters, builder.build(&data).unwrap().clusters);
+        assert_eq!(model.clusters, builder.clone().algorithm(Algorithm::Hamerly).build(&data).unwrap().clusters);
+        assert_eq!(model.clusters, builder.clone().metric(Euclidean).build(&data).unwrap().clusters);
+        let hamerly = builder.clone().algorithm(Algorithm::Hamerly).metric(SquaredEuclidean);
+        assert_eq!(hamerly.algorithm, Algorithm::Hamerly);
+        assert_eq!(model.clusters, hamerly.build(&data).unwrap().clusters);
+        assert!(Fit::fit(&builder.clone().algorithm(Algorithm::Elkan).metric(Manhattan), &data).is_err());
+        let fortran = Array2::from_shape_vec((60, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(model.clusters, builder.build(&fortran).unwrap().clusters);
+        assert_eq!(model.predict(&fortran), model.clusters);
+        assert_eq!(model.transform(&fortran), model.transform(&data));
+        assert_eq!(model.totss(&fortran, None), model.totss(&data, None));
+        assert_eq!(model.refit(&fortran, 5).centers, model.refit(&data, 5).centers);
+        assert_eq!(inertia_curve(&fortran, 1, 4, 10, 2).ks, vec![1, 2, 3, 4]);
+        let mut streamed = builder.build(&data).unwrap();
+        assert_eq!(streamed.partial_fit(&fortran), model.clusters);
+
+        let builder = builder.nseeds(4);
+        let restarts = builder.build_all(&data).unwrap();
+        assert_eq!(restarts.len(), 4);
+        let lowest = restarts.iter().map(|r| r.tot_withinss()).fold(f64::INFINITY, f64::min);
+        let model = builder.build(&data).unwrap();
+        assert_eq!(lowest, model.tot_withinss());
+        let winner = builder.restart(&data, model.seed.expect("winning seed")).unwrap();
+        assert_eq!(winner.centers, model.centers);
+    }
+
+    #[test]
+    fn test_random_swap() {
+        let data = Array2::from_shape_vec((9, 1), vec![0.0, 1.0, 2.0, 10.0, 11.0, 12.0, 20.0, 21.0, 22.0]).unwrap();
+        let builder = KmeansBuilder::new().k(3).init(InitMethod::Explicit(vec![arr1(&[0.0]), arr1(&[2.0]), arr1(&[16.0])])).seed(7);
+        let stuck = builder.build(&data).unwrap();
+        assert!(stuck.tot_withinss() > 100.0);
+        let swapped = builder.random_swaps(20).build(&data).unwrap();
+        assert!((swapped.tot_withinss() - 6.0).abs() < 1e-12);
+        assert_eq!(swapped.clusters[0], swapped.clusters[2]);
+        assert!(swapped.clusters[2] != swapped.clusters[3] && swapped.clusters[5] != swapped.clusters[6]);
+    }
+
+    #[test]
+    fn test_medians() {
+        let data = Array2::from_shape_vec((8, 1), vec![0.0, 1.0, 2.0, 3.0, 100.0, 10.0, 11.0, 12.0]).unwrap();
+        let centers = vec![arr1(&[0.0]), arr1(&[11.0])];
+        let means = Kmeans::lloyd(&data, centers.clone(), 20);
+        let medians = Kmeans::lloyd_with_metric(&data, centers, 20, 0.0, EmptyCluster::Reseed, Manhattan);
+        assert_eq!(medians.clusters, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(medians.centers, vec![arr1(&[1.5]), arr1(&[11.5])]);
+        assert!(means.centers.iter().any(|c| c[0] > 30.0));
+        assert_eq!(Kmeans::medians(&data, 2, 20, 3).unwrap().centers.len(), 2);
+    }
+
+    #[test]
+    fn test_partial_fit() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1]).unwrap();
+        let mut model = Kmeans::lloyd(&data, vec![arr1(&[0.0, 0.0]), arr1(&[8.0, 8.0])], 10);
+        assert_eq!(model.counts, vec![3, 3]);
+        let labels = model.partial_fit(&arr2(&[[0.5, 0.5], [9.0, 9.0], [9.0, 9.0]]));
+        assert_eq!(labels, vec![0, 1, 1]);
+        assert_eq!(model.counts, vec![4, 5]);
+        let all = arr2(&[[0.0, 0.0], [0.2, 0.1], [0.1, 0.3], [0.5, 0.5]]);
+        assert!((&model.centers[0] - &all.mean_axis(Axis(0))).iter().all(|x| x.abs() < 1e-12));
+        assert!((model.withinss[1] - 1.931_191_666_666_666).abs() < 1e-9);
+
+        let mut weighted = Kmeans::lloyd(&data, vec![arr1(&[0.0, 0.0]), arr1(&[8.0, 8.0])], 10);
+        weighted.partial_fit_weighted(&arr2(&[[0.5, 0.5], [0.9, 0.9]]), &[3.0, 0.0]);
+        assert_eq!(weighted.cluster_weights, vec![6.0, 3.0]);
+        assert!((&weighted.centers[0] - &arr1(&[0.3, 1.9 / 6.0])).iter().all(|x| x.abs() < 1e-12));
+
+        let line = Array2::from_shape_vec((6, 1), vec![0.0, 1.0, 2.0, 10.0, 11.0, 12.0]).unwrap();
+        let mut medians = Kmeans::lloyd_with_metric(&line, vec![arr1(&[1.0]), arr1(&[11.0])], 10, 0.0, EmptyCluster::Reseed, Manhattan);
+        medians.partial_fit(&arr2(&[[100.0]]));
+        assert!((medians.centers[1][0] - (11.0 + 1.0 / 6.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_weights() {
+        let data = Array2::from_shape_vec((6, 1), vec![0.0, 1.0, 2.0, 10.0, 11.0, 12.0]).unwrap();
+        let model = Kmeans::with_weights(&data, 2, 20, 3, &[1.0, 1.0, 4.0, 1.0, 0.0, 1.0]).unwrap();
+        let mut centers = model.centers.iter().map(|c| c[0]).collect::<Vec<f64>>();
+        centers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((centers[0] - 1.5).abs() < 1e-12);
+        assert!((centers[1] - 11.0).abs() < 1e-12);
+        assert!((model.withinss.iter().sum::<f64>() - 5.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sums_of_squares() {
+        let data = Array2::from_shape_vec((4, 1), vec![0.0, 2.0, 10.0, 12.0]).unwrap();
+        let model = Kmeans::lloyd(&data, vec![arr1(&[0.0]), arr1(&[12.0])], 10);
+        assert_eq!(model.tot_withinss(), 4.0);
+        assert_eq!(model.totss(&data, None), 104.0);
+        assert_eq!(model.betweenss(&data, None), 100.0);
+        assert_eq!(model.totss(&Array2::zeros((0, 1)), None), 0.0);
+
+        let weights = [1.0, 3.0, 0.0, 2.0];
+        let weighted = Kmeans::with_weights(&data, 2, 10, 1, &weights).unwrap();
+        assert_eq!(weighted.totss(&data, Some(&weights)), 150.0);
+        assert_eq!(weighted.betweenss(&data, Some(&weights)), 147.0);
+        assert_eq!(model.transform(&arr2(&[[1.0], [11.0]])), arr2(&[[0.0, 100.0], [100.0, 0.0]]));
+
+        let proba = model.predict_proba(&arr2(&[[1.0], [6.0], [11.0]]), 10.0);
+        assert!(proba.outer_iter().all(|row| (row.sum() - 1.0).abs() < 1e-12));
+        assert!(proba[[0, 0]] > 0.99 && proba[[2, 1]] > 0.99);
+        assert!((proba[[1, 0]] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_inertia_curve() {
+        let data = Array2::from_shape_fn((30, 2), |(row, col)| {
+            let center = [(0.0, 0.0), (10.0, 10.0), (20.0, 0.0)][row / 10];
+            let jitter = ((row % 10) as f64 * 0.37 + col as f64).sin() * 0.5;
+            if col == 0 {
+                center.0 + jitter
+            } else {
+                center.1 + jitter
+            }
+        });
+        let curve = inertia_curve(&data, 1, 6, 30, 10);
+        assert_eq!(curve.ks, vec![1, 2, 3, 4, 5, 6]);
+        assert!(curve.inertias.windows(2).all(|w| w[1] <= w[0] + 1e-9));
+        assert_eq!(dbg!(&curve).knee, Some(3));
+    }
+
+    #[test]
+    fn test_gap_statistic() {
+        let data = Array2::from_shape_fn((20, 2), |(row, col)| {
+            let center = [(0.0, 0.0), (10.0, 10.0)][row / 10];
+            let jitter = ((row % 10) as f64 * 0.37).sin() * 0.5;
+            if col == 0 {
+                center.0 + jitter
+            } else {
+                center.1 + ((row % 10) as f64 * 0.91).cos() * 0.5
+            }
+        });
+        let gap = gap_statistic_with_rng(&data, 1, 5, 10, 30, 10, &mut SmallRng::seed_from_u64(11));
+        assert_eq!(gap.ks, vec![1, 2, 3, 4, 5]);
+        assert_eq!(dbg!(&gap).best, 2);
+
+        let seeded = gap_statistic_with_rng(&data, 1, 4, 5, 30, 3, &mut SmallRng::seed_from_u64(7));
+        let again = gap_statistic_with_rng(&data, 1, 4, 5, 30, 3, &mut SmallRng::seed_from_u64(7));
+        assert_eq!((seeded.gaps, seeded.errors, seeded.best), (again.gaps, again.errors, again.best));
+    }
+
+    #[test]
+    fn test_metric() {
+        let data = Array2::from_shape_vec((6, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1]).unwrap();
+        let model = Kmeans::with_metric(&data, 2, 20, 5, Manhattan).unwrap();
+        let classes = model.predict(&data);
+        assert!(classes.iter().take(3).all(|x| *x == classes[0]));
+        assert!(classes.iter().skip(3).all(|x| *x != classes[0]));
+        assert_eq!(model.metric, Manhattan);
+
+        let directions = Array2::from_shape_vec((4, 2), vec![1.0, 0.1, 5.0, 0.4, 0.1, 1.0, 0.3, 6.0]).unwrap();
+        let model = Kmeans::with_metric(&directions, 2, 20, 5, Cosine).unwrap();
+        assert_eq!(model.clusters[0], model.clusters[1]);
+        assert_eq!(model.clusters[2], model.clusters[3]);
+        assert!(model.centers.iter().all(|c| (c.dot(c) - 1.0).abs() < 1e-12));
     }
 }
@@ -0,0 +1,273 @@
+use crate::error::Error;
+use crate::metric::Metric;
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::rngs::SmallRng;
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+
+#[derive(Debug)]
+pub struct Kmedoids<T: Float + One + Zero> {
+    pub medoids: Vec<usize>,
+    pub clusters: Vec<usize>,
+    pub cost: T,
+}
+
+impl<T: Float + One + Zero> Kmedoids<T> {
+    pub fn new<M>(data: &Array2<T>, nclust: usize, iterations: usize, metric: &M) -> Kmedoids<T>
+    where
+        M: Metric<T>,
+    {
+        Self::from_distances(&distance_matrix(data, metric), nclust, iterations)
+    }
+
+    pub fn from_distances(distances: &Array2<T>, nclust: usize, iterations: usize) -> Kmedoids<T> {
+        let n = distances.rows();
+        assert!(nclust > 0 && nclust <= n, "nclust must be between 1 and the number of rows");
+
+        let mut medoids = Vec::with_capacity(nclust);
+        let mut nearest = vec![T::infinity(); n];
+        while medoids.len() < nclust {
+            let best = (0..n)
+                .filter(|candidate| !medoids.contains(candidate))
+                .map(|candidate| (candidate, (0..n).fold(T::zero(), |acc, j| acc + nearest[j].min(distances[[candidate, j]]))))
+                .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd medoid cost"))
+                .expect("candidate medoid")
+                .0;
+            for (j, d) in nearest.iter_mut().enumerate() {
+                *d = d.min(distances[[best, j]]);
+            }
+            medoids.push(best);
+        }
+
+        let pairwise = |a: usize, b: usize| distances[[a, b]];
+        for _ in 0..iterations {
+            let (assignment, first, second) = assign(n, &medoids, &pairwise);
+            let mut best_swap = None;
+            let mut best_delta = T::zero();
+            for i in 0..nclust {
+                for candidate in (0..n).filter(|c| !medoids.contains(c)) {
+                    let delta = swap_delta(n, &assignment, &first, &second, i, candidate, &pairwise);
+                    if delta < best_delta {
+                        best_delta = delta;
+                        best_swap = Some((i, candidate));
+                    }
+                }
+            }
+            match best_swap {
+                Some((i, candidate)) => medoids[i] = candidate,
+                None => break,
+            }
+        }
+
+        let (clusters, first, _) = assign(n, &medoids, &pairwise);
+        Kmedoids {
+            medoids,
+            clusters,
+            cost: first.iter().fold(T::zero(), |acc, d| acc + *d),
+        }
+    }
+
+    pub fn clarans<M>(data: &Array2<T>, nclust: usize, num_local: usize, max_neighbor: usize, metric: &M) -> Result<Kmedoids<T>, Error>
+    where
+        M: Metric<T>,
+    {
+        let mut rng = SmallRng::from_rng(thread_rng()).expect("seed SmallRng from thread_rng");
+        Self::clarans_with_rng(data, nclust, num_local, max_neighbor, metric, &mut rng)
+    }
+
+    pub fn clarans_with_rng<M, R>(data: &Array2<T>, nclust: usize, num_local: usize, max_neighbor: usize, metric: &M, rng: &mut R) -> Result<Kmedoids<T>, Error>
+    where
+        M: Metric<T>,
+        R: Rng,
+    {
+        let n = data.rows();
+        if nclust == 0 || nclust >= n {
+            return Err(Error::InvalidParameter(format!(
+                "nclust = {} must be between 1 and the number of rows - 1 ({})",
+                nclust,
+                n.saturating_sub(1)
+            )));
+        }
+        if !data.is_standard_layout() {
+            return Self::clarans_with_rng(&standard_layout(data.view()), nclust, num_local, max_neighbor, metric, rng);
+        }
+
+        let pairwise = |a: usize, b: usize| metric.distance(data.row(a).as_slice().unwrap(), data.row(b).as_slice().unwrap());
+        Ok((0..num_local.max(1))
+            .map(|_| {
+                let mut medoids = sample(rng, n, nclust).into_vec();
+                let (mut assignment, mut first, mut second) = assign(n, &medoids, &pairwise);
+                let mut tries = 0;
+                while tries < max_neighbor {
+                    let i = rng.gen_range(0, nclust);
+                    let candidate = rng.gen_range(0, n);
+                    if medoids.contains(&candidate) {
+                        continue;
+                    }
+                    if swap_delta(n, &assignment, &first, &second, i, candidate, &pairwise) < T::zero() {
+                        medoids[i] = candidate;
+                        let assigned = assign(n, &medoids, &pairwise);
+                        assignment = assigned.0;
+                        first = assigned.1;
+                        second = assigned.2;
+                        tries = 0;
+                    } else {
+                        tries += 1;
+                    }
+                }
+                Kmedoids {
+                    medoids,
+                    clusters: assignment,
+                    cost: first.iter().fold(T::zero(), |acc, d| acc + *d),
+                }
+            })
+            .min_by(|a, b| a.cost.partial_cmp(&b.cost).expect("PartialOrd medoid cost"))
+            .expect("min medoid cost"))
+    }
+
+    pub fn predict<M>(&self, data: &Array2<T>, new_data: &Array2<T>, metric: &M) -> Vec<usize>
+    where
+        M: Metric<T>,
+    {
+        if !data.is_standard_layout() || !new_data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()), &standard_layout(new_data.view()), metric);
+        }
+        new_data
+            .outer_iter()
+            .map(|row| {
+                self.medoids
+                    .iter()
+                    .enumerate()
+                    .map(|(i, medoid)| (i, metric.distance(row.as_slice().unwrap(), data.row(*medoid).as_slice().unwrap())))
+                    .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from medoid"))
+                    .expect("min distance from medoid")
+                    .0
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+pub(crate) fn distance_matrix<T, M>(data: &Array2<T>, metric: &M) -> Array2<T>
+where
+    T: Float + One + Zero,
+    M: Metric<T>,
+{
+    if !data.is_standard_layout() {
+        return distance_matrix(&standard_layout(data.view()), metric);
+    }
+    let n = data.rows();
+    let mut distances = Array2::zeros((n, n));
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let d = metric.distance(data.row(i).as_slice().unwrap(), data.row(j).as_slice().unwrap());
+            distances[[i, j]] = d;
+            distances[[j, i]] = d;
+        }
+    }
+    distances
+}
+
+fn assign<T, D>(n: usize, medoids: &[usize], pairwise: &D) -> (Vec<usize>, Vec<T>, Vec<T>)
+where
+    T: Float + One + Zero,
+    D: Fn(usize, usize) -> T,
+{
+    let mut assignment = vec![0; n];
+    let mut first = vec![T::infinity(); n];
+    let mut second = vec![T::infinity(); n];
+    for j in 0..n {
+        for (i, medoid) in medoids.iter().enumerate() {
+            let d = pairwise(*medoid, j);
+            if d < first[j] {
+                second[j] = first[j];
+                first[j] = d;
+                assignment[j] = i;
+            } else if d < second[j] {
+                second[j] = d;
+            }
+        }
+    }
+    (assignment, first, second)
+}
+
+fn swap_delta<T, D>(n: usize, assignment: &[usize], first: &[T], second: &[T], medoid: usize, candidate: usize, pairwise: &D) -> T
+where
+    T: Float + One + Zero,
+    D: Fn(usize, usize) -> T,
+{
+    (0..n).fold(T::zero(), |acc, j| {
+        let d = pairwise(candidate, j);
+        if assignment[j] == medoid {
+            acc + d.min(second[j]) - first[j]
+        } else {
+            acc + (d - first[j]).min(T::zero())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::Dtw;
+    use itertools::Itertools;
+
+    fn manhattan(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum()
+    }
+
+    #[test]
+    fn test_medoids() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap();
+        let model = Kmedoids::new(&data, 2, 10, &manhattan);
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+        assert!(model.medoids.iter().any(|m| *m < 4) && model.medoids.iter().any(|m| *m >= 4));
+
+        let new_data = Array2::from_shape_vec((2, 2), vec![1.0, 2.05, -2.0, 3.05]).unwrap();
+        let predicted = model.predict(&data, &new_data, &manhattan);
+        assert_eq!(predicted, vec![clustering[0], clustering[4]]);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(Kmedoids::new(&fortran, 2, 10, &manhattan).medoids, model.medoids);
+        assert_eq!(model.predict(&fortran, &new_data.t().to_owned().reversed_axes(), &manhattan), predicted);
+        assert_eq!(Kmedoids::clarans_with_rng(&fortran, 2, 4, 20, &manhattan, &mut SmallRng::seed_from_u64(7)).unwrap().medoids.len(), 2);
+    }
+
+    #[test]
+    fn test_clarans() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap();
+        let pam = Kmedoids::new(&data, 2, 10, &manhattan);
+        let model = Kmedoids::clarans_with_rng(&data, 2, 4, 20, &manhattan, &mut SmallRng::seed_from_u64(7)).unwrap();
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+        assert!((model.cost - pam.cost).abs() < 1e-9);
+        assert!(Kmedoids::clarans(&data, 2, 4, 20, &manhattan).is_ok());
+        assert_eq!(
+            Kmedoids::clarans(&data, 8, 4, 20, &manhattan).unwrap_err(),
+            Error::InvalidParameter("nclust = 8 must be between 1 and the number of rows - 1 (7)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dtw() {
+        let data = Array2::from_shape_vec(
+            (4, 6),
+            vec![0.0, 1.0, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0, 0.0, 2.0, 2.0, 0.0, 0.0, 2.0, 2.0, 2.0, 0.0, 0.0, 2.0, 2.0, 2.0],
+        )
+        .unwrap();
+        let model = Kmedoids::new(&data, 2, 10, &Dtw::new(Some(2)));
+        let clustering = dbg!(&model.clusters);
+        assert_eq!(clustering[0], clustering[1]);
+        assert_eq!(clustering[2], clustering[3]);
+        assert!(clustering[0] != clustering[2]);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::Error;
+use ndarray::prelude::*;
+use rand::prelude::thread_rng;
+use rand::prelude::IteratorRandom;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug)]
+pub struct Kmodes<T: Copy + Eq + Hash> {
+    pub modes: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+    pub cost: usize,
+}
+
+impl<T: Copy + Eq + Hash> Kmodes<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize) -> Result<Kmodes<T>, Error> {
+        let mut rng = SmallRng::from_rng(thread_rng()).expect("seed SmallRng from thread_rng");
+        Self::with_rng(data, nclust, iterations, nseeds, &mut rng)
+    }
+
+    pub fn with_rng<R: Rng>(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize, rng: &mut R) -> Result<Kmodes<T>, Error> {
+        if nclust == 0 || nclust > data.rows() {
+            return Err(Error::InvalidParameter(format!("nclust = {} must be between 1 and the number of rows ({})", nclust, data.rows())));
+        }
+        Ok((0..nseeds.max(1))
+            .map(|_| {
+                let mut modes = data.outer_iter().choose_multiple(rng, nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>();
+                let mut clusters = vec![nclust; data.rows()];
+                for _ in 0..iterations {
+                    let mut changed = false;
+                    for (row_idx, row) in data.outer_iter().enumerate() {
+                        let cluster = Self::predict_with_modes(&modes, &row);
+                        if clusters[row_idx] != cluster {
+                            clusters[row_idx] = cluster;
+                            changed = true;
+                        }
+                    }
+                    if !changed {
+                        break;
+                    }
+                    for (cluster, mode) in modes.iter_mut().enumerate() {
+                        let members = (0..data.rows()).filter(|row_idx| clusters[*row_idx] == cluster).collect::<Vec<usize>>();
+                        if members.is_empty() {
+                            continue;
+                        }
+                        for (col_idx, value) in mode.iter_mut().enumerate() {
+                            *value = most_frequent(members.iter().map(|row_idx| data[[*row_idx, col_idx]]));
+                        }
+                    }
+                }
+                let clusters = data.outer_iter().map(|row| Self::predict_with_modes(&modes, &row)).collect::<Vec<usize>>();
+                let cost = data.outer_iter().zip(clusters.iter()).map(|(row, cluster)| dissimilarity(&row, &modes[*cluster].view())).sum();
+                Kmodes { modes, clusters, cost }
+            })
+            .min_by_key(|model| model.cost)
+            .expect("min cost"))
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        data.outer_iter().map(|row| Self::predict_with_modes(&self.modes, &row)).collect::<Vec<usize>>()
+    }
+
+    pub fn predict_with_modes(modes: &[Array1<T>], row: &ArrayView1<T>) -> usize {
+        modes
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, mode)| dissimilarity(row, &mode.view()))
+            .expect("min dissimilarity from mode")
+            .0
+    }
+}
+
+pub fn dissimilarity<T: Copy + Eq>(a: &ArrayView1<T>, b: &ArrayView1<T>) -> usize {
+    a.iter().zip(b.iter()).filter(|(x, y)| x != y).count()
+}
+
+fn most_frequent<T: Copy + Eq + Hash, I: Iterator<Item = T>>(values: I) -> T {
+    let mut counts = HashMap::new();
+    let mut best = None;
+    for value in values {
+        let count = counts.entry(value).or_insert(0);
+        *count += 1;
+        best = match best {
+            Some((_, best_count)) if best_count >= *count => best,
+            _ => Some((value, *count)),
+        };
+    }
+    best.expect("non-empty values").0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_kmodes() {
+        let data: Array2<u32> = Array2::from_shape_vec((6, 3), vec![0, 1, 2, 0, 1, 3, 0, 1, 2, 5, 6, 7, 5, 6, 7, 4, 6, 7]).unwrap();
+        let model = Kmodes::with_rng(&data, 2, 10, 5, &mut SmallRng::seed_from_u64(3)).unwrap();
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(3).all_equal());
+        assert!(clustering.iter().skip(3).all_equal());
+        assert!(clustering[0] != clustering[3]);
+        assert_eq!(model.cost, 2);
+        assert_eq!(model.modes[clustering[0]], arr1(&[0, 1, 2]));
+        assert_eq!(model.predict(&arr2(&[[5, 6, 0]])), vec![clustering[3]]);
+        assert!(Kmodes::new(&data, 2, 10, 5).is_ok());
+        assert_eq!(
+            Kmodes::new(&data, 7, 10, 5).unwrap_err(),
+            Error::InvalidParameter("nclust = 7 must be between 1 and the number of rows (6)".to_string())
+        );
+    }
+}
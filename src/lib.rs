@@ -3,8 +3,45 @@ extern crate kdtree;
 extern crate ndarray;
 extern crate rand;
 
+pub mod birch;
+pub mod bisecting;
+pub mod canopy;
+pub mod consensus;
+pub mod constrained;
+pub mod coreset;
+pub mod dataset;
 pub mod dbscan;
+pub mod divisive;
+pub mod dpmeans;
+pub mod error;
+pub mod gmeans;
+pub mod graph;
+pub mod harmonic;
+pub mod hdbscan;
+pub mod hierarchical;
 pub mod kmeans;
+pub mod kmedoids;
+pub mod kmodes;
+pub mod meanshift;
+pub mod metric;
+pub mod metrics;
+pub mod model;
+pub mod optics;
+pub mod pic;
+pub mod quantization;
+pub mod rock;
+pub mod som;
+pub mod sparse;
+pub mod spherical;
+pub mod stability;
+pub mod stream;
+pub mod trimmed;
+pub mod validation;
+pub mod vocabulary;
+pub mod vptree;
+pub mod xmeans;
+
+pub use crate::error::Error;
 
 #[cfg(test)]
 mod tests {}
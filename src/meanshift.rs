@@ -0,0 +1,155 @@
+use crate::dbscan::{kdtree_init, region_query_with_distances};
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel {
+    Flat,
+    Gaussian,
+}
+
+#[derive(Debug)]
+pub struct MeanShift<T: Float + One + Zero> {
+    pub bandwidth: T,
+    pub kernel: Kernel,
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+}
+
+impl<T: Float + One + Zero> MeanShift<T> {
+    pub fn new(data: &Array2<T>, bandwidth: Option<T>, kernel: Kernel, iterations: usize) -> MeanShift<T> {
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), bandwidth, kernel, iterations);
+        }
+        let bandwidth = bandwidth.unwrap_or_else(|| Self::estimate_bandwidth(data, T::from(0.3).expect("T::from(f64)")));
+        let radius = match kernel {
+            Kernel::Flat => bandwidth,
+            Kernel::Gaussian => bandwidth * T::from(3.0).expect("T::from(f64)"),
+        };
+        let tol = bandwidth * T::from(1e-3).expect("T::from(f64)");
+        let kdt = kdtree_init(data);
+        let mut neighbours = Vec::with_capacity(data.rows());
+
+        let mut modes = data
+            .outer_iter()
+            .map(|row| {
+                let mut mode = row.to_owned();
+                for _ in 0..iterations {
+                    neighbours.clear();
+                    region_query_with_distances(mode.as_slice().unwrap(), radius, &kdt, &mut neighbours);
+                    let mut shifted = Array1::zeros(data.cols());
+                    let mut total = T::zero();
+                    for (distance, neighbour_idx) in neighbours.iter() {
+                        let weight = match kernel {
+                            Kernel::Flat => T::one(),
+                            Kernel::Gaussian => (-distance.powi(2) / (bandwidth.powi(2) * T::from(2.0).expect("T::from(f64)"))).exp(),
+                        };
+                        shifted.zip_mut_with(&data.row(*neighbour_idx), |s, x| *s = *s + weight * *x);
+                        total = total + weight;
+                    }
+                    if total <= T::zero() {
+                        break;
+                    }
+                    shifted.mapv_inplace(|s| s / total);
+                    let shift = squared_euclidean(shifted.as_slice().unwrap(), mode.as_slice().unwrap()).sqrt();
+                    mode = shifted;
+                    if shift < tol {
+                        break;
+                    }
+                }
+                neighbours.clear();
+                region_query_with_distances(mode.as_slice().unwrap(), bandwidth, &kdt, &mut neighbours);
+                (neighbours.len(), mode)
+            })
+            .collect::<Vec<(usize, Array1<T>)>>();
+
+        modes.sort_by_key(|(support, _)| std::cmp::Reverse(*support));
+        let mut centers: Vec<Array1<T>> = Vec::new();
+        for (_, mode) in modes {
+            if centers.iter().all(|center| squared_euclidean(center.as_slice().unwrap(), mode.as_slice().unwrap()).sqrt() >= bandwidth) {
+                centers.push(mode);
+            }
+        }
+
+        let mut model = MeanShift {
+            bandwidth,
+            kernel,
+            centers,
+            clusters: Vec::new(),
+        };
+        model.clusters = model.predict(data);
+        model
+    }
+
+    pub fn estimate_bandwidth(data: &Array2<T>, quantile: T) -> T {
+        if !data.is_standard_layout() {
+            return Self::estimate_bandwidth(&standard_layout(data.view()), quantile);
+        }
+        let k = T::from(data.rows()).expect("T::from(usize)") * quantile;
+        let k = k.to_usize().expect("usize::from(T)").max(1);
+        let kdt = kdtree_init(data);
+        let total = data.outer_iter().fold(T::zero(), |acc, row| {
+            let nearest = kdt.nearest(row.as_slice().unwrap(), k, &squared_euclidean).expect("KdTree error checking point");
+            acc + nearest.last().map_or(T::zero(), |(distance, _)| distance.sqrt())
+        });
+        total / T::from(data.rows().max(1)).expect("T::from(usize)")
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()));
+        }
+        data.outer_iter()
+            .map(|row| {
+                self.centers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, center)| (i, squared_euclidean(row.as_slice().unwrap(), center.as_slice().unwrap())))
+                    .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from center"))
+                    .expect("min distance from center")
+                    .0
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap()
+    }
+
+    #[test]
+    fn test_kernels() {
+        for kernel in [Kernel::Flat, Kernel::Gaussian].iter() {
+            let model = MeanShift::new(&data(), Some(1.0), *kernel, 100);
+            assert_eq!(model.centers.len(), 2);
+            let clustering = dbg!(&model.clusters);
+            assert!(clustering.iter().take(4).all_equal());
+            assert!(clustering.iter().skip(4).all_equal());
+            assert!(clustering[0] != clustering[4]);
+        }
+    }
+
+    #[test]
+    fn test_estimated_bandwidth() {
+        let bandwidth = MeanShift::estimate_bandwidth(&data(), 0.5);
+        assert!(bandwidth > 0.0 && bandwidth < 3.0);
+
+        let model = MeanShift::new(&data(), None, Kernel::Flat, 100);
+        let clustering = dbg!(&model.clusters);
+        assert!(clustering.iter().take(4).all(|a| clustering.iter().skip(4).all(|b| a != b)));
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(MeanShift::estimate_bandwidth(&fortran, 0.5), bandwidth);
+        assert_eq!(MeanShift::new(&fortran, None, Kernel::Flat, 100).centers, model.centers);
+        assert_eq!(model.predict(&fortran), model.clusters);
+    }
+}
@@ -0,0 +1,550 @@
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Centroid {
+    Mean,
+    NormalizedMean,
+    Median,
+}
+
+pub trait Metric<T: Float + One + Zero> {
+    fn distance(&self, a: &[T], b: &[T]) -> T;
+
+    fn kdtree_compatible(&self) -> bool {
+        false
+    }
+
+    fn grid_compatible(&self) -> bool {
+        false
+    }
+
+    fn elkan_compatible(&self) -> bool {
+        false
+    }
+
+    fn centroid(&self) -> Centroid {
+        Centroid::Mean
+    }
+}
+
+impl<T: Float + One + Zero, F: Fn(&[T], &[T]) -> T> Metric<T> for F {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        self(a, b)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Custom<F> {
+    pub distance: F,
+    pub kdtree_compatible: bool,
+}
+
+impl<F> Custom<F> {
+    pub fn new(distance: F, kdtree_compatible: bool) -> Custom<F> {
+        Custom { distance, kdtree_compatible }
+    }
+}
+
+impl<T: Float + One + Zero, F: Fn(&[T], &[T]) -> T> Metric<T> for Custom<F> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        (self.distance)(a, b)
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        self.kdtree_compatible
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Euclidean;
+
+impl<T: Float + One + Zero> Metric<T> for Euclidean {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        SquaredEuclidean.distance(a, b).sqrt()
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        true
+    }
+
+    fn grid_compatible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SquaredEuclidean;
+
+impl<T: Float + One + Zero> Metric<T> for SquaredEuclidean {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        a.iter().zip(b.iter()).fold(T::zero(), |acc, (x, y)| acc + (*x - *y) * (*x - *y))
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        true
+    }
+
+    fn elkan_compatible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Manhattan;
+
+impl<T: Float + One + Zero> Metric<T> for Manhattan {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        a.iter().zip(b.iter()).fold(T::zero(), |acc, (x, y)| acc + (*x - *y).abs())
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        true
+    }
+
+    fn grid_compatible(&self) -> bool {
+        true
+    }
+
+    fn centroid(&self) -> Centroid {
+        Centroid::Median
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Minkowski<T: Float + One + Zero> {
+    pub p: T,
+}
+
+impl<T: Float + One + Zero> Minkowski<T> {
+    pub fn new(p: T) -> Minkowski<T> {
+        assert!(p > T::zero(), "p must be positive");
+        Minkowski { p }
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Minkowski<T> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let diffs = a.iter().zip(b.iter()).map(|(x, y)| (*x - *y).abs());
+        if self.p.is_infinite() {
+            diffs.fold(T::zero(), T::max)
+        } else {
+            diffs.fold(T::zero(), |acc, d| acc + d.powf(self.p)).powf(self.p.recip())
+        }
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        true
+    }
+
+    fn grid_compatible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Cosine;
+
+impl<T: Float + One + Zero> Metric<T> for Cosine {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let (dot, norm_a, norm_b) = a
+            .iter()
+            .zip(b.iter())
+            .fold((T::zero(), T::zero(), T::zero()), |(dot, na, nb), (x, y)| (dot + *x * *y, na + *x * *x, nb + *y * *y));
+        let norm = (norm_a * norm_b).sqrt();
+        if norm > T::zero() {
+            (T::one() - dot / norm).max(T::zero())
+        } else {
+            T::one()
+        }
+    }
+
+    fn centroid(&self) -> Centroid {
+        Centroid::NormalizedMean
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Hamming;
+
+impl Hamming {
+    pub fn packed(a: &[u64], b: &[u64]) -> u32 {
+        a.iter().zip(b.iter()).map(|(x, y)| (x ^ y).count_ones()).sum()
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Hamming {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        T::from(a.iter().zip(b.iter()).filter(|(x, y)| x != y).count()).expect("T::from(usize)")
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Jaccard;
+
+impl Jaccard {
+    pub fn sorted(a: &[usize], b: &[usize]) -> f64 {
+        let (mut i, mut j, mut shared) = (0, 0, 0);
+        while i < a.len() && j < b.len() {
+            match a[i].cmp(&b[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        let union = a.len() + b.len() - shared;
+        if union == 0 {
+            0.0
+        } else {
+            1.0 - shared as f64 / union as f64
+        }
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Jaccard {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let (shared, union) = a.iter().zip(b.iter()).fold((0, 0), |(shared, union), (x, y)| match (*x != T::zero(), *y != T::zero()) {
+            (true, true) => (shared + 1, union + 1),
+            (false, false) => (shared, union),
+            _ => (shared, union + 1),
+        });
+        if union == 0 {
+            T::zero()
+        } else {
+            T::one() - T::from(shared).expect("T::from(usize)") / T::from(union).expect("T::from(usize)")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Dtw {
+    pub window: Option<usize>,
+}
+
+impl Dtw {
+    pub fn new(window: Option<usize>) -> Dtw {
+        Dtw { window }
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Dtw {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let (n, m) = (a.len(), b.len());
+        let window = self.window.map_or(n.max(m), |w| w.max(n.abs_diff(m)));
+        let mut previous = vec![T::infinity(); m + 1];
+        let mut current = vec![T::infinity(); m + 1];
+        previous[0] = T::zero();
+        for i in 1..=n {
+            current[0] = T::infinity();
+            for j in 1..=m {
+                current[j] = if i.abs_diff(j) > window {
+                    T::infinity()
+                } else {
+                    (a[i - 1] - b[j - 1]).abs() + previous[j - 1].min(previous[j]).min(current[j - 1])
+                };
+            }
+            std::mem::swap(&mut previous, &mut current);
+        }
+        previous[m]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Numeric,
+    Categorical,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gower<T: Float + One + Zero> {
+    pub columns: Vec<Column>,
+    pub ranges: Vec<T>,
+}
+
+impl<T: Float + One + Zero> Gower<T> {
+    pub fn new(columns: Vec<Column>, ranges: Vec<T>) -> Gower<T> {
+        assert_eq!(columns.len(), ranges.len(), "one range per column");
+        Gower { columns, ranges }
+    }
+
+    pub fn from_data(data: &Array2<T>, columns: Vec<Column>) -> Gower<T> {
+        assert_eq!(columns.len(), data.cols(), "one column specification per data column");
+        let ranges = data
+            .gencolumns()
+            .into_iter()
+            .map(|col| col.fold(T::neg_infinity(), |acc, x| acc.max(*x)) - col.fold(T::infinity(), |acc, x| acc.min(*x)))
+            .collect::<Vec<T>>();
+        Gower::new(columns, ranges)
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Gower<T> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let total = self
+            .columns
+            .iter()
+            .zip(self.ranges.iter())
+            .zip(a.iter().zip(b.iter()))
+            .fold(T::zero(), |acc, ((column, range), (x, y))| {
+                acc + match column {
+                    Column::Numeric if *range > T::zero() => (*x - *y).abs() / *range,
+                    Column::Numeric => T::zero(),
+                    Column::Categorical if x != y => T::one(),
+                    Column::Categorical => T::zero(),
+                }
+            });
+        total / T::from(self.columns.len().max(1)).expect("T::from(usize)")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mahalanobis<T: Float + One + Zero> {
+    pub precision: Array2<T>,
+}
+
+impl<T: Float + One + Zero> Mahalanobis<T> {
+    pub fn new(precision: Array2<T>) -> Mahalanobis<T> {
+        assert_eq!(precision.rows(), precision.cols(), "precision matrix must be square");
+        Mahalanobis { precision }
+    }
+
+    pub fn from_data(data: &Array2<T>) -> Mahalanobis<T> {
+        let n = T::from(data.rows()).expect("T::from(usize)");
+        let mean = data.outer_iter().fold(Array1::<T>::zeros(data.cols()), |acc, row| acc + row).mapv(|x| x / n);
+        let mut covariance = Array2::<T>::zeros((data.cols(), data.cols()));
+        for row in data.outer_iter() {
+            let diff = &row - &mean;
+            for i in 0..data.cols() {
+                for j in 0..data.cols() {
+                    covariance[[i, j]] = covariance[[i, j]] + diff[i] * diff[j];
+                }
+            }
+        }
+        let covariance = covariance.mapv(|x| x / (n - T::one()).max(T::one()));
+        Mahalanobis::new(invert(&covariance).expect("covariance matrix is invertible"))
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Mahalanobis<T> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let diff = a.iter().zip(b.iter()).map(|(x, y)| *x - *y).collect::<Vec<T>>();
+        let mut total = T::zero();
+        for (i, di) in diff.iter().enumerate() {
+            for (j, dj) in diff.iter().enumerate() {
+                total = total + *di * self.precision[[i, j]] * *dj;
+            }
+        }
+        total.max(T::zero()).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anisotropic<T: Float + One + Zero> {
+    pub eps: Vec<T>,
+}
+
+impl<T: Float + One + Zero> Anisotropic<T> {
+    pub fn new(eps: &[T]) -> Anisotropic<T> {
+        assert!(eps.iter().all(|e| *e > T::zero()), "every per-dimension eps must be positive");
+        Anisotropic { eps: eps.to_vec() }
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Anisotropic<T> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        a.iter().zip(b.iter()).zip(self.eps.iter()).fold(T::zero(), |acc, ((x, y), e)| acc + ((*x - *y) / *e).powi(2)).sqrt()
+    }
+
+    fn kdtree_compatible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Periodic<T: Float + One + Zero> {
+    pub periods: Vec<Option<T>>,
+}
+
+impl<T: Float + One + Zero> Periodic<T> {
+    pub fn new(periods: &[Option<T>]) -> Periodic<T> {
+        assert!(periods.iter().flatten().all(|p| *p > T::zero()), "every period must be positive");
+        Periodic { periods: periods.to_vec() }
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Periodic<T> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        a.iter()
+            .zip(b.iter())
+            .zip(self.periods.iter())
+            .fold(T::zero(), |acc, ((x, y), period)| {
+                let d = (*x - *y).abs();
+                let d = match period {
+                    Some(period) => {
+                        let d = d % *period;
+                        d.min(*period - d)
+                    }
+                    None => d,
+                };
+                acc + d * d
+            })
+            .sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Haversine<T: Float + One + Zero> {
+    pub radius: T,
+}
+
+impl<T: Float + One + Zero> Haversine<T> {
+    pub fn new(radius: T) -> Haversine<T> {
+        Haversine { radius }
+    }
+
+    pub fn meters() -> Haversine<T> {
+        Haversine::new(T::from(6_371_008.8).expect("T::from(f64)"))
+    }
+
+    pub fn kilometers() -> Haversine<T> {
+        Haversine::new(T::from(6_371.008_8).expect("T::from(f64)"))
+    }
+}
+
+impl<T: Float + One + Zero> Metric<T> for Haversine<T> {
+    fn distance(&self, a: &[T], b: &[T]) -> T {
+        let two = T::from(2.0).expect("T::from(f64)");
+        let (lat_a, lon_a, lat_b, lon_b) = (a[0].to_radians(), a[1].to_radians(), b[0].to_radians(), b[1].to_radians());
+        let h = ((lat_b - lat_a) / two).sin().powi(2) + lat_a.cos() * lat_b.cos() * ((lon_b - lon_a) / two).sin().powi(2);
+        two * self.radius * h.min(T::one()).sqrt().asin()
+    }
+}
+
+pub(crate) fn invert<T: Float + One + Zero>(matrix: &Array2<T>) -> Option<Array2<T>> {
+    let n = matrix.rows();
+    let mut left = matrix.to_owned();
+    let mut right = Array2::from_shape_fn((n, n), |(i, j)| if i == j { T::one() } else { T::zero() });
+    for col in 0..n {
+        let pivot = (col..n).max_by(|a, b| left[[*a, col]].abs().partial_cmp(&left[[*b, col]].abs()).expect("PartialOrd pivot"))?;
+        if left[[pivot, col]].abs() <= T::epsilon() {
+            return None;
+        }
+        for k in 0..n {
+            left.swap([col, k], [pivot, k]);
+            right.swap([col, k], [pivot, k]);
+        }
+        let scale = left[[col, col]];
+        left.row_mut(col).mapv_inplace(|x| x / scale);
+        right.row_mut(col).mapv_inplace(|x| x / scale);
+        for row in 0..n {
+            if row != col {
+                let factor = left[[row, col]];
+                for k in 0..n {
+                    left[[row, k]] = left[[row, k]] - factor * left[[col, k]];
+                    right[[row, k]] = right[[row, k]] - factor * right[[col, k]];
+                }
+            }
+        }
+    }
+    Some(right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distances() {
+        let (a, b) = ([0.0, 0.0], [3.0, -4.0]);
+        assert_eq!(Euclidean.distance(&a, &b), 5.0);
+        assert_eq!(SquaredEuclidean.distance(&a, &b), 25.0);
+        assert_eq!(Manhattan.distance(&a, &b), 7.0);
+        assert_eq!(Cosine.distance(&a, &b), 1.0);
+        assert!(Cosine.distance(&[1.0, 1.0], &[3.0, 3.0]).abs() < 1e-12);
+        assert!((Cosine.distance(&[1.0, 0.0], &[0.0, 2.0]) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_minkowski() {
+        let (a, b) = ([0.0, 0.0], [3.0, -4.0]);
+        assert_eq!(Minkowski::new(1.0).distance(&a, &b), 7.0);
+        assert_eq!(Minkowski::new(2.0).distance(&a, &b), 5.0);
+        assert_eq!(Minkowski::new(f64::INFINITY).distance(&a, &b), 4.0);
+        assert!((Minkowski::new(0.5).distance(&a, &b) - (3f64.sqrt() + 2.0).powi(2)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hamming() {
+        assert_eq!(Hamming.distance(&[1.0, 0.0, 1.0], &[1.0, 1.0, 0.0]), 2.0);
+        assert_eq!(Hamming::packed(&[0b1011, u64::MAX], &[0b0001, 0]), 66);
+    }
+
+    #[test]
+    fn test_jaccard() {
+        assert_eq!(Jaccard.distance(&[1.0, 1.0, 0.0, 1.0], &[1.0, 0.0, 1.0, 1.0]), 0.5);
+        assert_eq!(Jaccard.distance(&[0.0, 0.0], &[0.0, 0.0]), 0.0);
+        assert_eq!(Jaccard::sorted(&[0, 1, 3], &[0, 2, 3]), 0.5);
+        assert_eq!(Jaccard::sorted(&[], &[4]), 1.0);
+    }
+
+    #[test]
+    fn test_dtw() {
+        let (a, b) = ([0.0, 1.0, 2.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 2.0, 1.0, 0.0]);
+        assert_eq!(Dtw::new(None).distance(&a, &b), 0.0);
+        assert_eq!(Dtw::new(Some(1)).distance(&a, &b), 0.0);
+        assert_eq!(Dtw::new(Some(0)).distance(&a, &b), Manhattan.distance(&a, &b));
+        assert_eq!(Dtw::new(None).distance(&[1.0, 2.0, 3.0], &[1.0, 3.0]), 1.0);
+    }
+
+    #[test]
+    fn test_anisotropic() {
+        let metric = Anisotropic::new(&[1.0, 100.0]);
+        assert_eq!(metric.distance(&[0.0, 0.0], &[0.0, 100.0]), 1.0);
+        assert_eq!(metric.distance(&[0.0, 0.0], &[3.0, 400.0]), 5.0);
+    }
+
+    #[test]
+    fn test_periodic() {
+        let metric = Periodic::new(&[Some(360.0), None]);
+        assert_eq!(metric.distance(&[359.0, 0.0], &[1.0, 0.0]), 2.0);
+        assert_eq!(metric.distance(&[10.0, 0.0], &[730.0, 0.0]), 0.0);
+        assert_eq!(metric.distance(&[359.0, 0.0], &[2.0, 4.0]), 5.0);
+    }
+
+    #[test]
+    fn test_haversine() {
+        let (paris, london) = ([48.8566, 2.3522], [51.5074, -0.1278]);
+        assert!((Haversine::kilometers().distance(&paris, &london) - 343.5).abs() < 1.0);
+        assert!((Haversine::meters().distance(&paris, &london) - 343_500.0).abs() < 1_000.0);
+        assert_eq!(Haversine::meters().distance(&paris, &paris), 0.0);
+    }
+
+    #[test]
+    fn test_gower() {
+        let data = arr2(&[[20.0, 0.0, 1.0], [40.0, 1.0, 1.0], [60.0, 0.0, 2.0]]);
+        let metric = Gower::from_data(&data, vec![Column::Numeric, Column::Categorical, Column::Categorical]);
+        assert_eq!(metric.ranges, vec![40.0, 1.0, 1.0]);
+        assert_eq!(metric.distance(&[20.0, 0.0, 1.0], &[60.0, 0.0, 2.0]), 2.0 / 3.0);
+        assert_eq!(metric.distance(&[20.0, 0.0, 1.0], &[40.0, 1.0, 1.0]), 0.5);
+    }
+
+    #[test]
+    fn test_mahalanobis() {
+        let metric = Mahalanobis::new(arr2(&[[0.25, 0.0], [0.0, 1.0]]));
+        assert_eq!(metric.distance(&[0.0, 0.0], &[2.0, 0.0]), 1.0);
+        assert_eq!(metric.distance(&[0.0, 0.0], &[0.0, 2.0]), 2.0);
+
+        let data = arr2(&[[0.0, 0.0], [1.0, 1.1], [2.0, 1.9], [3.0, 3.2], [4.0, 3.9]]);
+        let fitted = Mahalanobis::from_data(&data);
+        assert!(fitted.distance(&[0.0, 0.0], &[1.0, 1.0]) < fitted.distance(&[0.0, 0.0], &[1.0, -1.0]));
+    }
+}
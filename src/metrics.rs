@@ -0,0 +1,478 @@
+use crate::dbscan::kdtree_init;
+use crate::metric::{Euclidean, Metric};
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::seq::index::sample;
+use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub trait Label {
+    fn cluster(&self) -> Option<usize>;
+}
+
+impl Label for usize {
+    fn cluster(&self) -> Option<usize> {
+        Some(*self)
+    }
+}
+
+impl Label for Option<usize> {
+    fn cluster(&self) -> Option<usize> {
+        *self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Noise {
+    OwnClass,
+    Exclude,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contingency {
+    pub rows: Vec<Option<usize>>,
+    pub cols: Vec<Option<usize>>,
+    pub counts: Array2<usize>,
+}
+
+pub fn contingency_matrix<A: Label, B: Label>(labels_a: &[A], labels_b: &[B], noise: Noise) -> Contingency {
+    assert_eq!(labels_a.len(), labels_b.len(), "labelings must have the same length");
+    let pairs = labels_a
+        .iter()
+        .zip(labels_b.iter())
+        .map(|(a, b)| (a.cluster(), b.cluster()))
+        .filter(|(a, b)| noise == Noise::OwnClass || (a.is_some() && b.is_some()))
+        .collect::<Vec<(Option<usize>, Option<usize>)>>();
+    let rows = pairs.iter().map(|(a, _)| *a).collect::<BTreeSet<Option<usize>>>().into_iter().collect::<Vec<Option<usize>>>();
+    let cols = pairs.iter().map(|(_, b)| *b).collect::<BTreeSet<Option<usize>>>().into_iter().collect::<Vec<Option<usize>>>();
+    let mut counts = Array2::zeros((rows.len(), cols.len()));
+    for (a, b) in pairs.iter() {
+        let i = rows.binary_search(a).expect("row label");
+        let j = cols.binary_search(b).expect("column label");
+        counts[[i, j]] += 1;
+    }
+    Contingency { rows, cols, counts }
+}
+
+pub fn pair_confusion_matrix<A: Label, B: Label>(labels_a: &[A], labels_b: &[B], noise: Noise) -> [[usize; 2]; 2] {
+    let (both, rows, cols, total) = pair_counts(&contingency_matrix(labels_a, labels_b, noise).counts);
+    [[total + both - rows - cols, cols - both], [rows - both, both]]
+}
+
+fn pair_counts(counts: &Array2<usize>) -> (usize, usize, usize, usize) {
+    let choose2 = |n: usize| n * n.saturating_sub(1) / 2;
+    let both = counts.iter().map(|nij| choose2(*nij)).sum::<usize>();
+    let rows = counts.outer_iter().map(|row| choose2(row.sum())).sum::<usize>();
+    let cols = counts.gencolumns().into_iter().map(|col| choose2(col.sum())).sum::<usize>();
+    (both, rows, cols, choose2(counts.sum()))
+}
+
+pub fn adjusted_rand_index<A: Label, B: Label>(labels_a: &[A], labels_b: &[B], noise: Noise) -> f64 {
+    let (both, rows, cols, total) = pair_counts(&contingency_matrix(labels_a, labels_b, noise).counts);
+    let (both, rows, cols, total) = (both as f64, rows as f64, cols as f64, total as f64);
+    if total == 0.0 {
+        return 1.0;
+    }
+    let expected = rows * cols / total;
+    let maximum = (rows + cols) / 2.0;
+    if maximum == expected {
+        1.0
+    } else {
+        (both - expected) / (maximum - expected)
+    }
+}
+
+pub fn fowlkes_mallows_score<A: Label, B: Label>(labels_a: &[A], labels_b: &[B], noise: Noise) -> f64 {
+    let (both, rows, cols, _) = pair_counts(&contingency_matrix(labels_a, labels_b, noise).counts);
+    if rows == 0 || cols == 0 {
+        0.0
+    } else {
+        both as f64 / (rows as f64 * cols as f64).sqrt()
+    }
+}
+
+pub fn homogeneity_completeness_v_measure<A: Label, B: Label>(labels_true: &[A], labels_pred: &[B], noise: Noise) -> (f64, f64, f64) {
+    let counts = contingency_matrix(labels_true, labels_pred, noise).counts;
+    if counts.is_empty() {
+        return (1.0, 1.0, 1.0);
+    }
+    let (class_entropy, cluster_entropy, class_given_cluster, cluster_given_class) = entropies(&counts);
+    let homogeneity = if class_entropy > 0.0 { 1.0 - class_given_cluster / class_entropy } else { 1.0 };
+    let completeness = if cluster_entropy > 0.0 { 1.0 - cluster_given_class / cluster_entropy } else { 1.0 };
+    let v_measure = if homogeneity + completeness > 0.0 {
+        2.0 * homogeneity * completeness / (homogeneity + completeness)
+    } else {
+        0.0
+    };
+    (homogeneity, completeness, v_measure)
+}
+
+pub fn variation_of_information<A: Label, B: Label>(labels_a: &[A], labels_b: &[B], noise: Noise) -> f64 {
+    let (_, _, a_given_b, b_given_a) = entropies(&contingency_matrix(labels_a, labels_b, noise).counts);
+    a_given_b + b_given_a
+}
+
+pub fn align_labels<A: Label, B: Label>(reference: &[A], other: &[B]) -> Vec<Option<usize>> {
+    let table = contingency_matrix(reference, other, Noise::Exclude);
+    let size = table.rows.len().max(table.cols.len());
+    let most = table.counts.iter().cloned().max().unwrap_or(0);
+    let cost = Array2::from_shape_fn((size, size), |(i, j)| if i < table.rows.len() && j < table.cols.len() { most - table.counts[[i, j]] } else { most });
+
+    let mut next = table.rows.iter().flatten().max().map_or(0, |max| max + 1);
+    let assignment = hungarian(&cost);
+    let mut mapping = BTreeMap::new();
+    for (i, j) in assignment.into_iter().enumerate().filter(|(_, j)| *j < table.cols.len()) {
+        let target = if i < table.rows.len() {
+            table.rows[i].expect("noise excluded")
+        } else {
+            next += 1;
+            next - 1
+        };
+        mapping.insert(table.cols[j].expect("noise excluded"), target);
+    }
+    other.iter().map(|label| label.cluster().and_then(|c| mapping.get(&c).cloned())).collect::<Vec<Option<usize>>>()
+}
+
+fn hungarian(cost: &Array2<usize>) -> Vec<usize> {
+    let n = cost.rows();
+    let cost = |i: usize, j: usize| cost[[i - 1, j - 1]] as i64;
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; n + 1];
+    let mut owner = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+    for i in 1..=n {
+        owner[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = owner[j0];
+            let mut delta = i64::MAX;
+            let mut j1 = 0;
+            for j in 1..=n {
+                if !used[j] {
+                    let reduced = cost(i0, j) - u[i0] - v[j];
+                    if reduced < minv[j] {
+                        minv[j] = reduced;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[owner[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if owner[j0] == 0 {
+                break;
+            }
+        }
+        while j0 != 0 {
+            let j1 = way[j0];
+            owner[j0] = owner[j1];
+            j0 = j1;
+        }
+    }
+    let mut assignment = vec![0; n];
+    for j in 1..=n {
+        if owner[j] > 0 {
+            assignment[owner[j] - 1] = j - 1;
+        }
+    }
+    assignment
+}
+
+fn entropies(counts: &Array2<usize>) -> (f64, f64, f64, f64) {
+    let n = counts.sum() as f64;
+    if n == 0.0 {
+        return (0.0, 0.0, 0.0, 0.0);
+    }
+    let row_sizes = counts.sum_axis(Axis(1));
+    let col_sizes = counts.sum_axis(Axis(0));
+    let entropy = |sizes: &Array1<usize>| -sizes.iter().filter(|size| **size > 0).map(|size| *size as f64 / n * (*size as f64 / n).ln()).sum::<f64>();
+    let (mut row_given_col, mut col_given_row) = (0.0, 0.0);
+    for ((i, j), nij) in counts.indexed_iter().filter(|(_, nij)| **nij > 0) {
+        let joint = *nij as f64 / n;
+        row_given_col -= joint * (*nij as f64 / col_sizes[j] as f64).ln();
+        col_given_row -= joint * (*nij as f64 / row_sizes[i] as f64).ln();
+    }
+    (entropy(&row_sizes), entropy(&col_sizes), row_given_col, col_given_row)
+}
+
+pub fn silhouette_samples<T, L, M>(data: &Array2<T>, labels: &[L], metric: &M) -> Vec<Option<T>>
+where
+    T: Float + One + Zero,
+    L: Label,
+    M: Metric<T>,
+{
+    assert_eq!(data.rows(), labels.len(), "one label per row");
+    if !data.is_standard_layout() {
+        return silhouette_samples(&standard_layout(data.view()), labels, metric);
+    }
+    let mut sizes = BTreeMap::new();
+    for cluster in labels.iter().filter_map(Label::cluster) {
+        *sizes.entry(cluster).or_insert(0) += 1;
+    }
+
+    labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let own = label.cluster()?;
+            if sizes[&own] < 2 || sizes.len() < 2 {
+                return Some(T::zero());
+            }
+            let mut totals = BTreeMap::new();
+            for (j, other) in labels.iter().enumerate() {
+                if let Some(cluster) = other.cluster() {
+                    if i != j {
+                        let total = totals.entry(cluster).or_insert_with(T::zero);
+                        *total = *total + metric.distance(data.row(i).as_slice().unwrap(), data.row(j).as_slice().unwrap());
+                    }
+                }
+            }
+            let a = totals[&own] / T::from(sizes[&own] - 1).expect("T::from(usize)");
+            let b = totals
+                .iter()
+                .filter(|(cluster, _)| **cluster != own)
+                .map(|(cluster, total)| *total / T::from(sizes[cluster]).expect("T::from(usize)"))
+                .fold(T::infinity(), T::min);
+            let scale = a.max(b);
+            Some(if scale > T::zero() { (b - a) / scale } else { T::zero() })
+        })
+        .collect::<Vec<Option<T>>>()
+}
+
+pub fn silhouette_score<T, L, M>(data: &Array2<T>, labels: &[L], metric: &M) -> T
+where
+    T: Float + One + Zero,
+    L: Label,
+    M: Metric<T>,
+{
+    mean(silhouette_samples(data, labels, metric).into_iter().flatten())
+}
+
+pub fn silhouette_per_cluster<T, L, M>(data: &Array2<T>, labels: &[L], metric: &M) -> BTreeMap<usize, T>
+where
+    T: Float + One + Zero,
+    L: Label,
+    M: Metric<T>,
+{
+    let samples = silhouette_samples(data, labels, metric);
+    let mut grouped: BTreeMap<usize, Vec<T>> = BTreeMap::new();
+    for (label, sample) in labels.iter().zip(samples) {
+        if let (Some(cluster), Some(value)) = (label.cluster(), sample) {
+            grouped.entry(cluster).or_default().push(value);
+        }
+    }
+    grouped.into_iter().map(|(cluster, values)| (cluster, mean(values.into_iter()))).collect::<BTreeMap<usize, T>>()
+}
+
+pub fn davies_bouldin_score<T: Float + One + Zero, L: Label>(data: &Array2<T>, labels: &[L]) -> T {
+    assert_eq!(data.rows(), labels.len(), "one label per row");
+    if !data.is_standard_layout() {
+        return davies_bouldin_score(&standard_layout(data.view()), labels);
+    }
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (row_idx, label) in labels.iter().enumerate() {
+        if let Some(cluster) = label.cluster() {
+            members.entry(cluster).or_default().push(row_idx);
+        }
+    }
+    if members.len() < 2 {
+        return T::zero();
+    }
+
+    let centroids = members
+        .values()
+        .map(|rows| {
+            let n = T::from(rows.len()).expect("T::from(usize)");
+            rows.iter().fold(Array1::<T>::zeros(data.cols()), |acc, row_idx| acc + data.row(*row_idx)).mapv(|x| x / n)
+        })
+        .collect::<Vec<Array1<T>>>();
+    let scatters = members
+        .values()
+        .zip(centroids.iter())
+        .map(|(rows, centroid)| mean(rows.iter().map(|row_idx| Euclidean.distance(data.row(*row_idx).as_slice().unwrap(), centroid.as_slice().unwrap()))))
+        .collect::<Vec<T>>();
+
+    mean((0..centroids.len()).map(|i| {
+        (0..centroids.len())
+            .filter(|j| *j != i)
+            .map(|j| {
+                let separation = Euclidean.distance(centroids[i].as_slice().unwrap(), centroids[j].as_slice().unwrap());
+                if separation > T::zero() {
+                    (scatters[i] + scatters[j]) / separation
+                } else {
+                    T::infinity()
+                }
+            })
+            .fold(T::zero(), T::max)
+    }))
+}
+
+pub fn hopkins<T: Float + One + Zero, R: Rng>(data: &Array2<T>, n_samples: usize, rng: &mut R) -> T {
+    assert!(n_samples > 0 && n_samples < data.rows(), "n_samples must be between 1 and the number of rows - 1");
+    if !data.is_standard_layout() {
+        return hopkins(&standard_layout(data.view()), n_samples, rng);
+    }
+    let kdt = kdtree_init(data);
+    let nearest = |point: &[T], k: usize| {
+        kdt.nearest(point, k, &squared_euclidean)
+            .expect("KdTree error checking point")
+            .last()
+            .map_or(T::zero(), |(distance, _)| distance.sqrt())
+    };
+    let lows = data.gencolumns().into_iter().map(|col| col.fold(T::infinity(), |acc, x| acc.min(*x))).collect::<Vec<T>>();
+    let highs = data.gencolumns().into_iter().map(|col| col.fold(T::neg_infinity(), |acc, x| acc.max(*x))).collect::<Vec<T>>();
+
+    let uniform = (0..n_samples).fold(T::zero(), |acc, _| {
+        let point = lows
+            .iter()
+            .zip(highs.iter())
+            .map(|(low, high)| *low + (*high - *low) * T::from(rng.gen::<f64>()).expect("T::from(f64)"))
+            .collect::<Vec<T>>();
+        acc + nearest(&point, 1)
+    });
+    let observed = sample(rng, data.rows(), n_samples)
+        .iter()
+        .fold(T::zero(), |acc, row_idx| acc + nearest(data.row(row_idx).as_slice().unwrap(), 2));
+    if uniform + observed > T::zero() {
+        uniform / (uniform + observed)
+    } else {
+        T::zero()
+    }
+}
+
+fn mean<T: Float + One + Zero, I: Iterator<Item = T>>(values: I) -> T {
+    let (total, count) = values.fold((T::zero(), 0), |(total, count), x| (total + x, count + 1));
+    if count == 0 {
+        T::zero()
+    } else {
+        total / T::from(count).expect("T::from(usize)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbscan::Dbscan;
+    use rand::SeedableRng;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((7, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, 10.0, -10.0]).unwrap()
+    }
+
+    #[test]
+    fn test_silhouette() {
+        let good = silhouette_score(&data(), &[0, 0, 0, 1, 1, 1, 1], &Euclidean);
+        let bad = silhouette_score(&data(), &[0, 1, 0, 1, 0, 1, 0], &Euclidean);
+        assert!(good > bad);
+
+        let model = Dbscan::new(&data(), 0.5, 2, false, None).unwrap();
+        let labels = model.labels();
+        assert_eq!(labels[6], None);
+        assert!(silhouette_score(&data(), &labels, &Euclidean) > 0.9);
+        let per_cluster = silhouette_per_cluster(&data(), &labels, &Euclidean);
+        assert_eq!(per_cluster.len(), 2);
+        assert!(per_cluster.values().all(|s| *s > 0.9));
+
+        let fortran = Array2::from_shape_vec((7, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(silhouette_samples(&fortran, &labels, &Euclidean), silhouette_samples(&data(), &labels, &Euclidean));
+        assert_eq!(davies_bouldin_score(&fortran, &labels), davies_bouldin_score(&data(), &labels));
+        let hopkins_of = |data: &Array2<f64>| hopkins(data, 3, &mut rand::rngs::SmallRng::seed_from_u64(7));
+        assert_eq!(hopkins_of(&fortran), hopkins_of(&data()));
+    }
+
+    #[test]
+    fn test_contingency() {
+        let table = contingency_matrix(&[0, 0, 1, 1, 1], &[Some(2), None, Some(2), Some(0), Some(0)], Noise::OwnClass);
+        assert_eq!(table.rows, vec![Some(0), Some(1)]);
+        assert_eq!(table.cols, vec![None, Some(0), Some(2)]);
+        assert_eq!(table.counts, arr2(&[[1, 0, 1], [0, 2, 1]]));
+        assert_eq!(contingency_matrix(&[0, 0, 1, 1, 1], &[Some(2), None, Some(2), Some(0), Some(0)], Noise::Exclude).counts.sum(), 4);
+
+        let pairs = pair_confusion_matrix(&[0, 0, 1, 1], &[0, 0, 0, 1], Noise::OwnClass);
+        assert_eq!(pairs, [[2, 2], [1, 1]]);
+        assert_eq!(pairs.iter().flatten().sum::<usize>(), 6);
+    }
+
+    #[test]
+    fn test_align_labels() {
+        let reference = [0, 0, 0, 1, 1, 2, 2];
+        let other = [Some(5), Some(5), Some(3), Some(9), Some(9), Some(3), None];
+        assert_eq!(align_labels(&reference, &other), vec![Some(0), Some(0), Some(2), Some(1), Some(1), Some(2), None]);
+        assert_eq!(align_labels(&[0, 0, 1, 1], &[1, 1, 0, 2]), vec![Some(0), Some(0), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn test_adjusted_rand_index() {
+        assert_eq!(adjusted_rand_index(&[0, 0, 1, 1], &[1, 1, 0, 0], Noise::OwnClass), 1.0);
+        assert!((adjusted_rand_index(&[0, 0, 1, 1, 2, 2], &[0, 0, 1, 2, 1, 2], Noise::OwnClass) - 1.0 / 6.0).abs() < 1e-12);
+        assert!(adjusted_rand_index(&[0, 0, 0, 1, 1, 1], &[0, 1, 2, 0, 1, 2], Noise::OwnClass) < 0.0);
+
+        let truth = [0, 0, 0, 1, 1, 1];
+        let noisy = [Some(0), Some(0), None, Some(1), Some(1), None];
+        assert_eq!(adjusted_rand_index(&truth, &noisy, Noise::Exclude), 1.0);
+        assert!(adjusted_rand_index(&truth, &noisy, Noise::OwnClass) < 1.0);
+    }
+
+    #[test]
+    fn test_fowlkes_mallows() {
+        assert_eq!(fowlkes_mallows_score(&[0, 0, 1, 1], &[1, 1, 0, 0], Noise::OwnClass), 1.0);
+        assert_eq!(fowlkes_mallows_score(&[0, 0, 0, 0], &[0, 1, 2, 3], Noise::OwnClass), 0.0);
+        assert!((fowlkes_mallows_score(&[0, 0, 0, 1, 1, 1], &[0, 0, 1, 1, 2, 2], Noise::OwnClass) - 2.0 / 18f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_v_measure() {
+        assert_eq!(homogeneity_completeness_v_measure(&[0, 0, 1, 1], &[1, 1, 0, 0], Noise::OwnClass), (1.0, 1.0, 1.0));
+        let (h, c, v) = homogeneity_completeness_v_measure(&[0, 0, 1, 1], &[0, 1, 2, 3], Noise::OwnClass);
+        assert_eq!(h, 1.0);
+        assert!((c - 0.5).abs() < 1e-12);
+        assert!((v - 2.0 / 3.0).abs() < 1e-12);
+        let (h, c, _) = homogeneity_completeness_v_measure(&[0, 0, 1, 1], &[0, 0, 0, 0], Noise::OwnClass);
+        assert_eq!((h, c), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_hopkins() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(7);
+        let clustered = Array2::from_shape_fn((60, 2), |(row, col)| (row / 20) as f64 * 10.0 + ((row * 7 + col * 3) % 10) as f64 * 0.01);
+        let uniform = Array2::from_shape_fn((60, 2), |_| rng.gen::<f64>() * 10.0);
+        assert!(hopkins(&clustered, 20, &mut rng) > 0.9);
+        assert!((hopkins(&uniform, 20, &mut rng) - 0.5).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_variation_of_information() {
+        assert_eq!(variation_of_information(&[0, 0, 1, 1], &[1, 1, 0, 0], Noise::OwnClass), 0.0);
+        assert!((variation_of_information(&[0, 0, 0, 0], &[0, 0, 1, 1], Noise::OwnClass) - 2f64.ln()).abs() < 1e-12);
+        let (a, b, c) = ([0, 0, 1, 1, 2, 2], [0, 1, 1, 2, 2, 0], [0, 0, 0, 1, 1, 1]);
+        let vi = |x: &[usize], y: &[usize]| variation_of_information(x, y, Noise::OwnClass);
+        assert!(vi(&a, &c) <= vi(&a, &b) + vi(&b, &c) + 1e-12);
+        assert_eq!(vi(&a, &b), vi(&b, &a));
+    }
+
+    #[test]
+    fn test_davies_bouldin() {
+        let good = davies_bouldin_score(&data(), &[0, 0, 0, 1, 1, 1, 2]);
+        let bad = davies_bouldin_score(&data(), &[0, 1, 0, 1, 0, 1, 2]);
+        assert!(good < bad);
+        assert!(good < 0.1);
+        assert_eq!(davies_bouldin_score(&data(), &[Some(0), Some(0), None, None, None, None, None]), 0.0);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::error::Error;
+
+pub trait Fit<X> {
+    type Model;
+    fn fit(&self, x: &X) -> Result<Self::Model, Error>;
+}
+
+/// Every model labels rows the same way: `Some(cluster)` with clusters numbered from 0, or `None` for rows the model
+/// leaves unassigned (noise in DBSCAN). Models with their own numbering, such as `Dbscan::clusters`, convert to this.
+pub trait Predict<X> {
+    fn predict(&self, x: &X) -> Vec<Option<usize>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbscan::DbscanBuilder;
+    use crate::kmeans::KmeansBuilder;
+    use ndarray::prelude::*;
+
+    fn fit_predict<X, F: Fit<X>>(estimator: &F, x: &X) -> Result<Vec<Option<usize>>, Error>
+    where
+        F::Model: Predict<X>,
+    {
+        estimator.fit(x).map(|model| model.predict(x))
+    }
+
+    #[test]
+    fn test_fit_predict() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, 8.0, 8.0, 8.2, 8.1, 7.9, 8.1, 8.1, 7.8]).unwrap();
+        let kmeans = fit_predict(&KmeansBuilder::new().k(2).seed(3), &data).unwrap();
+        let dbscan = fit_predict(&DbscanBuilder::new().eps(0.5).min_points(3), &data).unwrap();
+        for labels in [kmeans, dbscan].iter() {
+            assert!(labels[..4].iter().all(|l| *l == labels[0]));
+            assert!(labels[4..].iter().all(|l| *l == labels[4]));
+            assert!(labels[0] != labels[4]);
+            let mut ids = vec![labels[0], labels[4]];
+            ids.sort();
+            assert_eq!(ids, vec![Some(0), Some(1)]);
+        }
+        let model = DbscanBuilder::new().eps(0.5).min_points(3).fit(&data).unwrap();
+        assert_eq!(Predict::predict(&model, &arr2(&[[20.0, 20.0]])), vec![None]);
+
+        assert_eq!(fit_predict(&KmeansBuilder::new().k(9), &data), Err(Error::InvalidParameter("k = 9 must be between 1 and the number of rows (8)".to_string())));
+        assert_eq!(fit_predict(&DbscanBuilder::new().eps(0.0), &data), Err(Error::InvalidParameter("eps must be positive".to_string())));
+        assert_eq!(fit_predict(&DbscanBuilder::new(), &Array2::<f64>::zeros((0, 2))), Err(Error::EmptyInput));
+        assert_eq!(DbscanBuilder::new().fit(&arr2(&[[0.0, f64::NAN]])).unwrap_err(), Error::NonFiniteValue { rows: vec![0] });
+    }
+}
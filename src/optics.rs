@@ -0,0 +1,146 @@
+use crate::dbscan::{kdtree_init, region_query_with_distances};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug)]
+pub struct Optics<T: Float + One + Zero> {
+    pub eps: T,
+    pub min_points: usize,
+    pub ordering: Vec<usize>,
+    pub reachability: Vec<Option<T>>,
+    pub core_distances: Vec<Option<T>>,
+}
+
+impl<T: Float + One + Zero> Optics<T> {
+    pub fn new(data: &Array2<T>, eps: T, min_points: usize) -> Optics<T> {
+        assert!(min_points > 0, "min_points must be at least 1");
+        if !data.is_standard_layout() {
+            return Optics::new(&standard_layout(data.view()), eps, min_points);
+        }
+        let mut neighbours = Vec::with_capacity(data.rows());
+        let mut seeds = Vec::with_capacity(data.rows());
+        let mut processed = vec![false; data.rows()];
+        let mut ordering = Vec::with_capacity(data.rows());
+        let mut reachability = vec![None; data.rows()];
+        let mut core_distances = vec![None; data.rows()];
+        let kdt = kdtree_init(data);
+
+        for row_idx in 0..data.rows() {
+            if processed[row_idx] {
+                continue;
+            }
+            seeds.push(row_idx);
+            while let Some(seed_pos) = next_seed(&seeds, &reachability) {
+                let point_idx = seeds.swap_remove(seed_pos);
+                processed[point_idx] = true;
+                ordering.push(point_idx);
+
+                neighbours.clear();
+                region_query_with_distances(data.row(point_idx).as_slice().unwrap(), eps, &kdt, &mut neighbours);
+                if neighbours.len() < min_points {
+                    continue;
+                }
+                let core_distance = neighbours[min_points - 1].0;
+                core_distances[point_idx] = Some(core_distance);
+
+                for (distance, neighbour_idx) in neighbours.iter().cloned() {
+                    if processed[neighbour_idx] {
+                        continue;
+                    }
+                    let new_reachability = core_distance.max(distance);
+                    match reachability[neighbour_idx] {
+                        None => {
+                            reachability[neighbour_idx] = Some(new_reachability);
+                            seeds.push(neighbour_idx);
+                        }
+                        Some(r) if new_reachability < r => reachability[neighbour_idx] = Some(new_reachability),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Optics {
+            eps,
+            min_points,
+            ordering,
+            reachability,
+            core_distances,
+        }
+    }
+
+    pub fn extract_dbscan(&self, eps: T) -> Vec<usize> {
+        let mut c = 0;
+        let mut clusters = vec![0; self.ordering.len()];
+        for &row_idx in self.ordering.iter() {
+            match self.reachability[row_idx] {
+                Some(r) if r <= eps => clusters[row_idx] = c,
+                _ => match self.core_distances[row_idx] {
+                    Some(d) if d <= eps => {
+                        c += 1;
+                        clusters[row_idx] = c;
+                    }
+                    _ => clusters[row_idx] = 0,
+                },
+            }
+        }
+        clusters
+    }
+}
+
+fn next_seed<T: Float + One + Zero>(seeds: &[usize], reachability: &[Option<T>]) -> Option<usize> {
+    seeds
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            let a = reachability[**a].unwrap_or_else(T::infinity);
+            let b = reachability[**b].unwrap_or_else(T::infinity);
+            a.partial_cmp(&b).expect("PartialOrd reachability distance")
+        })
+        .map(|(pos, _)| pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_ordering() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -2.0, -1.0]).unwrap();
+        let model = Optics::new(&data, 10.0, 2);
+        assert!(model.ordering.iter().cloned().sorted().eq(0..8));
+        assert!(model.reachability[model.ordering[0]].is_none());
+        assert!(model.core_distances.iter().all(|d| d.is_some()));
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = Optics::new(&fortran, 10.0, 2);
+        assert_eq!(model.ordering, transposed.ordering);
+        assert_eq!(model.reachability, transposed.reachability);
+    }
+
+    #[test]
+    fn test_extract_dbscan() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -1.0, -2.0, -2.0, -1.0]).unwrap();
+        let model = Optics::new(&data, 10.0, 2);
+
+        let clustering = dbg!(model.extract_dbscan(0.5));
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).take(2).all_equal());
+        assert!(clustering[0] != clustering[4]);
+        assert!(clustering[0] > 0 && clustering[4] > 0);
+        assert!(clustering.iter().skip(6).all(|c| *c == 0));
+
+        let coarse = model.extract_dbscan(5.0);
+        assert!(coarse.iter().all_equal());
+    }
+
+    #[test]
+    #[should_panic(expected = "min_points must be at least 1")]
+    fn test_zero_min_points() {
+        Optics::new(&arr2(&[[0.0, 0.0], [1.0, 1.0]]), 1.0, 0);
+    }
+}
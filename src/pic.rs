@@ -0,0 +1,96 @@
+use crate::kmeans::Kmeans;
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct Pic<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub sigma: T,
+    pub embedding: Array1<T>,
+    pub model: Kmeans<T>,
+    pub clusters: Vec<usize>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Pic<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, sigma: T, power_iterations: usize, iterations: usize, nseeds: usize) -> Pic<T> {
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), nclust, sigma, power_iterations, iterations, nseeds);
+        }
+        let n = data.rows();
+        let two = T::from(2.0).expect("T::from(f64)");
+        let mut affinity = Array2::zeros((n, n));
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let distance = squared_euclidean(data.row(i).as_slice().unwrap(), data.row(j).as_slice().unwrap());
+                let weight = (-distance / (two * sigma * sigma)).exp();
+                affinity[[i, j]] = weight;
+                affinity[[j, i]] = weight;
+            }
+        }
+        let degrees = affinity.sum_axis(Axis(1));
+        for (mut row, degree) in affinity.outer_iter_mut().zip(degrees.iter()) {
+            if *degree > T::zero() {
+                row.mapv_inplace(|x| x / *degree);
+            }
+        }
+
+        let total = degrees.sum();
+        let mut embedding = if total > T::zero() {
+            degrees / total
+        } else {
+            Array1::from_elem(n, T::one() / T::from(n).expect("T::from(usize)"))
+        };
+        let tolerance = T::from(1e-5).expect("T::from(f64)") / T::from(n.max(1)).expect("T::from(usize)");
+        let mut delta = T::infinity();
+        for _ in 0..power_iterations {
+            let next = affinity.dot(&embedding);
+            let norm = next.mapv(|x| x.abs()).sum();
+            if norm <= T::zero() {
+                break;
+            }
+            let next = next / norm;
+            let next_delta = (&next - &embedding).mapv(|x| x.abs()).sum();
+            embedding = next;
+            if (next_delta - delta).abs() < tolerance {
+                break;
+            }
+            delta = next_delta;
+        }
+
+        let model = Kmeans::new(&embedding.clone().insert_axis(Axis(1)), nclust, iterations, nseeds).expect("k-means on the embedding with nclust at most the number of rows");
+        Pic {
+            sigma,
+            embedding,
+            clusters: model.clusters.clone(),
+            model,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_pic() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap();
+        let model = Pic::new(&data, 2, 0.5, 50, 20, 5);
+        let clustering = dbg!(model.clusters);
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = Pic::new(&fortran, 2, 0.5, 50, 20, 5).clusters;
+        assert!(transposed.iter().take(4).all_equal());
+        assert!(transposed.iter().skip(4).all_equal());
+        assert!(transposed[0] != transposed[4]);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::kmeans::{Kmeans, KmeansBuilder};
+use crate::metric::{Metric, SquaredEuclidean};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct ProductQuantizer<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub subspaces: Vec<(usize, usize)>,
+    pub codebooks: Vec<Vec<Array1<T>>>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> ProductQuantizer<T> {
+    pub fn new(data: &Array2<T>, nsubspaces: usize, ncodes: usize, iterations: usize, nseeds: usize) -> ProductQuantizer<T> {
+        let dims = data.cols();
+        assert!(nsubspaces > 0 && nsubspaces <= dims, "nsubspaces must be between 1 and the number of columns");
+        let subspaces = (0..nsubspaces).map(|m| (m * dims / nsubspaces, (m + 1) * dims / nsubspaces)).collect::<Vec<(usize, usize)>>();
+        let builder = KmeansBuilder::new().k(ncodes).max_iter(iterations).nseeds(nseeds);
+        let codebooks = subspaces
+            .iter()
+            .map(|(start, end)| {
+                let model: Kmeans<T> = builder.build(&data.select(Axis(1), &(*start..*end).collect::<Vec<usize>>())).expect("k-means on a subspace with ncodes at most the number of rows");
+                model.centers
+            })
+            .collect::<Vec<Vec<Array1<T>>>>();
+        ProductQuantizer { subspaces, codebooks }
+    }
+
+    pub fn encode(&self, data: &Array2<T>) -> Vec<Vec<usize>> {
+        if !data.is_standard_layout() {
+            return self.encode(&standard_layout(data.view()));
+        }
+        data.outer_iter()
+            .map(|row| {
+                let row = row.as_slice().unwrap();
+                self.subspaces
+                    .iter()
+                    .zip(self.codebooks.iter())
+                    .map(|((start, end), codebook)| Kmeans::predict_with_centers(codebook, &aview1(&row[*start..*end])))
+                    .collect::<Vec<usize>>()
+            })
+            .collect::<Vec<Vec<usize>>>()
+    }
+
+    pub fn decode(&self, codes: &[Vec<usize>]) -> Array2<T> {
+        let dims = self.subspaces.last().map_or(0, |(_, end)| *end);
+        let mut data = Array2::zeros((codes.len(), dims));
+        for (mut row, code) in data.outer_iter_mut().zip(codes.iter()) {
+            for (((start, _), codebook), c) in self.subspaces.iter().zip(self.codebooks.iter()).zip(code.iter()) {
+                for (j, x) in codebook[*c].iter().enumerate() {
+                    row[start + j] = *x;
+                }
+            }
+        }
+        data
+    }
+
+    pub fn distance_table(&self, query: &ArrayView1<T>) -> Vec<Vec<T>> {
+        let query = query.to_vec();
+        self.subspaces
+            .iter()
+            .zip(self.codebooks.iter())
+            .map(|((start, end), codebook)| codebook.iter().map(|center| SquaredEuclidean.distance(&query[*start..*end], center.as_slice().unwrap())).collect::<Vec<T>>())
+            .collect::<Vec<Vec<T>>>()
+    }
+
+    pub fn asymmetric_distances(&self, query: &ArrayView1<T>, codes: &[Vec<usize>]) -> Vec<T> {
+        let table = self.distance_table(query);
+        codes.iter().map(|code| code.iter().zip(table.iter()).map(|(c, distances)| distances[*c]).sum::<T>()).collect::<Vec<T>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_product_quantizer() {
+        let data = Array2::from_shape_fn((40, 4), |(row, col)| if col < 2 { (row % 2) as f64 * 10.0 } else { (row % 4 / 2) as f64 * -5.0 } + col as f64);
+        let pq = ProductQuantizer::new(&data, 2, 2, 20, 3);
+        assert_eq!(pq.subspaces, vec![(0, 2), (2, 4)]);
+        let codes = pq.encode(&data);
+        assert_eq!(codes.len(), 40);
+        assert!(codes.iter().all(|code| code.len() == 2));
+        assert_eq!(pq.decode(&codes), data);
+
+        let query = data.row(3);
+        let adc = pq.asymmetric_distances(&query, &codes);
+        for (i, row) in data.outer_iter().enumerate() {
+            assert!((adc[i] - SquaredEuclidean.distance(query.as_slice().unwrap(), row.as_slice().unwrap())).abs() < 1e-9);
+        }
+
+        let fortran = Array2::from_shape_vec((40, 4).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(pq.encode(&fortran), codes);
+        assert_eq!(pq.asymmetric_distances(&fortran.row(3), &codes), adc);
+    }
+}
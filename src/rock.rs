@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+#[derive(Debug)]
+pub struct Rock {
+    pub theta: f64,
+    pub clusters: Vec<usize>,
+}
+
+impl Rock {
+    pub fn new<T: Eq + Hash>(records: &[Vec<T>], nclust: usize, theta: f64) -> Rock {
+        let n = records.len();
+        let sets = records.iter().map(|record| record.iter().collect::<HashSet<&T>>()).collect::<Vec<HashSet<&T>>>();
+        let neighbours = (0..n)
+            .map(|i| (0..n).filter(|j| *j != i && jaccard(&sets[i], &sets[*j]) >= theta).collect::<Vec<usize>>())
+            .collect::<Vec<Vec<usize>>>();
+
+        let mut links = vec![vec![0.0; n]; n];
+        for row in neighbours.iter() {
+            for a in row.iter() {
+                for b in row.iter().filter(|b| *b != a) {
+                    links[*a][*b] += 1.0;
+                }
+            }
+        }
+
+        let exponent = 1.0 + 2.0 * (1.0 - theta) / (1.0 + theta);
+        let goodness = |links: f64, a: usize, b: usize| links / (((a + b) as f64).powf(exponent) - (a as f64).powf(exponent) - (b as f64).powf(exponent));
+        let mut members = (0..n).map(|i| Some(vec![i])).collect::<Vec<Option<Vec<usize>>>>();
+        let mut active = n;
+        while active > nclust {
+            let mut best: Option<(usize, usize, f64)> = None;
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    if let (Some(a), Some(b)) = (&members[i], &members[j]) {
+                        if links[i][j] > 0.0 {
+                            let score = goodness(links[i][j], a.len(), b.len());
+                            if best.map_or(true, |(_, _, s)| score > s) {
+                                best = Some((i, j, score));
+                            }
+                        }
+                    }
+                }
+            }
+            let (i, j) = match best {
+                Some((i, j, _)) => (i, j),
+                None => break,
+            };
+            let merged = members[j].take().expect("active cluster");
+            members[i].as_mut().expect("active cluster").extend(merged);
+            let absorbed = std::mem::replace(&mut links[j], vec![0.0; n]);
+            for (k, link) in absorbed.into_iter().enumerate() {
+                links[i][k] += link;
+                links[k][i] = links[i][k];
+                links[k][j] = 0.0;
+            }
+            links[i][i] = 0.0;
+            active -= 1;
+        }
+
+        let mut clusters = vec![0; n];
+        for (label, rows) in members.iter().flatten().enumerate() {
+            for row_idx in rows.iter() {
+                clusters[*row_idx] = label;
+            }
+        }
+        Rock { theta, clusters }
+    }
+}
+
+fn jaccard<T: Eq + Hash>(a: &HashSet<&T>, b: &HashSet<&T>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_rock() {
+        let baskets = vec![
+            vec!["bread", "milk", "eggs"],
+            vec!["bread", "milk", "butter"],
+            vec!["bread", "eggs", "butter"],
+            vec!["milk", "eggs", "butter"],
+            vec!["beer", "chips", "salsa"],
+            vec!["beer", "chips", "nuts"],
+            vec!["beer", "salsa", "nuts"],
+            vec!["chips", "salsa", "nuts"],
+        ];
+        let model = Rock::new(&baskets, 2, 0.4);
+        let clustering = dbg!(model.clusters);
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::prelude::IteratorRandom;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decay {
+    Constant,
+    Linear,
+    Exponential,
+    InverseTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule<T: Float + One + Zero> {
+    pub initial: T,
+    pub decay: Decay,
+}
+
+impl<T: Float + One + Zero> Schedule<T> {
+    pub fn new(initial: T, decay: Decay) -> Schedule<T> {
+        Schedule { initial, decay }
+    }
+
+    pub fn value(&self, t: usize, total: usize) -> T {
+        let progress = T::from(t).expect("T::from(usize)") / T::from(total.max(1)).expect("T::from(usize)");
+        match self.decay {
+            Decay::Constant => self.initial,
+            Decay::Linear => self.initial * (T::one() - progress),
+            Decay::Exponential => self.initial * T::from(0.01).expect("T::from(f64)").powf(progress),
+            Decay::InverseTime => self.initial / (T::one() + progress * T::from(2.0).expect("T::from(f64)")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Som<T: Float + One + Zero> {
+    pub rows: usize,
+    pub cols: usize,
+    pub weights: Array2<T>,
+}
+
+impl<T: Float + One + Zero> Som<T> {
+    pub fn new(data: &Array2<T>, rows: usize, cols: usize, iterations: usize, learning_rate: Schedule<T>, sigma: Schedule<T>) -> Som<T> {
+        assert!(rows * cols > 0, "grid must have at least one unit");
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), rows, cols, iterations, learning_rate, sigma);
+        }
+        let mut rng = SmallRng::from_rng(thread_rng()).expect("seed SmallRng from thread_rng");
+        let seeds = data.outer_iter().choose_multiple(&mut rng, rows * cols);
+        assert!(!seeds.is_empty(), "data must have at least one row");
+        let mut weights = Array2::zeros((rows * cols, data.cols()));
+        for (unit, mut weight) in weights.outer_iter_mut().enumerate() {
+            weight.assign(&seeds[unit % seeds.len()]);
+        }
+        let mut som = Som { rows, cols, weights };
+
+        let two = T::from(2.0).expect("T::from(f64)");
+        for t in 0..iterations {
+            let row = data.row(rng.gen_range(0, data.rows()));
+            let bmu = som.bmu(&row);
+            let (bmu_r, bmu_c) = som.position(bmu);
+            let rate = learning_rate.value(t, iterations);
+            let radius = sigma.value(t, iterations).max(T::epsilon());
+            for (unit, mut weight) in som.weights.outer_iter_mut().enumerate() {
+                let (r, c) = (unit / cols, unit % cols);
+                let grid_distance = T::from((r as f64 - bmu_r as f64).powi(2) + (c as f64 - bmu_c as f64).powi(2)).expect("T::from(f64)");
+                let influence = rate * (-grid_distance / (two * radius * radius)).exp();
+                weight.zip_mut_with(&row, |w, x| *w = *w + influence * (*x - *w));
+            }
+        }
+        som
+    }
+
+    pub fn bmu(&self, row: &ArrayView1<T>) -> usize {
+        if row.as_slice().is_none() {
+            return self.bmu(&aview1(&row.to_vec()));
+        }
+        self.weights
+            .outer_iter()
+            .enumerate()
+            .map(|(unit, weight)| (unit, squared_euclidean(row.as_slice().unwrap(), weight.as_slice().unwrap())))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from unit"))
+            .expect("min distance from unit")
+            .0
+    }
+
+    pub fn position(&self, unit: usize) -> (usize, usize) {
+        (unit / self.cols, unit % self.cols)
+    }
+
+    pub fn map(&self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.map(&standard_layout(data.view()));
+        }
+        data.outer_iter().map(|row| self.bmu(&row)).collect::<Vec<usize>>()
+    }
+
+    pub fn quantization_error(&self, data: &Array2<T>) -> T {
+        if !data.is_standard_layout() {
+            return self.quantization_error(&standard_layout(data.view()));
+        }
+        let total = data.outer_iter().fold(T::zero(), |acc, row| {
+            let weight = self.weights.row(self.bmu(&row));
+            acc + squared_euclidean(row.as_slice().unwrap(), weight.as_slice().unwrap()).sqrt()
+        });
+        total / T::from(data.rows().max(1)).expect("T::from(usize)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((8, 2), vec![1.0, 2.0, 1.1, 2.2, 0.9, 1.9, 1.0, 2.1, -2.0, 3.0, -2.2, 3.1, -2.1, 2.9, -1.9, 3.0]).unwrap()
+    }
+
+    #[test]
+    fn test_map() {
+        let som = Som::new(&data(), 1, 2, 500, Schedule::new(0.5, Decay::Linear), Schedule::new(0.5, Decay::Exponential));
+        let units = dbg!(som.map(&data()));
+        assert!(units.iter().take(4).all_equal());
+        assert!(units.iter().skip(4).all_equal());
+        assert!(units[0] != units[4]);
+        assert!(som.quantization_error(&data()) < 0.5);
+
+        let fortran = Array2::from_shape_vec((8, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(som.map(&fortran), units);
+        assert_eq!(som.bmu(&fortran.row(4)), units[4]);
+        assert_eq!(som.quantization_error(&fortran), som.quantization_error(&data()));
+        let transposed = Som::new(&fortran, 1, 2, 500, Schedule::new(0.5, Decay::Linear), Schedule::new(0.5, Decay::Exponential));
+        assert_eq!(transposed.weights.dim(), (2, 2));
+    }
+
+    #[test]
+    fn test_schedules() {
+        for decay in [Decay::Linear, Decay::Exponential, Decay::InverseTime].iter() {
+            let schedule = Schedule::new(1.0, *decay);
+            assert_eq!(schedule.value(0, 100), 1.0);
+            assert!((0..100).map(|t| schedule.value(t, 100)).tuple_windows().all(|(a, b)| a > b));
+        }
+        assert_eq!(Schedule::new(1.0, Decay::Constant).value(50, 100), 1.0);
+    }
+}
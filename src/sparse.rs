@@ -0,0 +1,156 @@
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrMatrix<T: Float + One + Zero> {
+    pub shape: (usize, usize),
+    pub indptr: Vec<usize>,
+    pub indices: Vec<usize>,
+    pub data: Vec<T>,
+}
+
+impl<T: Float + One + Zero> CsrMatrix<T> {
+    pub fn new(shape: (usize, usize), indptr: Vec<usize>, indices: Vec<usize>, data: Vec<T>) -> CsrMatrix<T> {
+        assert_eq!(indptr.len(), shape.0 + 1, "indptr must have one entry per row plus one");
+        assert_eq!(indices.len(), data.len(), "indices and data must have the same length");
+        assert_eq!(indptr[shape.0], data.len(), "indptr must end at the number of stored values");
+        for window in indptr.windows(2) {
+            assert!(window[0] <= window[1], "indptr must be non-decreasing");
+            let row = &indices[window[0]..window[1]];
+            assert!(row.windows(2).all(|pair| pair[0] < pair[1]), "column indices must be strictly increasing within a row");
+            assert!(row.iter().all(|col| *col < shape.1), "column index out of bounds");
+        }
+        CsrMatrix { shape, indptr, indices, data }
+    }
+
+    pub fn from_dense(dense: &Array2<T>) -> CsrMatrix<T> {
+        let mut indptr = vec![0];
+        let mut indices = vec![];
+        let mut data = vec![];
+        for row in dense.outer_iter() {
+            for (col, x) in row.iter().enumerate() {
+                if *x != T::zero() {
+                    indices.push(col);
+                    data.push(*x);
+                }
+            }
+            indptr.push(data.len());
+        }
+        CsrMatrix {
+            shape: dense.dim(),
+            indptr,
+            indices,
+            data,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.shape.0
+    }
+
+    pub fn cols(&self) -> usize {
+        self.shape.1
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn row(&self, idx: usize) -> (&[usize], &[T]) {
+        let (start, end) = (self.indptr[idx], self.indptr[idx + 1]);
+        (&self.indices[start..end], &self.data[start..end])
+    }
+
+    pub fn to_dense(&self) -> Array2<T> {
+        let mut dense = Array2::zeros(self.shape);
+        for row_idx in 0..self.rows() {
+            let (indices, values) = self.row(row_idx);
+            for (col, x) in indices.iter().zip(values.iter()) {
+                dense[[row_idx, *col]] = *x;
+            }
+        }
+        dense
+    }
+
+    pub fn normalized(&self) -> CsrMatrix<T> {
+        let mut data = self.data.clone();
+        for window in self.indptr.windows(2) {
+            let row = &mut data[window[0]..window[1]];
+            let norm = row.iter().fold(T::zero(), |acc, x| acc + *x * *x).sqrt();
+            if norm > T::zero() {
+                row.iter_mut().for_each(|x| *x = *x / norm);
+            }
+        }
+        CsrMatrix {
+            shape: self.shape,
+            indptr: self.indptr.clone(),
+            indices: self.indices.clone(),
+            data,
+        }
+    }
+
+    pub fn dot_dense(&self, idx: usize, dense: &ArrayView1<T>) -> T {
+        let (indices, values) = self.row(idx);
+        indices.iter().zip(values.iter()).fold(T::zero(), |acc, (col, x)| acc + *x * dense[*col])
+    }
+
+    pub fn squared_distance(&self, a: usize, b: usize) -> T {
+        let (a_indices, a_values) = self.row(a);
+        let (b_indices, b_values) = self.row(b);
+        let (mut i, mut j) = (0, 0);
+        let mut total = T::zero();
+        while i < a_indices.len() || j < b_indices.len() {
+            let diff = match (a_indices.get(i), b_indices.get(j)) {
+                (Some(x), Some(y)) => match x.cmp(y) {
+                    Ordering::Less => {
+                        i += 1;
+                        a_values[i - 1]
+                    }
+                    Ordering::Greater => {
+                        j += 1;
+                        b_values[j - 1]
+                    }
+                    Ordering::Equal => {
+                        i += 1;
+                        j += 1;
+                        a_values[i - 1] - b_values[j - 1]
+                    }
+                },
+                (Some(_), None) => {
+                    i += 1;
+                    a_values[i - 1]
+                }
+                _ => {
+                    j += 1;
+                    b_values[j - 1]
+                }
+            };
+            total = total + diff * diff;
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csr() {
+        let dense = arr2(&[[0.0, 3.0, 0.0, 4.0], [1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 0.0], [2.0, 3.0, 0.0, 0.0]]);
+        let csr = CsrMatrix::from_dense(&dense);
+        assert_eq!(csr, CsrMatrix::new((4, 4), vec![0, 2, 3, 3, 5], vec![1, 3, 0, 0, 1], vec![3.0, 4.0, 1.0, 2.0, 3.0]));
+        assert_eq!(csr.nnz(), 5);
+        assert_eq!(csr.to_dense(), dense);
+        for a in 0..4 {
+            for b in 0..4 {
+                let diff = &dense.row(a) - &dense.row(b);
+                assert!((csr.squared_distance(a, b) - diff.dot(&diff)).abs() < 1e-12);
+            }
+        }
+        assert_eq!(csr.normalized().row(0).1, &[0.6, 0.8]);
+        assert_eq!(csr.dot_dense(3, &aview1(&[1.0, 1.0, 1.0, 1.0])), 5.0);
+    }
+}
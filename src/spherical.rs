@@ -0,0 +1,164 @@
+use crate::sparse::CsrMatrix;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::prelude::IteratorRandom;
+use rand::seq::index::sample;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct SphericalKmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<usize>,
+    pub similarity: Vec<T>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> SphericalKmeans<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, iterations: usize, nseeds: usize) -> SphericalKmeans<T> {
+        let mut normalized = data.to_owned();
+        for mut row in normalized.outer_iter_mut() {
+            let norm = row.dot(&row).sqrt();
+            if norm > T::zero() {
+                row.mapv_inplace(|x| x / norm);
+            }
+        }
+        let mut rng = thread_rng();
+        (0..nseeds)
+            .map(|_| {
+                let centers = normalized.outer_iter().choose_multiple(&mut rng, nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>();
+                Self::iterate(&normalized, centers, iterations)
+            })
+            .max_by(|a, b| {
+                a.similarity
+                    .iter()
+                    .cloned()
+                    .sum::<T>()
+                    .partial_cmp(&b.similarity.iter().cloned().sum::<T>())
+                    .expect("similarity is not NAN")
+            })
+            .expect("max similarity")
+    }
+
+    pub fn from_csr(csr: &CsrMatrix<T>, nclust: usize, iterations: usize, nseeds: usize) -> SphericalKmeans<T> {
+        let normalized = csr.normalized();
+        let mut rng = thread_rng();
+        (0..nseeds)
+            .map(|_| {
+                let centers = sample(&mut rng, normalized.rows(), nclust)
+                    .iter()
+                    .map(|row_idx| {
+                        let mut center = Array1::zeros(normalized.cols());
+                        let (indices, values) = normalized.row(row_idx);
+                        for (col, x) in indices.iter().zip(values.iter()) {
+                            center[*col] = *x;
+                        }
+                        center
+                    })
+                    .collect::<Vec<Array1<T>>>();
+                Self::iterate_csr(&normalized, centers, iterations)
+            })
+            .max_by(|a, b| {
+                a.similarity
+                    .iter()
+                    .cloned()
+                    .sum::<T>()
+                    .partial_cmp(&b.similarity.iter().cloned().sum::<T>())
+                    .expect("similarity is not NAN")
+            })
+            .expect("max similarity")
+    }
+
+    fn iterate_csr(normalized: &CsrMatrix<T>, mut centers: Vec<Array1<T>>, iterations: usize) -> SphericalKmeans<T> {
+        let nclust = centers.len();
+        let mut clusters = vec![0; normalized.rows()];
+        let mut similarity = vec![T::zero(); nclust];
+        for _ in 0..iterations {
+            let mut sums: Vec<Array1<T>> = vec![Array1::zeros(normalized.cols()); nclust];
+            similarity = vec![T::zero(); nclust];
+            for (row_idx, cluster) in clusters.iter_mut().enumerate() {
+                let (nearest, cosine) = centers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, center)| (i, normalized.dot_dense(row_idx, &center.view())))
+                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd cosine similarity"))
+                    .expect("max cosine similarity");
+                *cluster = nearest;
+                let (indices, values) = normalized.row(row_idx);
+                for (col, x) in indices.iter().zip(values.iter()) {
+                    sums[nearest][*col] += *x;
+                }
+                similarity[nearest] += cosine;
+            }
+            for (center, sum) in centers.iter_mut().zip(sums) {
+                let norm = sum.dot(&sum).sqrt();
+                if norm > T::zero() {
+                    *center = sum / norm;
+                }
+            }
+        }
+        SphericalKmeans { centers, clusters, similarity }
+    }
+
+    fn iterate(normalized: &Array2<T>, mut centers: Vec<Array1<T>>, iterations: usize) -> SphericalKmeans<T> {
+        let nclust = centers.len();
+        let mut clusters = vec![0; normalized.rows()];
+        let mut similarity = vec![T::zero(); nclust];
+        for _ in 0..iterations {
+            let mut sums = vec![Array1::zeros(normalized.cols()); nclust];
+            similarity = vec![T::zero(); nclust];
+            for (row_idx, row) in normalized.outer_iter().enumerate() {
+                let (cluster, cosine) = nearest(&centers, &row);
+                clusters[row_idx] = cluster;
+                sums[cluster] = &sums[cluster] + &row;
+                similarity[cluster] += cosine;
+            }
+            for (center, sum) in centers.iter_mut().zip(sums) {
+                let norm = sum.dot(&sum).sqrt();
+                if norm > T::zero() {
+                    *center = sum / norm;
+                }
+            }
+        }
+        SphericalKmeans { centers, clusters, similarity }
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        data.outer_iter().map(|row| nearest(&self.centers, &row).0).collect::<Vec<usize>>()
+    }
+}
+
+fn nearest<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(centers: &[Array1<T>], row: &ArrayView1<T>) -> (usize, T) {
+    let norm = row.dot(row).sqrt();
+    centers
+        .iter()
+        .enumerate()
+        .map(|(i, center)| (i, if norm > T::zero() { row.dot(center) / norm } else { T::zero() }))
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd cosine similarity"))
+        .expect("max cosine similarity")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_spherical_kmeans() {
+        let data = Array2::from_shape_vec((8, 2), vec![1.0, 0.1, 10.0, 0.5, 0.2, 0.01, 5.0, -0.3, 0.1, 1.0, 0.5, 10.0, -0.02, 0.3, 0.2, 4.0]).unwrap();
+        let model = SphericalKmeans::new(&data, 2, 20, 5);
+        let clustering = dbg!(model.predict(&data));
+        assert!(clustering.iter().take(4).all_equal());
+        assert!(clustering.iter().skip(4).all_equal());
+        assert!(clustering[0] != clustering[4]);
+        assert!(model.centers.iter().all(|center| (center.dot(center) - 1.0).abs() < 1e-9));
+
+        let sparse = SphericalKmeans::from_csr(&CsrMatrix::from_dense(&data), 2, 20, 5);
+        assert_eq!(sparse.predict(&data), sparse.clusters);
+        assert!(sparse.clusters.iter().take(4).all_equal());
+        assert!(sparse.clusters.iter().skip(4).all_equal());
+        assert!(sparse.clusters[0] != sparse.clusters[4]);
+    }
+}
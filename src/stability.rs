@@ -0,0 +1,86 @@
+use crate::metrics::Label;
+use ndarray::prelude::*;
+use rand::prelude::thread_rng;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn bootstrap_stability<T, L, B, F>(data: &Array2<T>, labels: &[L], resamples: usize, mut cluster: F) -> BTreeMap<usize, f64>
+where
+    T: Copy,
+    L: Label,
+    B: Label,
+    F: FnMut(&Array2<T>) -> Vec<B>,
+{
+    assert_eq!(data.rows(), labels.len(), "one label per row");
+    let n = data.rows();
+    let mut original: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for (row_idx, label) in labels.iter().enumerate() {
+        if let Some(c) = label.cluster() {
+            original.entry(c).or_default().insert(row_idx);
+        }
+    }
+
+    let mut rng = SmallRng::from_rng(thread_rng()).expect("seed SmallRng from thread_rng");
+    let mut totals = original.keys().map(|c| (*c, 0.0)).collect::<BTreeMap<usize, f64>>();
+    for _ in 0..resamples {
+        let indices = (0..n).map(|_| rng.gen_range(0, n)).collect::<Vec<usize>>();
+        let resampled = data.select(Axis(0), &indices);
+        let bootstrap_labels = cluster(&resampled);
+        assert_eq!(bootstrap_labels.len(), indices.len(), "one label per resampled row");
+
+        let mut present = BTreeSet::new();
+        let mut bootstrap: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for (row_idx, label) in indices.iter().zip(bootstrap_labels.iter()) {
+            if present.insert(*row_idx) {
+                if let Some(c) = label.cluster() {
+                    bootstrap.entry(c).or_default().insert(*row_idx);
+                }
+            }
+        }
+
+        for (c, members) in original.iter() {
+            let restricted = members.intersection(&present).cloned().collect::<BTreeSet<usize>>();
+            let best = bootstrap
+                .values()
+                .map(|other| {
+                    let union = restricted.union(other).count();
+                    if union == 0 {
+                        0.0
+                    } else {
+                        restricted.intersection(other).count() as f64 / union as f64
+                    }
+                })
+                .fold(0.0, f64::max);
+            *totals.get_mut(c).expect("original cluster") += best;
+        }
+    }
+
+    totals.into_iter().map(|(c, total)| (c, total / resamples.max(1) as f64)).collect::<BTreeMap<usize, f64>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbscan::Dbscan;
+
+    #[test]
+    fn test_bootstrap_stability() {
+        let data = Array2::from_shape_fn((40, 2), |(row, col)| {
+            let offset = ((row % 20) as f64 * 0.7 + col as f64).sin() * 0.3;
+            if row < 20 {
+                offset
+            } else {
+                10.0 + offset
+            }
+        });
+        let model = Dbscan::new(&data, 1.0, 3, true, None).unwrap();
+        let stability = bootstrap_stability(&data, &model.labels(), 20, |resampled| Dbscan::new(resampled, 1.0, 3, true, None).unwrap().labels());
+        assert_eq!(stability.len(), 2);
+        assert!(dbg!(stability).values().all(|s| *s > 0.95));
+
+        let split = (0..40).map(|row| (row % 2) as usize).collect::<Vec<usize>>();
+        let arbitrary = bootstrap_stability(&data, &split, 20, |resampled| Dbscan::new(resampled, 1.0, 3, true, None).unwrap().labels());
+        assert!(dbg!(arbitrary).values().all(|s| *s < 0.75));
+    }
+}
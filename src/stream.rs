@@ -0,0 +1,470 @@
+use crate::dbscan::Dbscan;
+use crate::error::Error;
+use crate::kmeans::Kmeans;
+use crate::validation::standard_layout;
+use kdtree::distance::squared_euclidean;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone)]
+pub struct MicroCluster<T: Float + One + Zero> {
+    pub n: usize,
+    pub linear_sum: Array1<T>,
+    pub squared_sum: Array1<T>,
+    pub time_sum: T,
+    pub time_squared_sum: T,
+}
+
+impl<T: Float + One + Zero> MicroCluster<T> {
+    pub fn new(point: &ArrayView1<T>, timestamp: T) -> MicroCluster<T> {
+        let linear_sum = Array1::from_vec(point.to_vec());
+        MicroCluster {
+            n: 1,
+            squared_sum: linear_sum.mapv(|x| x * x),
+            linear_sum,
+            time_sum: timestamp,
+            time_squared_sum: timestamp * timestamp,
+        }
+    }
+
+    pub fn centroid(&self) -> Array1<T> {
+        let n = T::from(self.n).expect("T::from(usize)");
+        self.linear_sum.mapv(|x| x / n)
+    }
+
+    pub fn radius(&self) -> T {
+        let n = T::from(self.n).expect("T::from(usize)");
+        let variance = self.squared_sum.iter().zip(self.linear_sum.iter()).fold(T::zero(), |acc, (sq, ls)| acc + *sq / n - (*ls / n).powi(2));
+        variance.max(T::zero()).sqrt()
+    }
+
+    pub fn insert(&mut self, point: &ArrayView1<T>, timestamp: T) {
+        self.n += 1;
+        self.linear_sum.zip_mut_with(point, |a, x| *a = *a + *x);
+        self.squared_sum.zip_mut_with(point, |a, x| *a = *a + *x * *x);
+        self.time_sum = self.time_sum + timestamp;
+        self.time_squared_sum = self.time_squared_sum + timestamp * timestamp;
+    }
+
+    pub fn merge(&mut self, other: &MicroCluster<T>) {
+        self.n += other.n;
+        self.linear_sum.zip_mut_with(&other.linear_sum, |a, b| *a = *a + *b);
+        self.squared_sum.zip_mut_with(&other.squared_sum, |a, b| *a = *a + *b);
+        self.time_sum = self.time_sum + other.time_sum;
+        self.time_squared_sum = self.time_squared_sum + other.time_squared_sum;
+    }
+
+    pub fn relevance_stamp(&self) -> T {
+        let n = T::from(self.n).expect("T::from(usize)");
+        let mean = self.time_sum / n;
+        let std = (self.time_squared_sum / n - mean * mean).max(T::zero()).sqrt();
+        mean + std
+    }
+}
+
+#[derive(Debug)]
+pub struct CluStream<T: Float + One + Zero> {
+    pub max_micro_clusters: usize,
+    pub radius_factor: T,
+    pub horizon: T,
+    pub micro_clusters: Vec<MicroCluster<T>>,
+}
+
+impl<T: Float + One + Zero> CluStream<T> {
+    pub fn new(max_micro_clusters: usize, radius_factor: T, horizon: T) -> CluStream<T> {
+        assert!(max_micro_clusters > 1, "max_micro_clusters must be at least 2");
+        CluStream {
+            max_micro_clusters,
+            radius_factor,
+            horizon,
+            micro_clusters: Vec::with_capacity(max_micro_clusters),
+        }
+    }
+
+    pub fn partial_fit(&mut self, data: &Array2<T>, timestamps: &[T]) {
+        if !data.is_standard_layout() {
+            return self.partial_fit(&standard_layout(data.view()), timestamps);
+        }
+        for (row, timestamp) in data.outer_iter().zip(timestamps.iter()) {
+            self.insert(&row, *timestamp);
+        }
+    }
+
+    pub fn insert(&mut self, point: &ArrayView1<T>, timestamp: T) {
+        if point.as_slice().is_none() {
+            return self.insert(&aview1(&point.to_vec()), timestamp);
+        }
+        let centroids = self.micro_clusters.iter().map(|mc| mc.centroid()).collect::<Vec<Array1<T>>>();
+        let nearest = nearest(&centroids, point.as_slice().unwrap());
+        if let Some((idx, distance)) = nearest {
+            let boundary = if self.micro_clusters[idx].n > 1 {
+                self.micro_clusters[idx].radius() * self.radius_factor
+            } else {
+                nearest_other(&centroids, idx).unwrap_or_else(T::zero)
+            };
+            if distance <= boundary {
+                self.micro_clusters[idx].insert(point, timestamp);
+                return;
+            }
+        }
+
+        if self.micro_clusters.len() >= self.max_micro_clusters {
+            let expired = self
+                .micro_clusters
+                .iter()
+                .enumerate()
+                .filter(|(_, mc)| mc.relevance_stamp() < timestamp - self.horizon)
+                .min_by(|(_, a), (_, b)| a.relevance_stamp().partial_cmp(&b.relevance_stamp()).expect("PartialOrd relevance stamp"))
+                .map(|(idx, _)| idx);
+            match expired {
+                Some(idx) => {
+                    self.micro_clusters.swap_remove(idx);
+                }
+                None => {
+                    let (a, b) = closest_pair(&centroids);
+                    let absorbed = self.micro_clusters.swap_remove(b.max(a));
+                    self.micro_clusters[b.min(a)].merge(&absorbed);
+                }
+            }
+        }
+        self.micro_clusters.push(MicroCluster::new(point, timestamp));
+    }
+
+    pub fn snapshot(&self) -> Vec<MicroCluster<T>> {
+        self.micro_clusters.clone()
+    }
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> CluStream<T> {
+    pub fn macro_clusters(&self, nclust: usize, iterations: usize, nseeds: usize) -> Result<Kmeans<T>, Error> {
+        let dims = self.micro_clusters.first().map_or(0, |mc| mc.linear_sum.len());
+        let flat = self.micro_clusters.iter().flat_map(|mc| mc.centroid().to_vec()).collect::<Vec<T>>();
+        let centroids = Array2::from_shape_vec((self.micro_clusters.len(), dims), flat).expect("centroid matrix shape");
+        Kmeans::new(&centroids, nclust, iterations, nseeds)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DampedMicroCluster<T: Float + One + Zero> {
+    pub weight: T,
+    pub linear_sum: Array1<T>,
+    pub squared_sum: Array1<T>,
+    pub created: T,
+    pub updated: T,
+}
+
+impl<T: Float + One + Zero> DampedMicroCluster<T> {
+    pub fn new(point: &ArrayView1<T>, timestamp: T) -> DampedMicroCluster<T> {
+        let linear_sum = Array1::from_vec(point.to_vec());
+        DampedMicroCluster {
+            weight: T::one(),
+            squared_sum: linear_sum.mapv(|x| x * x),
+            linear_sum,
+            created: timestamp,
+            updated: timestamp,
+        }
+    }
+
+    pub fn center(&self) -> Array1<T> {
+        self.linear_sum.mapv(|x| x / self.weight)
+    }
+
+    pub fn radius(&self) -> T {
+        let variance = self
+            .squared_sum
+            .iter()
+            .zip(self.linear_sum.iter())
+            .fold(T::zero(), |acc, (sq, ls)| acc + *sq / self.weight - (*ls / self.weight).powi(2));
+        variance.max(T::zero()).sqrt()
+    }
+
+    pub fn decay(&mut self, timestamp: T, lambda: T) {
+        let factor = T::from(2.0).expect("T::from(f64)").powf(-lambda * (timestamp - self.updated));
+        self.weight = self.weight * factor;
+        self.linear_sum.mapv_inplace(|x| x * factor);
+        self.squared_sum.mapv_inplace(|x| x * factor);
+        self.updated = timestamp;
+    }
+
+    pub fn insert(&mut self, point: &ArrayView1<T>, timestamp: T, lambda: T) {
+        self.decay(timestamp, lambda);
+        self.weight = self.weight + T::one();
+        self.linear_sum.zip_mut_with(point, |a, x| *a = *a + *x);
+        self.squared_sum.zip_mut_with(point, |a, x| *a = *a + *x * *x);
+    }
+
+    fn radius_with(&self, point: &ArrayView1<T>, timestamp: T, lambda: T) -> T {
+        let mut merged = self.clone();
+        merged.insert(point, timestamp, lambda);
+        merged.radius()
+    }
+}
+
+#[derive(Debug)]
+pub struct DenStream<T: Float + One + Zero> {
+    pub eps: T,
+    pub beta: T,
+    pub mu: T,
+    pub lambda: T,
+    pub potential: Vec<DampedMicroCluster<T>>,
+    pub outliers: Vec<DampedMicroCluster<T>>,
+    prune_period: T,
+    last_prune: T,
+}
+
+impl<T: Float + One + Zero> DenStream<T> {
+    pub fn new(eps: T, beta: T, mu: T, lambda: T) -> DenStream<T> {
+        let threshold = beta * mu;
+        assert!(threshold > T::one(), "beta * mu must be greater than 1");
+        assert!(lambda > T::zero(), "lambda must be positive");
+        DenStream {
+            eps,
+            beta,
+            mu,
+            lambda,
+            potential: Vec::new(),
+            outliers: Vec::new(),
+            prune_period: ((threshold / (threshold - T::one())).log2() / lambda).ceil(),
+            last_prune: T::zero(),
+        }
+    }
+
+    pub fn partial_fit(&mut self, data: &Array2<T>, timestamps: &[T]) {
+        if !data.is_standard_layout() {
+            return self.partial_fit(&standard_layout(data.view()), timestamps);
+        }
+        for (row, timestamp) in data.outer_iter().zip(timestamps.iter()) {
+            self.insert(&row, *timestamp);
+        }
+    }
+
+    pub fn insert(&mut self, point: &ArrayView1<T>, timestamp: T) {
+        if point.as_slice().is_none() {
+            return self.insert(&aview1(&point.to_vec()), timestamp);
+        }
+        let slice = point.as_slice().unwrap();
+        let nearest_potential = nearest(&self.potential.iter().map(|mc| mc.center()).collect::<Vec<Array1<T>>>(), slice);
+        let merged = match nearest_potential {
+            Some((idx, _)) if self.potential[idx].radius_with(point, timestamp, self.lambda) <= self.eps => {
+                self.potential[idx].insert(point, timestamp, self.lambda);
+                true
+            }
+            _ => false,
+        };
+
+        if !merged {
+            let nearest_outlier = nearest(&self.outliers.iter().map(|mc| mc.center()).collect::<Vec<Array1<T>>>(), slice);
+            match nearest_outlier {
+                Some((idx, _)) if self.outliers[idx].radius_with(point, timestamp, self.lambda) <= self.eps => {
+                    self.outliers[idx].insert(point, timestamp, self.lambda);
+                    if self.outliers[idx].weight > self.beta * self.mu {
+                        let promoted = self.outliers.swap_remove(idx);
+                        self.potential.push(promoted);
+                    }
+                }
+                _ => self.outliers.push(DampedMicroCluster::new(point, timestamp)),
+            }
+        }
+
+        if timestamp - self.last_prune >= self.prune_period {
+            self.prune(timestamp);
+        }
+    }
+
+    fn prune(&mut self, timestamp: T) {
+        let (lambda, threshold, period) = (self.lambda, self.beta * self.mu, self.prune_period);
+        let two = T::from(2.0).expect("T::from(f64)");
+        for mc in self.potential.iter_mut().chain(self.outliers.iter_mut()) {
+            mc.decay(timestamp, lambda);
+        }
+        self.potential.retain(|mc| mc.weight >= threshold);
+        self.outliers.retain(|mc| {
+            let xi = (two.powf(-lambda * (timestamp - mc.created + period)) - T::one()) / (two.powf(-lambda * period) - T::one());
+            mc.weight >= xi
+        });
+        self.last_prune = timestamp;
+    }
+
+    pub fn centers(&self) -> Array2<T> {
+        let dims = self.potential.first().map_or(0, |mc| mc.linear_sum.len());
+        let flat = self.potential.iter().flat_map(|mc| mc.center().to_vec()).collect::<Vec<T>>();
+        Array2::from_shape_vec((self.potential.len(), dims), flat).expect("center matrix shape")
+    }
+
+    pub fn macro_clusters(&self) -> Result<Dbscan<T>, Error> {
+        Dbscan::new(&self.centers(), self.eps + self.eps, 1, true, None)
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()));
+        }
+        if self.potential.is_empty() {
+            return vec![0; data.rows()];
+        }
+        let model = self.macro_clusters().expect("DBSCAN over the potential micro-cluster centers");
+        let centers = self.potential.iter().map(|mc| mc.center()).collect::<Vec<Array1<T>>>();
+        data.outer_iter()
+            .map(|row| match nearest(&centers, row.as_slice().unwrap()) {
+                Some((idx, distance)) if distance <= self.potential[idx].radius() + self.eps => model.clusters[idx],
+                _ => 0,
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+#[derive(Debug)]
+pub struct Leader<T: Float + One + Zero> {
+    pub threshold: T,
+    pub leaders: Vec<Array1<T>>,
+    pub counts: Vec<usize>,
+}
+
+impl<T: Float + One + Zero> Leader<T> {
+    pub fn new(threshold: T) -> Leader<T> {
+        Leader {
+            threshold,
+            leaders: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    pub fn partial_fit(&mut self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.partial_fit(&standard_layout(data.view()));
+        }
+        data.outer_iter().map(|row| self.insert(&row)).collect::<Vec<usize>>()
+    }
+
+    pub fn insert(&mut self, point: &ArrayView1<T>) -> usize {
+        if point.as_slice().is_none() {
+            return self.insert(&aview1(&point.to_vec()));
+        }
+        match nearest(&self.leaders, point.as_slice().unwrap()) {
+            Some((idx, distance)) if distance <= self.threshold => {
+                self.counts[idx] += 1;
+                idx
+            }
+            _ => {
+                self.leaders.push(point.to_owned());
+                self.counts.push(1);
+                self.leaders.len() - 1
+            }
+        }
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<Option<usize>> {
+        if !data.is_standard_layout() {
+            return self.predict(&standard_layout(data.view()));
+        }
+        data.outer_iter()
+            .map(|row| match nearest(&self.leaders, row.as_slice().unwrap()) {
+                Some((idx, distance)) if distance <= self.threshold => Some(idx),
+                _ => None,
+            })
+            .collect::<Vec<Option<usize>>>()
+    }
+}
+
+fn nearest<T: Float + One + Zero>(centroids: &[Array1<T>], point: &[T]) -> Option<(usize, T)> {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(idx, centroid)| (idx, squared_euclidean(centroid.as_slice().unwrap(), point).sqrt()))
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("PartialOrd distance from centroid"))
+}
+
+fn nearest_other<T: Float + One + Zero>(centroids: &[Array1<T>], idx: usize) -> Option<T> {
+    centroids
+        .iter()
+        .enumerate()
+        .filter(|(other, _)| *other != idx)
+        .map(|(_, centroid)| squared_euclidean(centroid.as_slice().unwrap(), centroids[idx].as_slice().unwrap()).sqrt())
+        .min_by(|a, b| a.partial_cmp(b).expect("PartialOrd distance between centroids"))
+}
+
+fn closest_pair<T: Float + One + Zero>(centroids: &[Array1<T>]) -> (usize, usize) {
+    let mut best = (0, 1, T::infinity());
+    for i in 0..centroids.len() {
+        for j in (i + 1)..centroids.len() {
+            let d = squared_euclidean(centroids[i].as_slice().unwrap(), centroids[j].as_slice().unwrap());
+            if d < best.2 {
+                best = (i, j, d);
+            }
+        }
+    }
+    (best.0, best.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((10, 2), vec![1.0, 2.0, -2.0, 3.0, 1.1, 2.2, -2.2, 3.1, 0.9, 1.9, -2.1, 2.9, 1.0, 2.1, -1.9, 3.0, 1.2, 2.0, -2.0, 3.2]).unwrap()
+    }
+
+    #[test]
+    fn test_clustream() {
+        let mut stream = CluStream::new(4, 2.0, 100.0);
+        let timestamps = (0..10).map(f64::from).collect::<Vec<f64>>();
+        stream.partial_fit(&data(), &timestamps);
+        assert!(stream.micro_clusters.len() <= 4);
+        assert_eq!(stream.snapshot().iter().map(|mc| mc.n).sum::<usize>(), 10);
+
+        let model = stream.macro_clusters(2, 20, 5).unwrap();
+        let clustering = dbg!(model.predict(&data()));
+        assert!(clustering.iter().step_by(2).all_equal());
+        assert!(clustering.iter().skip(1).step_by(2).all_equal());
+        assert!(clustering[0] != clustering[1]);
+    }
+
+    #[test]
+    fn test_denstream() {
+        let mut stream = DenStream::new(0.5, 0.5, 3.0, 0.01);
+        let timestamps = (0..10).map(f64::from).collect::<Vec<f64>>();
+        stream.partial_fit(&data(), &timestamps);
+        stream.insert(&arr1(&[10.0, -10.0]).view(), 10.0);
+        assert_eq!(stream.potential.len(), 2);
+        assert_eq!(stream.outliers.len(), 1);
+
+        let clustering = dbg!(stream.predict(&data()));
+        assert!(clustering.iter().step_by(2).all_equal());
+        assert!(clustering.iter().skip(1).step_by(2).all_equal());
+        assert!(clustering[0] != clustering[1]);
+        assert!(clustering[0] > 0 && clustering[1] > 0);
+        assert_eq!(stream.predict(&arr2(&[[10.0, -10.0]])), vec![0]);
+    }
+
+    #[test]
+    fn test_leader() {
+        let mut stream = Leader::new(0.5);
+        let clustering = dbg!(stream.partial_fit(&data()));
+        assert_eq!(clustering[..2], [0, 1]);
+        assert!(clustering.iter().step_by(2).all_equal());
+        assert!(clustering.iter().skip(1).step_by(2).all_equal());
+        assert_eq!(stream.counts, vec![5, 5]);
+        assert_eq!(stream.insert(&arr1(&[10.0, -10.0]).view()), 2);
+        assert_eq!(stream.predict(&arr2(&[[1.0, 2.0], [20.0, 20.0]])), vec![Some(0), None]);
+
+        let fortran = Array2::from_shape_vec((10, 2).f(), data().t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(Leader::new(0.5).partial_fit(&fortran), clustering);
+        assert_eq!(stream.predict(&fortran), clustering.iter().map(|c| Some(*c)).collect::<Vec<Option<usize>>>());
+        assert_eq!(Leader::new(0.5).insert(&fortran.row(1)), 0);
+    }
+
+    #[test]
+    fn test_expired_micro_clusters() {
+        let mut stream = CluStream::new(2, 2.0, 5.0);
+        let points = arr2(&[[0.0, 0.0], [10.0, 10.0], [30.0, 30.0]]);
+        stream.partial_fit(&points, &[0.0, 1.0, 100.0]);
+        assert_eq!(stream.micro_clusters.len(), 2);
+        assert_eq!(stream.snapshot().iter().map(|mc| mc.n).sum::<usize>(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::kmeans::Kmeans;
+use crate::metric::{Metric, SquaredEuclidean};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use rand::prelude::thread_rng;
+use rand::prelude::IteratorRandom;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct TrimmedKmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub alpha: T,
+    pub centers: Vec<Array1<T>>,
+    pub clusters: Vec<Option<usize>>,
+    pub withinss: Vec<T>,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> TrimmedKmeans<T> {
+    pub fn new(data: &Array2<T>, nclust: usize, alpha: T, iterations: usize, nseeds: usize) -> TrimmedKmeans<T> {
+        assert!(alpha >= T::zero() && alpha < T::one(), "alpha must be in [0, 1)");
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), nclust, alpha, iterations, nseeds);
+        }
+        let trimmed = (alpha * T::from(data.rows()).expect("T::from(usize)")).floor().to_usize().expect("usize from T");
+        assert!(data.rows() - trimmed >= nclust, "too few points left after trimming");
+        let mut rng = thread_rng();
+        (0..nseeds)
+            .map(|_| {
+                let centers = data.outer_iter().choose_multiple(&mut rng, nclust).iter().map(|row| row.to_owned()).collect::<Vec<Array1<T>>>();
+                Self::iterate(data, centers, alpha, trimmed, iterations)
+            })
+            .min_by(|a, b| a.withinss.iter().cloned().sum::<T>().partial_cmp(&b.withinss.iter().cloned().sum::<T>()).expect("withinss is not NAN"))
+            .expect("min withinss")
+    }
+
+    fn iterate(data: &Array2<T>, mut centers: Vec<Array1<T>>, alpha: T, trimmed: usize, iterations: usize) -> TrimmedKmeans<T> {
+        let nclust = centers.len();
+        let mut clusters = vec![None; data.rows()];
+        let mut withinss = vec![T::zero(); nclust];
+        for _ in 0..iterations {
+            let nearest = data
+                .outer_iter()
+                .map(|row| {
+                    let cluster = Kmeans::predict_with_centers(&centers, &row);
+                    (cluster, SquaredEuclidean.distance(row.as_slice().unwrap(), centers[cluster].as_slice().unwrap()))
+                })
+                .collect::<Vec<(usize, T)>>();
+            let mut order = (0..data.rows()).collect::<Vec<usize>>();
+            order.sort_by(|a, b| nearest[*a].1.partial_cmp(&nearest[*b].1).expect("PartialOrd distance from center"));
+            let kept = &order[..data.rows() - trimmed];
+
+            clusters = vec![None; data.rows()];
+            withinss = vec![T::zero(); nclust];
+            let mut sums = vec![Array1::zeros(data.cols()); nclust];
+            let mut counts = vec![0; nclust];
+            for i in kept.iter() {
+                let (cluster, distance) = nearest[*i];
+                clusters[*i] = Some(cluster);
+                sums[cluster] = &sums[cluster] + &data.row(*i);
+                counts[cluster] += 1;
+                withinss[cluster] += distance;
+            }
+            let updated = sums
+                .into_iter()
+                .zip(counts)
+                .zip(centers.iter())
+                .map(|((sum, count), center)| if count > 0 { sum / T::from(count).expect("T::from(usize)") } else { center.clone() })
+                .collect::<Vec<Array1<T>>>();
+            if updated == centers {
+                break;
+            }
+            centers = updated;
+        }
+        TrimmedKmeans { alpha, centers, clusters, withinss }
+    }
+
+    pub fn outliers(&self) -> Vec<usize> {
+        self.clusters.iter().enumerate().filter(|(_, c)| c.is_none()).map(|(i, _)| i).collect::<Vec<usize>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trimmed() {
+        let data = Array2::from_shape_vec((10, 2), vec![0.0, 0.0, 0.2, 0.1, 0.1, 0.3, 0.3, 0.2, 8.0, 8.0, 8.3, 7.9, 7.8, 8.1, 8.1, 8.2, 50.0, -40.0, -60.0, 30.0]).unwrap();
+        let model = TrimmedKmeans::new(&data, 2, 0.2, 20, 10);
+        assert_eq!(model.outliers(), vec![8, 9]);
+        let clusters = &model.clusters;
+        assert!(clusters[..4].iter().all(|c| *c == clusters[0]));
+        assert!(clusters[4..8].iter().all(|c| *c == clusters[4]));
+        assert!(clusters[0] != clusters[4]);
+        assert!(model.centers.iter().all(|c| c.iter().all(|x| x.abs() < 10.0)));
+
+        let fortran = Array2::from_shape_vec((10, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        assert_eq!(TrimmedKmeans::new(&fortran, 2, 0.2, 20, 10).outliers(), vec![8, 9]);
+    }
+}
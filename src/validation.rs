@@ -0,0 +1,97 @@
+use crate::error::Error;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinite {
+    Reject,
+    Skip,
+    Impute,
+}
+
+#[derive(Debug, Clone)]
+pub struct Checked<T: Float + One + Zero> {
+    pub data: Array2<T>,
+    pub rows: Vec<usize>,
+}
+
+pub fn standard_layout<T: Copy>(data: ArrayView2<T>) -> Array2<T> {
+    Array2::from_shape_vec(data.dim(), data.iter().cloned().collect::<Vec<T>>()).expect("shape of a 2-d view")
+}
+
+pub fn non_finite_rows<T: Float + One + Zero>(data: &Array2<T>) -> Vec<usize> {
+    data.outer_iter().enumerate().filter(|(_, row)| row.iter().any(|x| !x.is_finite())).map(|(i, _)| i).collect::<Vec<usize>>()
+}
+
+pub fn check<T: Float + One + Zero>(data: &Array2<T>, policy: NonFinite) -> Result<Checked<T>, Error> {
+    let offending = non_finite_rows(data);
+    if offending.is_empty() {
+        return Ok(Checked {
+            data: data.to_owned(),
+            rows: (0..data.rows()).collect::<Vec<usize>>(),
+        });
+    }
+    match policy {
+        NonFinite::Reject => Err(Error::NonFiniteValue { rows: offending }),
+        NonFinite::Skip => {
+            let rows = (0..data.rows()).filter(|i| offending.binary_search(i).is_err()).collect::<Vec<usize>>();
+            Ok(Checked {
+                data: data.select(Axis(0), &rows),
+                rows,
+            })
+        }
+        NonFinite::Impute => {
+            let means = data
+                .gencolumns()
+                .into_iter()
+                .map(|col| {
+                    let (total, count) = col.iter().filter(|x| x.is_finite()).fold((T::zero(), 0), |(total, count), x| (total + *x, count + 1));
+                    if count > 0 {
+                        total / T::from(count).expect("T::from(usize)")
+                    } else {
+                        T::zero()
+                    }
+                })
+                .collect::<Vec<T>>();
+            let data = Array2::from_shape_fn(data.dim(), |(i, j)| if data[[i, j]].is_finite() { data[[i, j]] } else { means[j] });
+            Ok(Checked {
+                rows: (0..data.rows()).collect::<Vec<usize>>(),
+                data,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbscan::Dbscan;
+    use crate::kmeans::Kmeans;
+    use std::f64;
+
+    fn data() -> Array2<f64> {
+        Array2::from_shape_vec((5, 2), vec![0.0, 0.0, 0.1, f64::NAN, 0.2, 0.1, 5.0, 5.0, f64::INFINITY, 5.1]).unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check(&data(), NonFinite::Reject).unwrap_err(), Error::NonFiniteValue { rows: vec![1, 4] });
+
+        let skipped = check(&data(), NonFinite::Skip).unwrap();
+        assert_eq!(skipped.rows, vec![0, 2, 3]);
+        assert_eq!(skipped.data.rows(), 3);
+
+        let imputed = check(&data(), NonFinite::Impute).unwrap();
+        assert_eq!(imputed.data[[1, 1]], (0.0 + 0.1 + 5.0 + 5.1) / 4.0);
+        assert_eq!(imputed.data[[4, 0]], (0.0 + 0.1 + 0.2 + 5.0) / 4.0);
+
+        assert_eq!(Kmeans::try_new(&data(), 2, 10, 2, NonFinite::Reject).unwrap_err(), Error::NonFiniteValue { rows: vec![1, 4] });
+        assert_eq!(Kmeans::try_new(&data(), 4, 10, 2, NonFinite::Skip).unwrap_err(), Error::InvalidParameter("k = 4 must be between 1 and the number of rows (3)".to_string()));
+        assert_eq!(Dbscan::try_new(&data(), -0.5, 2, false, NonFinite::Skip).unwrap_err(), Error::InvalidParameter("eps must be positive".to_string()));
+        let (model, rows) = Kmeans::try_new(&data(), 2, 10, 2, NonFinite::Skip).unwrap();
+        assert_eq!(model.clusters.len(), rows.len());
+        let (model, rows) = Dbscan::try_new(&data(), 0.5, 2, false, NonFinite::Impute).unwrap();
+        assert_eq!(model.clusters.len(), rows.len());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::kmeans::{Kmeans, KmeansBuilder};
+use crate::metric::{Metric, SquaredEuclidean};
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone)]
+pub struct Node<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub center: Array1<T>,
+    pub children: Vec<usize>,
+    pub word: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct VocabularyTree<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub branching: usize,
+    pub depth: usize,
+    pub nodes: Vec<Node<T>>,
+    pub nwords: usize,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> VocabularyTree<T> {
+    pub fn new(data: &Array2<T>, branching: usize, depth: usize, iterations: usize, nseeds: usize) -> VocabularyTree<T> {
+        assert!(branching > 1, "branching must be at least 2");
+        assert!(data.rows() > 0, "need at least one row");
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), branching, depth, iterations, nseeds);
+        }
+        let builder = KmeansBuilder::new().k(branching).max_iter(iterations).nseeds(nseeds);
+        let mut tree = VocabularyTree {
+            branching,
+            depth,
+            nodes: vec![],
+            nwords: 0,
+        };
+        tree.grow(data, &(0..data.rows()).collect::<Vec<usize>>(), data.mean_axis(Axis(0)), 0, &builder);
+        tree
+    }
+
+    fn grow(&mut self, data: &Array2<T>, indices: &[usize], center: Array1<T>, level: usize, builder: &KmeansBuilder<T>) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node { center, children: vec![], word: None });
+        if level == self.depth || indices.len() <= self.branching {
+            self.nodes[id].word = Some(self.nwords);
+            self.nwords += 1;
+            return id;
+        }
+
+        let subset = data.select(Axis(0), indices);
+        let model: Kmeans<T> = builder.build(&subset).expect("k-means on more rows than branching");
+        let mut children = vec![];
+        for (cluster, center) in model.centers.into_iter().enumerate() {
+            let members = indices.iter().zip(model.clusters.iter()).filter(|(_, c)| **c == cluster).map(|(i, _)| *i).collect::<Vec<usize>>();
+            if !members.is_empty() {
+                children.push(self.grow(data, &members, center, level + 1, builder));
+            }
+        }
+        self.nodes[id].children = children;
+        id
+    }
+
+    pub fn quantize(&self, data: &Array2<T>) -> Vec<usize> {
+        if !data.is_standard_layout() {
+            return self.quantize(&standard_layout(data.view()));
+        }
+        data.outer_iter()
+            .map(|row| {
+                let mut node = &self.nodes[0];
+                while node.word.is_none() {
+                    node = node
+                        .children
+                        .iter()
+                        .map(|child| &self.nodes[*child])
+                        .min_by(|a, b| {
+                            let da = SquaredEuclidean.distance(row.as_slice().unwrap(), a.center.as_slice().unwrap());
+                            let db = SquaredEuclidean.distance(row.as_slice().unwrap(), b.center.as_slice().unwrap());
+                            da.partial_cmp(&db).expect("PartialOrd distance from center")
+                        })
+                        .expect("min distance from child center");
+                }
+                node.word.expect("leaf word")
+            })
+            .collect::<Vec<usize>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vocabulary() {
+        let data = Array2::from_shape_vec((16, 1), vec![0.0, 0.5, 1.0, 1.5, 10.0, 10.5, 11.0, 11.5, 100.0, 100.5, 101.0, 101.5, 110.0, 110.5, 111.0, 111.5]).unwrap();
+        let tree = VocabularyTree::new(&data, 2, 2, 50, 5);
+        assert_eq!(tree.nwords, 4);
+        let words = tree.quantize(&data);
+        for block in words.chunks(4) {
+            assert!(block.iter().all(|w| *w == block[0]));
+        }
+        assert_eq!(words.iter().step_by(4).cloned().collect::<std::collections::BTreeSet<usize>>().len(), 4);
+        assert_eq!(tree.quantize(&arr2(&[[0.7], [110.2]])), vec![words[0], words[12]]);
+
+        let fortran = Array2::from_shape_vec((16, 2).f(), data.iter().chain(data.iter()).cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let tree = VocabularyTree::new(&fortran, 2, 2, 50, 5);
+        assert_eq!(tree.nwords, 4);
+        let transposed = tree.quantize(&fortran);
+        assert!(transposed.chunks(4).all(|block| block.iter().all(|w| *w == block[0])));
+        assert_eq!(transposed, tree.quantize(&standard_layout(fortran.view())));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::metric::Metric;
+use crate::validation::standard_layout;
+use ndarray::prelude::*;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+
+#[derive(Debug, Clone)]
+pub struct VpNode<T: Float + One + Zero> {
+    pub point: usize,
+    pub radius: T,
+    pub inside: Option<usize>,
+    pub outside: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VpTree<T: Float + One + Zero> {
+    pub nodes: Vec<VpNode<T>>,
+    pub root: Option<usize>,
+}
+
+impl<T: Float + One + Zero> VpTree<T> {
+    pub fn new<M: Metric<T>>(data: &Array2<T>, metric: &M) -> VpTree<T> {
+        if !data.is_standard_layout() {
+            return Self::new(&standard_layout(data.view()), metric);
+        }
+        let mut tree = VpTree { nodes: Vec::with_capacity(data.rows()), root: None };
+        let mut indices = (0..data.rows()).collect::<Vec<usize>>();
+        tree.root = tree.grow(data, &mut indices, metric);
+        tree
+    }
+
+    fn grow<M: Metric<T>>(&mut self, data: &Array2<T>, indices: &mut [usize], metric: &M) -> Option<usize> {
+        let (point, rest) = indices.split_first_mut()?;
+        let vantage = data.row(*point);
+        let mut distances = rest.iter().map(|idx| (metric.distance(vantage.as_slice().unwrap(), data.row(*idx).as_slice().unwrap()), *idx)).collect::<Vec<(T, usize)>>();
+        distances.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("PartialOrd distance from vantage point"));
+        let middle = distances.len() / 2;
+        let radius = distances.get(middle).map_or(T::zero(), |(d, _)| *d);
+        let split = distances.iter().position(|(d, _)| *d > radius).unwrap_or(distances.len());
+        for (slot, (_, idx)) in rest.iter_mut().zip(distances.iter()) {
+            *slot = *idx;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(VpNode {
+            point: *point,
+            radius,
+            inside: None,
+            outside: None,
+        });
+        let (inside, outside) = rest.split_at_mut(split);
+        self.nodes[id].inside = self.grow(data, inside, metric);
+        self.nodes[id].outside = self.grow(data, outside, metric);
+        Some(id)
+    }
+
+    pub fn insert<M: Metric<T>>(&mut self, data: &Array2<T>, idx: usize, metric: &M) {
+        if !data.is_standard_layout() {
+            return self.insert(&standard_layout(data.view()), idx, metric);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(VpNode {
+            point: idx,
+            radius: T::zero(),
+            inside: None,
+            outside: None,
+        });
+        let mut current = match self.root {
+            Some(root) => root,
+            None => {
+                self.root = Some(id);
+                return;
+            }
+        };
+        let row = data.row(idx);
+        loop {
+            let node = &self.nodes[current];
+            let d = metric.distance(row.as_slice().unwrap(), data.row(node.point).as_slice().unwrap());
+            let child = if d <= node.radius { node.inside } else { node.outside };
+            match child {
+                Some(child) => current = child,
+                None => {
+                    if d <= self.nodes[current].radius {
+                        self.nodes[current].inside = Some(id);
+                    } else {
+                        self.nodes[current].outside = Some(id);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn within<M: Metric<T>>(&self, data: &Array2<T>, row: &[T], eps: T, metric: &M, neighbours: &mut Vec<usize>) {
+        if !data.is_standard_layout() {
+            return self.within(&standard_layout(data.view()), row, eps, metric, neighbours);
+        }
+        let mut pending = self.root.into_iter().collect::<Vec<usize>>();
+        while let Some(id) = pending.pop() {
+            let node = &self.nodes[id];
+            let d = metric.distance(row, data.row(node.point).as_slice().unwrap());
+            if d <= eps {
+                neighbours.push(node.point);
+            }
+            if let Some(inside) = node.inside {
+                if d - eps <= node.radius {
+                    pending.push(inside);
+                }
+            }
+            if let Some(outside) = node.outside {
+                if d + eps >= node.radius {
+                    pending.push(outside);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::Manhattan;
+
+    #[test]
+    fn test_within() {
+        let data = Array2::from_shape_fn((50, 2), |(row, col)| ((row * 11 + col * 5) as f64 * 0.83).sin() * 3.0);
+        let mut tree = VpTree::new(&data.select(Axis(0), &(0..40).collect::<Vec<usize>>()), &Manhattan);
+        for idx in 40..50 {
+            tree.insert(&data, idx, &Manhattan);
+        }
+        for row in data.outer_iter() {
+            let mut found = vec![];
+            tree.within(&data, row.as_slice().unwrap(), 1.5, &Manhattan, &mut found);
+            found.sort_unstable();
+            let expected = (0..50).filter(|idx| Manhattan.distance(row.as_slice().unwrap(), data.row(*idx).as_slice().unwrap()) <= 1.5).collect::<Vec<usize>>();
+            assert_eq!(found, expected);
+        }
+
+        let fortran = Array2::from_shape_vec((50, 2).f(), data.t().iter().cloned().collect()).unwrap();
+        assert!(!fortran.is_standard_layout());
+        let transposed = VpTree::new(&fortran, &Manhattan);
+        for row in data.outer_iter() {
+            let (mut found, mut expected) = (vec![], vec![]);
+            transposed.within(&fortran, row.as_slice().unwrap(), 1.5, &Manhattan, &mut found);
+            VpTree::new(&data, &Manhattan).within(&data, row.as_slice().unwrap(), 1.5, &Manhattan, &mut expected);
+            assert_eq!(found, expected);
+        }
+    }
+}
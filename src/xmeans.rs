@@ -0,0 +1,174 @@
+use crate::kmeans::Kmeans;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::float::Float;
+use num_traits::identities::{One, Zero};
+use std::f64::consts::PI;
+use std::iter::Sum;
+use std::ops::AddAssign;
+
+#[derive(Debug)]
+pub struct Xmeans<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> {
+    pub k: usize,
+    pub model: Kmeans<T>,
+    pub bic: T,
+}
+
+impl<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum> Xmeans<T> {
+    pub fn new(data: &Array2<T>, kmin: usize, kmax: usize, iterations: usize, nseeds: usize) -> Xmeans<T> {
+        assert!(kmin > 0 && kmin <= kmax, "kmin must be between 1 and kmax");
+        let mut model = Kmeans::new(data, kmin, iterations, nseeds).expect("k-means on finite data with kmin at most the number of rows");
+        loop {
+            let k = model.centers.len();
+            let mut budget = kmax - k;
+            let mut centers = Vec::with_capacity(kmax);
+            for (cluster, center) in model.centers.iter().enumerate() {
+                let members = (0..data.rows()).filter(|row_idx| model.clusters[*row_idx] == cluster).collect::<Vec<usize>>();
+                if budget > 0 && members.len() > 2 {
+                    let subset = data.select(Axis(0), &members);
+                    let parent_bic = bic(&subset, std::slice::from_ref(center), &vec![0; members.len()]);
+                    let children = Kmeans::new(&subset, 2, iterations, nseeds).expect("two-way split of at least two rows");
+                    if bic(&subset, &children.centers, &children.clusters) > parent_bic {
+                        centers.extend(children.centers);
+                        budget -= 1;
+                        continue;
+                    }
+                }
+                centers.push(center.clone());
+            }
+            if centers.len() == k {
+                break;
+            }
+            model = Kmeans::lloyd(data, centers, iterations);
+        }
+
+        Xmeans {
+            k: model.centers.len(),
+            bic: bic(data, &model.centers, &model.clusters),
+            model,
+        }
+    }
+
+    pub fn predict(&self, data: &Array2<T>) -> Vec<usize> {
+        self.model.predict(data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score<T: Float + One + Zero> {
+    pub k: usize,
+    pub bic: T,
+    pub aic: T,
+}
+
+pub fn sweep<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, kmin: usize, kmax: usize, iterations: usize, nseeds: usize) -> Vec<Score<T>> {
+    assert!(kmin > 0 && kmin <= kmax, "kmin must be between 1 and kmax");
+    (kmin..=kmax)
+        .map(|k| {
+            let model = Kmeans::new(data, k, iterations, nseeds).expect("k-means on finite data with k at most the number of rows");
+            Score {
+                k,
+                bic: bic(data, &model.centers, &model.clusters),
+                aic: aic(data, &model.centers, &model.clusters),
+            }
+        })
+        .collect::<Vec<Score<T>>>()
+}
+
+pub fn bic<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, centers: &[Array1<T>], clusters: &[usize]) -> T {
+    let (log_likelihood, params) = log_likelihood(data, centers, clusters);
+    log_likelihood - params / T::from(2.0).expect("T::from(f64)") * T::from(data.rows()).expect("T::from(usize)").ln()
+}
+
+pub fn aic<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, centers: &[Array1<T>], clusters: &[usize]) -> T {
+    let (log_likelihood, params) = log_likelihood(data, centers, clusters);
+    log_likelihood - params
+}
+
+fn log_likelihood<T: Float + One + Zero + ScalarOperand + AddAssign + Copy + Sum>(data: &Array2<T>, centers: &[Array1<T>], clusters: &[usize]) -> (T, T) {
+    let (n, m, k) = (data.rows(), data.cols(), centers.len());
+    if n <= k {
+        return (T::neg_infinity(), T::zero());
+    }
+    let (r, dims, nclust) = (T::from(n).expect("T::from(usize)"), T::from(m).expect("T::from(usize)"), T::from(k).expect("T::from(usize)"));
+    let two = T::from(2.0).expect("T::from(f64)");
+
+    let mut sizes = vec![0; k];
+    let mut sse = T::zero();
+    for (row, cluster) in data.outer_iter().zip(clusters.iter()) {
+        let diff = &row - &centers[*cluster];
+        sse += (&diff * &diff).sum();
+        sizes[*cluster] += 1;
+    }
+    let variance = (sse / (dims * (r - nclust))).max(T::epsilon());
+
+    let log_likelihood = sizes
+        .iter()
+        .filter(|size| **size > 0)
+        .map(|size| {
+            let rj = T::from(*size).expect("T::from(usize)");
+            rj * (rj / r).ln()
+        })
+        .fold(T::zero(), |acc, x| acc + x)
+        - r * dims / two * (T::from(2.0 * PI).expect("T::from(f64)") * variance).ln()
+        - dims * (r - nclust) / two;
+    let params = (nclust - T::one()) + dims * nclust + T::one();
+    (log_likelihood, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn blobs() -> Array2<f64> {
+        let offsets = [
+            (-0.3, 0.1),
+            (0.2, -0.4),
+            (0.0, 0.0),
+            (0.4, 0.3),
+            (-0.1, -0.2),
+            (0.1, 0.5),
+            (-0.5, -0.1),
+            (0.3, -0.2),
+            (-0.2, 0.4),
+            (0.05, -0.5),
+        ];
+        let centers = [(0.0, 0.0), (10.0, 10.0), (20.0, 0.0)];
+        let flat = centers
+            .iter()
+            .flat_map(|(cx, cy)| offsets.iter().flat_map(move |(dx, dy)| vec![cx + dx, cy + dy]))
+            .collect::<Vec<f64>>();
+        Array2::from_shape_vec((30, 2), flat).unwrap()
+    }
+
+    #[test]
+    fn test_xmeans() {
+        let data = blobs();
+        let model = Xmeans::new(&data, 1, 10, 50, 10);
+        assert_eq!(model.k, 3);
+        let clustering = dbg!(model.predict(&data));
+        assert!(clustering.chunks(10).all(|chunk| chunk.iter().all_equal()));
+        assert!(clustering.iter().unique().count() == 3);
+    }
+
+    #[test]
+    fn test_bic_prefers_true_k() {
+        let data = blobs();
+        let score = |k| {
+            let model = Kmeans::new(&data, k, 50, 10).unwrap();
+            bic(&data, &model.centers, &model.clusters)
+        };
+        assert!(score(3) > score(1));
+        assert!(score(3) > score(2));
+    }
+
+    #[test]
+    fn test_sweep() {
+        let scores = sweep(&blobs(), 1, 5, 50, 10);
+        assert_eq!(scores.iter().map(|s| s.k).collect::<Vec<usize>>(), vec![1, 2, 3, 4, 5]);
+        assert!(scores.iter().all(|s| s.aic >= s.bic));
+        let best = |key: fn(&Score<f64>) -> f64| scores.iter().max_by(|a, b| key(a).partial_cmp(&key(b)).unwrap()).unwrap().k;
+        assert_eq!(best(|s| s.bic), 3);
+    }
+}